@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use eframe::epaint::Color32;
+use uuid::Uuid;
+
+use crate::gizmos::{Gizmo, GizmoResult};
+
+// Number of consecutive frames a reading has to be out of (or back within) the bounds
+// before the alarm is raised (or cleared), so that a single noisy frame does not trigger it.
+const DEBOUNCE_FRAMES: u32 = 3;
+
+#[derive(Default)]
+struct AlarmState {
+    active: bool,
+    // Consecutive frames disagreeing with `active`
+    pending_frames: u32,
+}
+
+//
+// Tracks which gizmos are past their alarm thresholds.
+//
+#[derive(Default)]
+pub struct AlarmMonitor {
+    states: HashMap<Uuid, AlarmState>,
+}
+
+impl AlarmMonitor {
+    //
+    // Updates the alarms with the results of a new frame.
+    //
+    pub fn update(&mut self, gizmos: &[Gizmo], results: &HashMap<Uuid, GizmoResult>) {
+        // Forget gizmos which were removed, disabled or had their alarms turned off
+        self.states.retain(|uuid, _| {
+            gizmos
+                .iter()
+                .any(|gizmo| gizmo.uuid == *uuid && gizmo.enabled && gizmo.has_alarm())
+        });
+
+        for gizmo in gizmos.iter().filter(|gizmo| gizmo.has_alarm()) {
+            let Some(result) = results.get(&gizmo.uuid) else {
+                continue;
+            };
+            let out_of_bounds = gizmo
+                .alarm_above
+                .is_some_and(|threshold| result.temperature > threshold)
+                || gizmo
+                    .alarm_below
+                    .is_some_and(|threshold| result.temperature < threshold);
+
+            let state = self.states.entry(gizmo.uuid).or_default();
+            if out_of_bounds == state.active {
+                state.pending_frames = 0;
+                continue;
+            }
+            state.pending_frames += 1;
+            if state.pending_frames >= DEBOUNCE_FRAMES {
+                state.active = out_of_bounds;
+                state.pending_frames = 0;
+            }
+        }
+    }
+
+    pub fn is_active(&self, uuid: Uuid) -> bool {
+        self.states.get(&uuid).is_some_and(|state| state.active)
+    }
+
+    pub fn any_active(&self) -> bool {
+        self.states.values().any(|state| state.active)
+    }
+}
+
+// Pulsing red used to highlight gizmos with an active alarm, `time` is in seconds
+pub fn alarm_pulse_color(time: f64) -> Color32 {
+    let intensity = 0.4 + 0.6 * (time * 6.0).sin().abs() as f32;
+    Color32::RED.gamma_multiply(intensity)
+}
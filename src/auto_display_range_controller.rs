@@ -1,6 +1,49 @@
 use std::time::{Duration, Instant};
 
-use crate::temperature::{Temp, TempRange};
+use eframe::egui::{Grid, Slider, Ui};
+
+use crate::temperature::{Temp, TempRange, TemperatureUnit};
+
+//
+// Tunable parameters of the auto temp range algorithm.
+//
+#[derive(Clone, Copy, PartialEq)]
+pub struct AutoRangeSettings {
+    // How long the captured range has to be clipped (or too narrow) before the range is adjusted
+    pub clipping_time_threshold: Duration,
+    pub anim_duration: Duration,
+
+    // headroom to add to captured range when setting new range
+    pub new_range_max_headroom: Temp,
+    pub new_range_min_headroom: Temp,
+
+    pub shrink_range_max_headroom: Temp,
+    pub shrink_range_min_headroom: Temp,
+
+    pub min_separation: Temp,
+}
+
+impl Default for AutoRangeSettings {
+    fn default() -> Self {
+        Self {
+            clipping_time_threshold: Duration::from_millis(900),
+            anim_duration: Duration::from_millis(500),
+
+            new_range_max_headroom: Temp::new(3.0),
+            new_range_min_headroom: Temp::new(3.0),
+
+            shrink_range_max_headroom: Temp::new(8.0),
+            shrink_range_min_headroom: Temp::new(8.0),
+            min_separation: Temp::new(30.0),
+        }
+    }
+}
+
+impl AutoRangeSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
 
 //
 // Struct holding the state of the auto temp range algorithm.
@@ -15,41 +58,26 @@ pub struct AutoDisplayRangeController {
 
     clipping_time: Duration,
 
-    // Settings
-    clipping_time_threshold: Duration,
-    anim_duration: Duration,
-
-    // headroom to add to captured range when setting new range
-    new_range_max_headroom: Temp,
-    new_range_min_headroom: Temp,
-
-    shrink_range_max_headroom: Temp,
-    shrink_range_min_headroom: Temp,
-
-    min_separation: Temp,
+    settings: AutoRangeSettings,
 }
 
 impl AutoDisplayRangeController {
-    pub fn new() -> AutoDisplayRangeController {
+    pub fn new(settings: AutoRangeSettings) -> AutoDisplayRangeController {
         AutoDisplayRangeController {
             current: TempRange::new(Temp::from_celsius(0.0), Temp::from_celsius(50.0)),
             last_compute_call: None,
             anim_target_range: None,
             anim_progress: Duration::from_secs(0),
             clipping_time: Duration::from_secs(0),
-
-            clipping_time_threshold: Duration::from_millis(900),
-            anim_duration: Duration::from_millis(500),
-
-            new_range_max_headroom: Temp::new(3.0),
-            new_range_min_headroom: Temp::new(3.0),
-
-            shrink_range_max_headroom: Temp::new(8.0),
-            shrink_range_min_headroom: Temp::new(8.0),
-            min_separation: Temp::new(30.0),
+            settings,
         }
     }
 
+    // Applied from the next compute call, the current range is kept
+    pub fn set_settings(&mut self, settings: AutoRangeSettings) {
+        self.settings = settings;
+    }
+
     pub fn compute(&mut self, captured_range: TempRange) -> TempRange {
         let now = Instant::now();
         let last_compute_call = self.last_compute_call.unwrap_or(now);
@@ -58,8 +86,8 @@ impl AutoDisplayRangeController {
 
         // if the max point or min point of the captured range is in the shrinking range, start shrinking the current range
         let shrinking_range = TempRange::new(
-            self.current.min + self.shrink_range_min_headroom,
-            self.current.max - self.shrink_range_max_headroom,
+            self.current.min + self.settings.shrink_range_min_headroom,
+            self.current.max - self.settings.shrink_range_max_headroom,
         );
 
         // check clipping
@@ -74,11 +102,11 @@ impl AutoDisplayRangeController {
             self.clipping_time = Duration::from_secs(0);
         }
 
-        if self.clipping_time > self.clipping_time_threshold {
+        if self.clipping_time > self.settings.clipping_time_threshold {
             if self.anim_target_range.is_none() {
                 let target = TempRange::new(
-                    captured_range.min - self.new_range_min_headroom,
-                    captured_range.max + self.new_range_max_headroom,
+                    captured_range.min - self.settings.new_range_min_headroom,
+                    captured_range.max + self.settings.new_range_max_headroom,
                 );
                 self.anim_target_range = Some(target);
 
@@ -90,7 +118,12 @@ impl AutoDisplayRangeController {
 
         if let Some(target_range) = self.anim_target_range {
             self.anim_progress += delta;
-            let factor = self.anim_progress.as_secs_f32() / self.anim_duration.as_secs_f32();
+            // A zero duration jumps to the target right away
+            let factor = if self.settings.anim_duration.is_zero() {
+                1.0
+            } else {
+                self.anim_progress.as_secs_f32() / self.settings.anim_duration.as_secs_f32()
+            };
             self.current = self.current.animate(target_range, factor);
             if factor >= 1.0 {
                 self.anim_target_range = None;
@@ -98,13 +131,100 @@ impl AutoDisplayRangeController {
         }
 
         // at the end apply min separation
-        if self.current.diff() < self.min_separation {
+        if self.current.diff() < self.settings.min_separation {
             TempRange::new(
                 self.current.min,
-                self.current.max + (self.min_separation - self.current.diff()),
+                self.current.max + (self.settings.min_separation - self.current.diff()),
             )
         } else {
             self.current
         }
     }
 }
+
+//
+// Sliders for each parameter of the auto range algorithm. Returns true if anything changed.
+//
+pub fn auto_range_settings_editor(ui: &mut Ui, settings: &mut AutoRangeSettings) -> bool {
+    let mut changed = false;
+    Grid::new("auto_range_settings_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (label, hover_text, duration) in [
+                (
+                    "Clipping delay",
+                    "How long the scene has to be outside of the range before it is adjusted",
+                    &mut settings.clipping_time_threshold,
+                ),
+                (
+                    "Animation duration",
+                    "Duration of the transition to the new range",
+                    &mut settings.anim_duration,
+                ),
+            ] {
+                let mut millis = duration.as_millis() as u64;
+                ui.label(label).on_hover_text(hover_text);
+                if ui
+                    .add(Slider::new(&mut millis, 0..=5000).suffix(" ms"))
+                    .changed()
+                {
+                    *duration = Duration::from_millis(millis);
+                    changed = true;
+                }
+                ui.end_row();
+            }
+
+            // Temperature differences, the same in Kelvin and Celsius
+            for (label, hover_text, range, temp) in [
+                (
+                    "Min headroom",
+                    "Added below the minimum when the range is adjusted",
+                    0.0..=20.0,
+                    &mut settings.new_range_min_headroom,
+                ),
+                (
+                    "Max headroom",
+                    "Added above the maximum when the range is adjusted",
+                    0.0..=20.0,
+                    &mut settings.new_range_max_headroom,
+                ),
+                (
+                    "Min shrink margin",
+                    "The range shrinks when the minimum is this far above its lower end",
+                    0.0..=50.0,
+                    &mut settings.shrink_range_min_headroom,
+                ),
+                (
+                    "Max shrink margin",
+                    "The range shrinks when the maximum is this far below its upper end",
+                    0.0..=50.0,
+                    &mut settings.shrink_range_max_headroom,
+                ),
+                (
+                    "Minimum span",
+                    "The range is never narrower than this",
+                    0.0..=100.0,
+                    &mut settings.min_separation,
+                ),
+            ] {
+                let mut kelvin = temp.to_unit(TemperatureUnit::Kelvin);
+                ui.label(label).on_hover_text(hover_text);
+                if ui
+                    .add(Slider::new(&mut kelvin, range).suffix(" K"))
+                    .changed()
+                {
+                    *temp = Temp::new(kelvin);
+                    changed = true;
+                }
+                ui.end_row();
+            }
+        });
+    if ui
+        .add_enabled(!settings.is_default(), eframe::egui::Button::new("Reset"))
+        .clicked()
+    {
+        *settings = AutoRangeSettings::default();
+        changed = true;
+    }
+    changed
+}
@@ -0,0 +1,33 @@
+use crate::temperature::{Temp, TempRange};
+
+// Relative margin around the threshold, so that the palette does not flicker
+// when the scene span hovers around it
+const HYSTERESIS: f32 = 0.1;
+
+//
+// Picks between a palette suited for scenes with a narrow temperature span
+// and one suited for scenes with a wide span.
+//
+pub struct AutoGradientSelector {
+    wide_span: Option<bool>,
+}
+
+impl AutoGradientSelector {
+    pub fn new() -> AutoGradientSelector {
+        AutoGradientSelector { wide_span: None }
+    }
+
+    //
+    // Returns true if the palette for wide spans should be used for the given scene.
+    //
+    pub fn is_wide_span(&mut self, captured_range: TempRange, threshold: Temp) -> bool {
+        let span = captured_range.diff();
+        let wide_span = match self.wide_span {
+            Some(true) => span > threshold * (1.0 - HYSTERESIS),
+            Some(false) => span > threshold * (1.0 + HYSTERESIS),
+            None => span > threshold,
+        };
+        self.wide_span = Some(wide_span);
+        wide_span
+    }
+}
@@ -6,7 +6,7 @@ use nokhwa::{
 
 use crate::{temperature::Temp, thermal_data::ThermalData};
 
-use super::CameraAdapter;
+use super::{read_u16_le_block, CameraAdapter};
 
 const IMAGE_WIDTH: u32 = 256;
 const IMAGE_HEIGHT: u32 = 192;
@@ -34,11 +34,15 @@ impl CameraAdapter for InfirayP2ProAdapter {
     }
 
     fn requested_format(&self) -> nokhwa::utils::RequestedFormat<'static> {
-        RequestedFormat::new::<RgbFormat>(RequestedFormatType::Closest(CameraFormat::new(
+        RequestedFormat::new::<RgbFormat>(RequestedFormatType::Closest(self.expected_format()))
+    }
+
+    fn expected_format(&self) -> CameraFormat {
+        CameraFormat::new(
             Resolution::new(IMAGE_WIDTH, IMAGE_HEIGHT * 2),
             FrameFormat::YUYV,
             25,
-        )))
+        )
     }
 
     fn temperature_range(&self) -> (f32, f32) {
@@ -52,24 +56,27 @@ impl CameraAdapter for InfirayP2ProAdapter {
 
         // crop to the bottom half of the frame, which contains the thermal data
         // We have IMAGE_WIDTH * IMAGE_HEIGHT times 2 bytes per pixel (YUYV)
-        let thermal_data_buf = &frame_data[(IMAGE_WIDTH * IMAGE_HEIGHT * 2) as usize..];
-
-        let u16_temperature_data = unsafe {
-            std::slice::from_raw_parts(thermal_data_buf.as_ptr() as *const u16, 256 * 192)
-        };
+        let u16_temperature_data = read_u16_le_block(
+            &frame_data,
+            (IMAGE_WIDTH * IMAGE_HEIGHT * 2) as usize,
+            (IMAGE_WIDTH * IMAGE_HEIGHT) as usize,
+        )?;
 
-        Ok::<ThermalData, NokhwaError>(ThermalData::new(
-            IMAGE_WIDTH as usize,
-            IMAGE_HEIGHT as usize,
-            u16_temperature_data
-                .iter()
-                .map(|&x| Temp::new(x as f32 / 64.0))
-                .collect(),
-        ))
+        Ok::<ThermalData, NokhwaError>(
+            ThermalData::new(
+                IMAGE_WIDTH as usize,
+                IMAGE_HEIGHT as usize,
+                u16_temperature_data
+                    .iter()
+                    .map(|&x| Temp::new(x as f32 / 64.0))
+                    .collect(),
+            )
+            .with_raw_counts(u16_temperature_data),
+        )
     }
 
-    fn usb_vid_pid(&self) -> (u16, u16) {
+    fn usb_vid_pids(&self) -> Vec<(u16, u16)> {
         // Bus 001 Device 061: ID 0bda:5830 Realtek Semiconductor Corp. USB Camera
-        (0x0bda, 0x5830)
+        vec![(0x0bda, 0x5830)]
     }
 }
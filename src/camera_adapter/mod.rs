@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use nokhwa::{utils::RequestedFormat, Camera, NokhwaError};
+use nokhwa::{
+    utils::{CameraFormat, RequestedFormat},
+    Camera, NokhwaError,
+};
 use once_cell::sync::Lazy;
 
 use crate::thermal_data::ThermalData;
@@ -21,20 +24,26 @@ pub trait CameraAdapter: Send + Sync {
     fn short_name(&self) -> String;
 
     ///
-    /// Get the USB PID/VID of the camera to match against
+    /// Get the USB VID/PID pairs of the camera to match against,
+    /// rebranded models of the same camera may use different ones
     ///
-    fn usb_vid_pid(&self) -> (u16, u16);
+    fn usb_vid_pids(&self) -> Vec<(u16, u16)>;
 
     ///
     /// Get requested format for the camera
     ///
     fn requested_format(&self) -> RequestedFormat<'static>;
 
+    ///
+    /// Get the stream format the adapter expects the camera to negotiate.
+    /// Used to detect mismatches between the opened stream and the adapter.
+    ///
+    fn expected_format(&self) -> CameraFormat;
+
     ///
     /// Get the advertised temperature range of the camera
-    /// (min, max)
+    /// (min, max) in Kelvin
     ///
-    #[allow(dead_code)]
     fn temperature_range(&self) -> (f32, f32);
 
     ///
@@ -42,3 +51,67 @@ pub trait CameraAdapter: Send + Sync {
     ///
     fn capture_thermal_data(&self, cam: &mut Camera) -> Result<ThermalData, NokhwaError>;
 }
+
+// Whether any of the adapters handles the USB device with the given VID/PID
+pub fn is_supported_usb_device(vendor_id: u16, product_id: u16) -> bool {
+    CAMERA_ADAPTERS
+        .iter()
+        .any(|adapter| adapter.usb_vid_pids().contains(&(vendor_id, product_id)))
+}
+
+//
+// Reads `count` little endian uint16 values starting at `offset` bytes into a raw frame.
+// Returns an error instead of reading out of bounds when the frame is truncated.
+//
+pub fn read_u16_le_block(
+    frame_data: &[u8],
+    offset: usize,
+    count: usize,
+) -> Result<Vec<u16>, NokhwaError> {
+    let too_short = || {
+        NokhwaError::ReadFrameError(format!(
+            "Frame too short: got {} bytes, expected {} values at offset {}",
+            frame_data.len(),
+            count,
+            offset
+        ))
+    };
+    let end = count
+        .checked_mul(2)
+        .and_then(|len| len.checked_add(offset))
+        .ok_or_else(too_short)?;
+    let block = frame_data.get(offset..end).ok_or_else(too_short)?;
+    // The frame is not necessarily aligned to 2 bytes
+    Ok(block
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_u16_le_block_reads_unaligned_values() {
+        let frame_data = [0xff, 0x34, 0x12, 0xcd, 0xab];
+        assert_eq!(
+            read_u16_le_block(&frame_data, 1, 2).unwrap(),
+            [0x1234, 0xabcd]
+        );
+        assert!(read_u16_le_block(&frame_data, 5, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_u16_le_block_rejects_short_frames() {
+        // Odd length, one byte short of the last value
+        assert!(read_u16_le_block(&[0x34, 0x12, 0xcd], 0, 2).is_err());
+        // Even length, but the block starts at an odd offset
+        assert!(read_u16_le_block(&[0x00, 0x34, 0x12, 0xcd], 1, 2).is_err());
+        assert!(read_u16_le_block(&[], 0, 1).is_err());
+        assert!(read_u16_le_block(&[0x34, 0x12], 3, 0).is_err());
+        // Sizes which overflow
+        assert!(read_u16_le_block(&[0x34, 0x12], 0, usize::MAX).is_err());
+        assert!(read_u16_le_block(&[0x34, 0x12], usize::MAX, 1).is_err());
+    }
+}
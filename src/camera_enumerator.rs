@@ -68,6 +68,14 @@ impl EnumeratedCamera {
 
         job
     }
+
+    // Identifies the camera in the user preferences: the USB VID/PID if known, the name otherwise
+    pub fn preference_key(&self) -> String {
+        match self.usb_vid_pid {
+            Some((vid, pid)) => format!("USB {:04x}:{:04x}", vid, pid),
+            None => self.info.human_name(),
+        }
+    }
 }
 
 pub fn enumerate_cameras() -> Result<Vec<EnumeratedCamera>, anyhow::Error> {
@@ -85,8 +93,8 @@ pub fn enumerate_cameras() -> Result<Vec<EnumeratedCamera>, anyhow::Error> {
                 let adapter = crate::camera_adapter::CAMERA_ADAPTERS
                     .iter()
                     .find(|adapter| {
-                        if let Some((vid, pid)) = usb_vid_pid {
-                            adapter.usb_vid_pid() == (vid, pid)
+                        if let Some(vid_pid) = usb_vid_pid {
+                            adapter.usb_vid_pids().contains(&vid_pid)
                         } else {
                             false
                         }
@@ -0,0 +1,92 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+// How often the directory listing is compared with the previous one
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Size and modification time of every file in the directory, used to detect changes
+type DirectorySnapshot = HashMap<PathBuf, (u64, Option<SystemTime>)>;
+
+//
+// Watches the captures directory for files added, removed or modified outside of the application.
+// A change is reported only once the directory stops changing between two polls,
+// so a file which is still being written (e.g. a video) is reported once, after it is finished.
+//
+pub struct CapturesWatcher {
+    pub receiver: mpsc::Receiver<()>,
+    directory: PathBuf,
+    stop: Arc<AtomicBool>,
+}
+
+impl CapturesWatcher {
+    pub fn new<F: Fn() + Send + 'static>(directory: PathBuf, callback: F) -> CapturesWatcher {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_stop = stop.clone();
+        let thread_directory = directory.clone();
+        thread::spawn(move || {
+            let mut last_snapshot = snapshot_directory(&thread_directory);
+            let mut pending_change = false;
+            while !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+                let snapshot = snapshot_directory(&thread_directory);
+                if snapshot != last_snapshot {
+                    last_snapshot = snapshot;
+                    pending_change = true;
+                } else if pending_change {
+                    pending_change = false;
+                    if sender.send(()).is_err() {
+                        break;
+                    }
+                    callback();
+                }
+            }
+        });
+
+        CapturesWatcher {
+            receiver,
+            directory,
+            stop,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // Returns true if the directory has changed since the last call, coalescing all pending events
+    pub fn take_change(&self) -> bool {
+        self.receiver.try_iter().count() > 0
+    }
+}
+
+impl Drop for CapturesWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn snapshot_directory(directory: &Path) -> DirectorySnapshot {
+    let Ok(read_dir) = directory.read_dir() else {
+        return DirectorySnapshot::new();
+    };
+    read_dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            Some((entry.path(), (metadata.len(), metadata.modified().ok())))
+        })
+        .collect()
+}
@@ -1,17 +1,51 @@
 use std::{
     cell::RefCell,
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
     rc::Rc,
     time::{Duration, Instant},
 };
 
-use eframe::{egui, emath::Vec2b, epaint::Vec2};
-use egui_plot::{Line, Plot, PlotPoints};
+use anyhow::Result;
+use chrono::{DateTime, Local};
 
-use crate::{pane_dispatcher::Pane, AppGlobalState};
+use eframe::{
+    egui,
+    emath::Vec2b,
+    epaint::{Color32, Vec2},
+};
+use egui_plot::{Line, LineStyle, Plot, PlotPoints};
+use uuid::Uuid;
+
+use crate::{
+    history_data_collector::{FrameStatsPoint, HistoryDataCollector},
+    pane_dispatcher::Pane,
+    temperature::{format_temperature_value, Temp, TemperatureUnit},
+    util::unique_path,
+    AppGlobalState,
+};
+
+// Overlays of the whole frame statistics: name, color and the plotted value
+const FRAME_STATS_LINES: [(&str, Color32, fn(&FrameStatsPoint) -> Temp); 3] = [
+    ("Frame min", Color32::from_rgb(72, 219, 251), |point| {
+        point.min
+    }),
+    ("Frame max", Color32::from_rgb(255, 99, 72), |point| {
+        point.max
+    }),
+    ("Frame avg", Color32::from_rgb(254, 202, 87), |point| {
+        point.avg
+    }),
+];
 
 pub struct ChartPane {
     global_state: Rc<RefCell<AppGlobalState>>,
     display_duration: Duration,
+    // Which of FRAME_STATS_LINES are shown
+    show_frame_stats: [bool; 3],
+    // Outcome of the last CSV export, shown in the menu bar
+    last_export_message: Option<String>,
 }
 
 impl ChartPane {
@@ -24,6 +58,8 @@ impl ChartPane {
         ChartPane {
             global_state,
             display_duration: Self::POSSIBLE_DURATIONS[2],
+            show_frame_stats: [false; 3],
+            last_export_message: None,
         }
     }
 
@@ -41,6 +77,29 @@ impl ChartPane {
     }
 }
 
+fn export_history_csv(
+    history: &HistoryDataCollector,
+    gizmos: &[(Uuid, String)],
+    destination_folder: PathBuf,
+    unit: TemperatureUnit,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(&destination_folder)?;
+    let current_local: DateTime<Local> = Local::now();
+    let path = unique_path(
+        &destination_folder,
+        &format!("history_{}", current_local.format("%Y-%m-%d_%H-%M-%S")),
+        "csv",
+    );
+    history.write_csv(
+        BufWriter::new(File::create(&path)?),
+        gizmos,
+        unit,
+        Instant::now(),
+        current_local,
+    )?;
+    Ok(path)
+}
+
 impl Pane for ChartPane {
     fn title(&self) -> egui::WidgetText {
         "Chart".into()
@@ -52,7 +111,53 @@ impl Pane for ChartPane {
 
         let unit_suffix = global_state.preferred_temperature_unit().suffix();
         let unit_suffix_clone = unit_suffix.clone(); // TODO: fixme
+        let decimals = global_state.temperature_decimals();
         egui::menu::bar(ui, |ui| {
+            for ((name, ..), show) in FRAME_STATS_LINES.iter().zip(&mut self.show_frame_stats) {
+                ui.toggle_value(show, *name);
+            }
+            if ui
+                .button("Export CSV")
+                .on_hover_text("Saves the history of all measurements to the captures directory")
+                .clicked()
+            {
+                let gizmos: Vec<(Uuid, String)> = global_state
+                    .thermal_capturer_settings
+                    .gizmo
+                    .children()
+                    .unwrap()
+                    .iter()
+                    .filter(|gizmo| {
+                        global_state
+                            .history_data_collector
+                            .stored_data
+                            .contains_key(&gizmo.uuid)
+                    })
+                    .map(|gizmo| (gizmo.uuid, gizmo.name.clone()))
+                    .collect();
+                let captures_dir = global_state
+                    .prefs
+                    .as_ref()
+                    .map(|prefs| prefs.captures_directory.clone())
+                    .unwrap_or("./".to_string());
+                self.last_export_message = Some(
+                    match export_history_csv(
+                        &global_state.history_data_collector,
+                        &gizmos,
+                        PathBuf::from(captures_dir),
+                        global_state.preferred_temperature_unit(),
+                    ) {
+                        Ok(path) => format!("Saved to {}", path.to_string_lossy()),
+                        Err(err) => {
+                            log::error!("Failed to export history: {}", err);
+                            format!("Export failed: {}", err)
+                        }
+                    },
+                );
+            }
+            if let Some(message) = self.last_export_message.as_ref() {
+                ui.weak(message);
+            }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
                 Self::POSSIBLE_DURATIONS.iter().for_each(|&duration| {
                     if ui
@@ -82,16 +187,53 @@ impl Pane for ChartPane {
                 global_state.preferred_temperature_unit().suffix()
             ))
             .y_axis_formatter(move |grid_mark, _range| {
-                format!("{:.0} {}", grid_mark.value, unit_suffix)
+                format!(
+                    "{} {}",
+                    format_temperature_value(grid_mark.value, decimals),
+                    unit_suffix
+                )
             })
             .x_axis_formatter(move |grid_mark, _range| {
                 let dur = Duration::from_secs_f64(grid_mark.value.abs());
                 ChartPane::duration_to_string(dur)
             })
             .label_formatter(move |lbl: &str, p| {
-                format!("{:.0} {} {}", p.y, unit_suffix_clone, lbl)
+                format!(
+                    "{} {} {}",
+                    format_temperature_value(p.y, decimals),
+                    unit_suffix_clone,
+                    lbl
+                )
             })
             .show(ui, |plot_ui| {
+                let unit = global_state.preferred_temperature_unit();
+                let now = global_state
+                    .last_thermal_capturer_result
+                    .as_ref()
+                    .map(|cr| cr.capture_time)
+                    .unwrap_or(Instant::now());
+                for ((name, color, value), _) in FRAME_STATS_LINES
+                    .iter()
+                    .zip(self.show_frame_stats)
+                    .filter(|(_, show)| *show)
+                {
+                    let mut points = vec![];
+                    global_state
+                        .history_data_collector
+                        .for_each_frame_stats_point(now - self.display_duration, now, |point| {
+                            points.push([
+                                -(now - point.time).as_secs_f64(),
+                                value(point).to_unit(unit) as f64,
+                            ]);
+                        });
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(points))
+                            .color(*color)
+                            .style(LineStyle::dashed_dense())
+                            .name(*name),
+                    );
+                }
+
                 let gizmos = global_state
                     .thermal_capturer_settings
                     .gizmo
@@ -99,33 +241,36 @@ impl Pane for ChartPane {
                     .unwrap()
                     .clone(); // todo: remove clone
 
-                gizmos.iter().for_each(|gizmo| {
-                    let now = global_state
-                        .last_thermal_capturer_result
-                        .as_ref()
-                        .map(|cr| cr.capture_time)
-                        .unwrap_or(Instant::now());
-                    let start_of_range = now - self.display_duration;
-                    let mut points = vec![];
-                    global_state.history_data_collector.for_each_data_point(
-                        gizmo.uuid,
-                        start_of_range,
-                        now,
-                        |data_point| {
-                            points.push([
-                                -(now - data_point.time).as_secs_f64(),
-                                data_point
-                                    .temperature
-                                    .to_unit(global_state.preferred_temperature_unit())
-                                    as f64,
-                            ]);
-                        },
-                    );
-                    let line = Line::new(PlotPoints::new(points))
-                        .color(gizmo.color)
-                        .name(gizmo.name.clone());
-                    plot_ui.line(line);
-                })
+                gizmos
+                    .iter()
+                    .filter(|gizmo| gizmo.enabled)
+                    .for_each(|gizmo| {
+                        let now = global_state
+                            .last_thermal_capturer_result
+                            .as_ref()
+                            .map(|cr| cr.capture_time)
+                            .unwrap_or(Instant::now());
+                        let start_of_range = now - self.display_duration;
+                        let mut points = vec![];
+                        global_state.history_data_collector.for_each_data_point(
+                            gizmo.uuid,
+                            start_of_range,
+                            now,
+                            |data_point| {
+                                points.push([
+                                    -(now - data_point.time).as_secs_f64(),
+                                    data_point
+                                        .temperature
+                                        .to_unit(global_state.preferred_temperature_unit())
+                                        as f64,
+                                ]);
+                            },
+                        );
+                        let line = Line::new(PlotPoints::new(points))
+                            .color(gizmo.color)
+                            .name(gizmo.name.clone());
+                        plot_ui.line(line);
+                    })
             });
 
         if plot_ret.response.hovered() {
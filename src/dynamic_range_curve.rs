@@ -229,6 +229,7 @@ pub fn dynamic_curve_editor(
 
     let curve = &mut settings.dynamic_range_curve;
     let gradient = &settings.gradient;
+    let invert_gradient = settings.invert_gradient;
 
     ui.with_layout(
         Layout::right_to_left(Align::Min).with_cross_justify(false),
@@ -279,6 +280,7 @@ pub fn dynamic_curve_editor(
             // generate refgerence gradient texture if needed
             let mut hasher = DefaultHasher::new();
             gradient.hash(&mut hasher);
+            invert_gradient.hash(&mut hasher);
             let gradient_hash: u64 = hasher.finish();
             if state.last_gradient_hash != gradient_hash {
                 state.last_gradient_hash = gradient_hash;
@@ -286,7 +288,12 @@ pub fn dynamic_curve_editor(
                     "curve_editor_ref_gradient",
                     rotate_image(
                         gradient.create_demo_image(128, 2),
-                        ImageRotation::Clockwise90,
+                        // Rotating the other way flips the gradient
+                        if invert_gradient {
+                            ImageRotation::Clockwise270
+                        } else {
+                            ImageRotation::Clockwise90
+                        },
                     ),
                     TextureOptions {
                         ..Default::default()
@@ -0,0 +1,100 @@
+use std::hash::Hash;
+
+use eframe::egui::{ComboBox, Ui};
+
+//
+// Emissivity of common materials, offered for the emissivity of the scene and of rect measurements.
+// Values are typical for the 8-14 µm band of uncooled thermal cameras.
+//
+pub struct EmissivityPreset {
+    pub name: &'static str,
+    pub emissivity: f32,
+}
+
+pub static EMISSIVITY_PRESETS: [EmissivityPreset; 11] = [
+    EmissivityPreset {
+        name: "Human skin",
+        emissivity: 0.98,
+    },
+    EmissivityPreset {
+        name: "Water",
+        emissivity: 0.96,
+    },
+    EmissivityPreset {
+        name: "Matte black paint",
+        emissivity: 0.97,
+    },
+    EmissivityPreset {
+        name: "Electrical tape",
+        emissivity: 0.95,
+    },
+    EmissivityPreset {
+        name: "Wood",
+        emissivity: 0.94,
+    },
+    EmissivityPreset {
+        name: "Brick",
+        emissivity: 0.93,
+    },
+    EmissivityPreset {
+        name: "Concrete",
+        emissivity: 0.92,
+    },
+    EmissivityPreset {
+        name: "PCB (FR-4)",
+        emissivity: 0.91,
+    },
+    EmissivityPreset {
+        name: "Oxidized steel",
+        emissivity: 0.88,
+    },
+    EmissivityPreset {
+        name: "Polished copper",
+        emissivity: 0.05,
+    },
+    EmissivityPreset {
+        name: "Aluminum foil",
+        emissivity: 0.03,
+    },
+];
+
+// The preset with the given emissivity, if any (the values are unique)
+pub fn preset_for(emissivity: f32) -> Option<&'static EmissivityPreset> {
+    EMISSIVITY_PRESETS
+        .iter()
+        .find(|preset| (preset.emissivity - emissivity).abs() < 0.001)
+}
+
+//
+// Combo box of the presets, "Custom" sets `is_custom` so that the caller reveals the numeric field.
+// Returns true if the emissivity has been changed.
+//
+pub fn emissivity_preset_combo(
+    ui: &mut Ui,
+    id_source: impl Hash,
+    emissivity: &mut f32,
+    is_custom: &mut bool,
+) -> bool {
+    let selected = preset_for(*emissivity).filter(|_| !*is_custom);
+    let mut changed = false;
+    ComboBox::from_id_source(id_source)
+        .selected_text(selected.map_or("Custom", |preset| preset.name))
+        .width(110.0)
+        .show_ui(ui, |ui| {
+            for preset in EMISSIVITY_PRESETS.iter() {
+                let label = format!("{} ({:.2})", preset.name, preset.emissivity);
+                if ui
+                    .selectable_label(selected.is_some_and(|s| std::ptr::eq(s, preset)), label)
+                    .clicked()
+                {
+                    *emissivity = preset.emissivity;
+                    *is_custom = false;
+                    changed = true;
+                }
+            }
+            if ui.selectable_label(selected.is_none(), "Custom").clicked() {
+                *is_custom = true;
+            }
+        });
+    changed
+}
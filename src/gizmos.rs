@@ -1,23 +1,111 @@
 use eframe::epaint::{Color32, Hsva};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{temperature::Temp, thermal_data::ThermalDataPos};
+use crate::{
+    temperature::Temp,
+    thermal_data::{ThermalDataPos, ThermalDataRect},
+    types::image_rotation::ImageRotation,
+};
 
-#[derive(Clone)]
+// Uuid of the spot measurement kept at the center of the image (show_center_spot preference)
+pub const CENTER_SPOT_UUID: Uuid = Uuid::from_u128(0x6c1f_3a52_8e0d_4b7a_9f21_c0e5_d34a_7b18);
+// Uuids of the Max and Min gizmos, fixed so that deltas saved in presets still find them after a restart
+pub const MAX_TEMP_UUID: Uuid = Uuid::from_u128(0x0b9e_51d4_2c7f_4e83_a6d0_93f1_5e2c_8a47);
+pub const MIN_TEMP_UUID: Uuid = Uuid::from_u128(0xd47a_0e6c_91b3_4f25_8c1e_27a9_f06b_3d52);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum GizmoKind {
-    Root { children: Vec<Gizmo> },
+    Root {
+        children: Vec<Gizmo>,
+    },
     MaxTemp,
     MinTemp,
-    TempAt { pos: ThermalDataPos },
+    // Average of the pixels within `radius` of `pos`, a radius of 0 reads a single pixel
+    TempAt {
+        pos: ThermalDataPos,
+        radius: usize,
+    },
+    // Sum of the temperature above ambient over a region, a proxy for the relative thermal load
+    IntegratedAboveAmbient {
+        region: ThermalDataRect,
+    },
+    // Temperature profile along a segment
+    Line {
+        start: ThermalDataPos,
+        end: ThermalDataPos,
+    },
+    // Minimum, maximum and average over a rectangle, both corners are inclusive
+    Rect {
+        top_left: ThermalDataPos,
+        bottom_right: ThermalDataPos,
+        // Overrides the emissivity of the scene, for surfaces of a different material
+        emissivity: Option<f32>,
+    },
+    // Difference between the temperatures of two other gizmos (a - b)
+    Delta {
+        a: Uuid,
+        b: Uuid,
+    },
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Gizmo {
     pub uuid: Uuid,
     pub kind: GizmoKind,
     pub name: String,
     pub color: Color32,
     pub show_temperature_label: bool,
+    // Disabled gizmos are kept, but not computed, drawn or recorded
+    pub enabled: bool,
+    // Locked gizmos can not be moved on the image
+    pub locked: bool,
+    // Alarm thresholds, the alarm is raised when the temperature is above / below them
+    pub alarm_above: Option<Temp>,
+    pub alarm_below: Option<Temp>,
+}
+
+//
+// Named set of user measurements, stored in the user preferences.
+// Positions are in image pixels, so a preset only fits the resolution and rotation it was saved with.
+//
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeasurementPreset {
+    pub name: String,
+    pub gizmos: Vec<Gizmo>,
+}
+
+impl MeasurementPreset {
+    // Captures the user gizmos of the root gizmo
+    pub fn from_root(name: String, root: &Gizmo) -> Self {
+        Self {
+            name,
+            gizmos: root
+                .children()
+                .map(|children| {
+                    children
+                        .iter()
+                        .filter(|gizmo| !gizmo.is_builtin())
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    // Replaces the user gizmos of the root gizmo with the preset, keeping the built-in ones.
+    // The uuids are kept, so that deltas keep referencing the right gizmos.
+    pub fn apply_to(&self, root: &mut Gizmo) {
+        let Some(children) = root.children_mut() else {
+            return;
+        };
+        children.retain(|gizmo| gizmo.is_builtin());
+        for gizmo in self.gizmos.iter() {
+            if !children.iter().any(|child| child.uuid == gizmo.uuid) {
+                children.push(gizmo.clone());
+            }
+        }
+    }
 }
 
 impl Gizmo {
@@ -28,6 +116,10 @@ impl Gizmo {
             name,
             color,
             show_temperature_label: true,
+            enabled: true,
+            locked: false,
+            alarm_above: None,
+            alarm_below: None,
         }
     }
     pub fn new_root(children: Vec<Gizmo>) -> Self {
@@ -37,6 +129,27 @@ impl Gizmo {
             name: "Root".to_string(),
             color: Color32::WHITE,
             show_temperature_label: true,
+            enabled: true,
+            locked: false,
+            alarm_above: None,
+            alarm_below: None,
+        }
+    }
+
+    // Max, Min and the center spot are managed by the application rather than added by the user
+    pub fn is_builtin(&self) -> bool {
+        matches!(self.kind, GizmoKind::MaxTemp | GizmoKind::MinTemp)
+            || self.uuid == CENTER_SPOT_UUID
+    }
+
+    pub fn has_alarm(&self) -> bool {
+        self.alarm_above.is_some() || self.alarm_below.is_some()
+    }
+
+    pub fn children(&self) -> Option<&Vec<Gizmo>> {
+        match &self.kind {
+            GizmoKind::Root { children } => Some(children),
+            _ => None,
         }
     }
 
@@ -61,10 +174,320 @@ impl Gizmo {
             _ => panic!("Cannot push child to non-root gizmo"),
         }
     }
+
+    //
+    // Moves the point gizmos so that they stay on the same spot of the imaged object
+    // when the image rotation changes from `from` to `to`.
+    //
+    // `size` is the (width, height) of the image rotated by `from`.
+    //
+    pub fn rotate_positions(
+        &mut self,
+        from: ImageRotation,
+        to: ImageRotation,
+        size: (usize, usize),
+    ) {
+        let Some(children) = self.children_mut() else {
+            return;
+        };
+        let (mut width, mut height) = size;
+        for _ in 0..from.clockwise_steps_to(to) {
+            for child in children.iter_mut() {
+                match &mut child.kind {
+                    GizmoKind::TempAt { pos, .. } => {
+                        *pos = pos.rotated(ImageRotation::Clockwise90, width, height);
+                    }
+                    GizmoKind::IntegratedAboveAmbient { region } => {
+                        *region = region.rotated(ImageRotation::Clockwise90, width, height);
+                    }
+                    GizmoKind::Line { start, end } => {
+                        *start = start.rotated(ImageRotation::Clockwise90, width, height);
+                        *end = end.rotated(ImageRotation::Clockwise90, width, height);
+                    }
+                    GizmoKind::Rect {
+                        top_left,
+                        bottom_right,
+                        ..
+                    } => {
+                        // The rotated corners are no longer top left and bottom right
+                        let rect = ThermalDataRect::from_corners(*top_left, *bottom_right).rotated(
+                            ImageRotation::Clockwise90,
+                            width,
+                            height,
+                        );
+                        *top_left = rect.min;
+                        *bottom_right = rect.max;
+                    }
+                    _ => {}
+                }
+            }
+            (width, height) = (height, width);
+        }
+    }
+
+    //
+    // Moves the point gizmos so that they stay on the same spot of the imaged object
+    // when the image is mirrored. `size` is the (width, height) of the image.
+    //
+    pub fn flip_positions(&mut self, horizontal: bool, vertical: bool, size: (usize, usize)) {
+        let Some(children) = self.children_mut() else {
+            return;
+        };
+        let (width, height) = size;
+        let flip = |pos: ThermalDataPos| pos.flipped(horizontal, vertical, width, height);
+        for child in children.iter_mut() {
+            match &mut child.kind {
+                GizmoKind::TempAt { pos, .. } => {
+                    *pos = flip(*pos);
+                }
+                GizmoKind::IntegratedAboveAmbient { region } => {
+                    *region = ThermalDataRect::from_corners(flip(region.min), flip(region.max));
+                }
+                GizmoKind::Line { start, end } => {
+                    *start = flip(*start);
+                    *end = flip(*end);
+                }
+                GizmoKind::Rect {
+                    top_left,
+                    bottom_right,
+                    ..
+                } => {
+                    let rect = ThermalDataRect::from_corners(flip(*top_left), flip(*bottom_right));
+                    *top_left = rect.min;
+                    *bottom_right = rect.max;
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct GizmoResult {
     pub temperature: Temp,
     pub pos: ThermalDataPos,
+    // Sum of (temperature - ambient) over the region, in K·px (IntegratedAboveAmbient only)
+    pub integrated_above_ambient: Option<f32>,
+    // The temperature was outside of the camera's valid range and has been clamped
+    pub clamped: bool,
+    // Temperatures sampled along the segment, from start to end (Line only)
+    pub line_samples: Vec<Temp>,
+    // Statistics over the rectangle (Rect only)
+    pub min: Temp,
+    pub max: Temp,
+    pub avg: Temp,
+    // Difference between the referenced gizmos, in Kelvin (Delta only)
+    pub delta: Option<Temp>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thermal_data::ThermalData;
+
+    const ROTATIONS: [ImageRotation; 4] = [
+        ImageRotation::None,
+        ImageRotation::Clockwise90,
+        ImageRotation::Clockwise180,
+        ImageRotation::Clockwise270,
+    ];
+
+    // Non-square frame, so that swapped axes are caught, with a distinct value in every pixel
+    fn test_data() -> ThermalData {
+        ThermalData::new(3, 2, (0..6).map(|i| Temp::new(i as f32)).collect())
+    }
+
+    fn value_at(data: &ThermalData, pos: ThermalDataPos) -> Temp {
+        data.data[pos.y * data.width + pos.x]
+    }
+
+    // Root with a spot in each corner, a line across the diagonal and a rect covering the right column
+    fn test_root() -> Gizmo {
+        let mut children = vec![(0, 0), (2, 0), (0, 1), (2, 1)]
+            .into_iter()
+            .map(|(x, y)| {
+                Gizmo::new(
+                    GizmoKind::TempAt {
+                        pos: ThermalDataPos::new(x, y),
+                        radius: 0,
+                    },
+                    format!("{},{}", x, y),
+                    Color32::RED,
+                )
+            })
+            .collect::<Vec<_>>();
+        children.push(Gizmo::new(
+            GizmoKind::Line {
+                start: ThermalDataPos::new(0, 0),
+                end: ThermalDataPos::new(2, 1),
+            },
+            "Line".to_string(),
+            Color32::GREEN,
+        ));
+        children.push(Gizmo::new(
+            GizmoKind::Rect {
+                top_left: ThermalDataPos::new(2, 0),
+                bottom_right: ThermalDataPos::new(2, 1),
+                emissivity: None,
+            },
+            "Rect".to_string(),
+            Color32::BLUE,
+        ));
+        Gizmo::new_root(children)
+    }
+
+    // Positions of the point gizmos in order, the rect as its two corners
+    fn points(root: &Gizmo) -> Vec<ThermalDataPos> {
+        root.children()
+            .unwrap()
+            .iter()
+            .flat_map(|gizmo| match gizmo.kind {
+                GizmoKind::TempAt { pos, .. } => vec![pos],
+                GizmoKind::Line { start, end } => vec![start, end],
+                GizmoKind::Rect {
+                    top_left,
+                    bottom_right,
+                    ..
+                } => vec![top_left, bottom_right],
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rotated_points_stay_on_the_same_pixel() {
+        let data = test_data();
+        let original = points(&test_root());
+        for rotation in ROTATIONS {
+            let rotated_data = data.rotated(rotation);
+            let mut root = test_root();
+            root.rotate_positions(ImageRotation::None, rotation, (3, 2));
+            let rotated = points(&root);
+            // Spots and line ends keep reading the same pixel
+            for (pos, rotated_pos) in original.iter().zip(rotated.iter()).take(6) {
+                assert_eq!(
+                    value_at(&data, *pos),
+                    value_at(&rotated_data, *rotated_pos),
+                    "{:?}",
+                    rotation
+                );
+            }
+            // The rect still covers the right column, with its corners reordered
+            let (top_left, bottom_right) = (rotated[6], rotated[7]);
+            assert!(top_left.x <= bottom_right.x && top_left.y <= bottom_right.y);
+            let mut covered = vec![];
+            for y in top_left.y..=bottom_right.y {
+                for x in top_left.x..=bottom_right.x {
+                    covered.push(value_at(&rotated_data, ThermalDataPos::new(x, y)));
+                }
+            }
+            covered.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(
+                covered,
+                vec![Temp::new(2.0), Temp::new(5.0)],
+                "{:?}",
+                rotation
+            );
+        }
+    }
+
+    #[test]
+    fn rotation_round_trips() {
+        let original = points(&test_root());
+        for from in ROTATIONS {
+            for to in ROTATIONS {
+                let from_size = if from.swaps_axes() { (2, 3) } else { (3, 2) };
+                let to_size = if to.swaps_axes() { (2, 3) } else { (3, 2) };
+                let mut root = test_root();
+                root.rotate_positions(ImageRotation::None, from, (3, 2));
+                root.rotate_positions(from, to, from_size);
+                root.rotate_positions(to, ImageRotation::None, to_size);
+                assert_eq!(points(&root), original, "{:?} -> {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_points_stay_on_the_same_pixel() {
+        let data = test_data();
+        let original = points(&test_root());
+        for (horizontal, vertical) in [(true, false), (false, true), (true, true)] {
+            let flipped_data = data.flipped(horizontal, vertical);
+            let mut root = test_root();
+            root.flip_positions(horizontal, vertical, (3, 2));
+            let flipped = points(&root);
+            for (pos, flipped_pos) in original.iter().zip(flipped.iter()).take(6) {
+                assert_eq!(value_at(&data, *pos), value_at(&flipped_data, *flipped_pos));
+            }
+            let (top_left, bottom_right) = (flipped[6], flipped[7]);
+            assert!(top_left.x <= bottom_right.x && top_left.y <= bottom_right.y);
+
+            // Flipping again gives back the original positions
+            root.flip_positions(horizontal, vertical, (3, 2));
+            assert_eq!(points(&root), original);
+        }
+    }
+
+    // Root as created on startup, with the built-in Max and Min gizmos
+    fn startup_root() -> Gizmo {
+        Gizmo::new_root(vec![
+            Gizmo {
+                uuid: MAX_TEMP_UUID,
+                ..Gizmo::new(GizmoKind::MaxTemp, "Max".to_string(), Color32::RED)
+            },
+            Gizmo {
+                uuid: MIN_TEMP_UUID,
+                ..Gizmo::new(GizmoKind::MinTemp, "Min".to_string(), Color32::BLUE)
+            },
+        ])
+    }
+
+    #[test]
+    fn preset_deltas_on_max_survive_a_restart() {
+        let mut root = startup_root();
+        root.push_child(
+            GizmoKind::TempAt {
+                pos: ThermalDataPos::new(1, 1),
+                radius: 0,
+            },
+            "Spot".to_string(),
+        );
+        let spot_uuid = root.children().unwrap().last().unwrap().uuid;
+        root.push_child(
+            GizmoKind::Delta {
+                a: MAX_TEMP_UUID,
+                b: spot_uuid,
+            },
+            "Max - Spot".to_string(),
+        );
+
+        // Presets are stored in the preferences file
+        let preset = MeasurementPreset::from_root("Preset".to_string(), &root);
+        let preset: MeasurementPreset =
+            serde_json::from_str(&serde_json::to_string(&preset).unwrap()).unwrap();
+        assert_eq!(preset.gizmos.len(), 2);
+
+        let mut restarted_root = startup_root();
+        preset.apply_to(&mut restarted_root);
+        let children = restarted_root.children().unwrap();
+        assert_eq!(children.len(), 4);
+        let (a, b) = children
+            .iter()
+            .find_map(|gizmo| match gizmo.kind {
+                GizmoKind::Delta { a, b } => Some((a, b)),
+                _ => None,
+            })
+            .unwrap();
+        let kind_of = |uuid: Uuid| {
+            children
+                .iter()
+                .find(|gizmo| gizmo.uuid == uuid)
+                .map(|gizmo| gizmo.kind.clone())
+        };
+        assert!(matches!(kind_of(a), Some(GizmoKind::MaxTemp)));
+        assert!(matches!(
+            kind_of(b),
+            Some(GizmoKind::TempAt { pos, .. }) if pos == ThermalDataPos::new(1, 1)
+        ));
+    }
 }
@@ -0,0 +1,155 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use eframe::{
+    egui::{self, DragValue, Grid, Image, ImageButton, TextEdit, Ui},
+    epaint::{TextureHandle, Vec2},
+};
+use uuid::Uuid;
+
+use crate::thermal_gradient::{ThermalGradient, ThermalGradientPoint};
+
+pub enum GradientEditorAction {
+    None,
+    Save(ThermalGradient),
+    Cancel,
+}
+
+//
+// Edits the points of a single gradient, with a live preview.
+//
+pub struct GradientEditorView {
+    gradient: ThermalGradient,
+    preview_texture: Option<TextureHandle>,
+    // Hash of the gradient the preview was created for
+    preview_hash: u64,
+    error: Option<String>,
+}
+
+impl GradientEditorView {
+    pub fn new(gradient: ThermalGradient) -> Self {
+        Self {
+            gradient,
+            preview_texture: None,
+            preview_hash: 0,
+            error: None,
+        }
+    }
+
+    pub fn new_gradient() -> Self {
+        Self::new(ThermalGradient::new(
+            Uuid::new_v4(),
+            "Custom gradient".to_string(),
+            vec![
+                ThermalGradientPoint::from_rgbv(0, 0, 0, 0.0),
+                ThermalGradientPoint::from_rgbv(255, 255, 255, 1.0),
+            ],
+        ))
+    }
+
+    pub fn draw(&mut self, ui: &mut Ui) -> GradientEditorAction {
+        let mut action = GradientEditorAction::None;
+
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.add(TextEdit::singleline(&mut self.gradient.name).desired_width(150.0));
+        });
+
+        // The points are kept in the edited order, the preview and the saved gradient are sorted
+        let sorted = ThermalGradient::new(
+            self.gradient.uuid,
+            self.gradient.name.clone(),
+            self.gradient.points.clone(),
+        );
+        let mut hasher = DefaultHasher::new();
+        sorted.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.preview_texture.is_none() || self.preview_hash != hash {
+            self.preview_texture = Some(ui.ctx().load_texture(
+                format!("gradient_editor_{}", self.gradient.uuid),
+                sorted.create_demo_image(256, 32),
+                Default::default(),
+            ));
+            self.preview_hash = hash;
+        }
+        if let Some(texture) = self.preview_texture.as_ref() {
+            ui.add(Image::new(texture).fit_to_exact_size(Vec2::new(256.0, 32.0)));
+        }
+
+        let mut point_to_remove = None;
+        Grid::new("gradient_editor_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, point) in self.gradient.points.iter_mut().enumerate() {
+                    ui.color_edit_button_srgba(&mut point.color);
+                    ui.add(
+                        DragValue::new(&mut point.pos)
+                            .range(0.0..=1.0)
+                            .speed(0.01)
+                            .max_decimals(3),
+                    );
+                    if ui
+                        .add(
+                            ImageButton::new(
+                                Image::new(egui::include_image!("./icons/trash.svg"))
+                                    .tint(ui.style().visuals.widgets.active.fg_stroke.color),
+                            )
+                            .frame(false),
+                        )
+                        .clicked()
+                    {
+                        point_to_remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = point_to_remove {
+            self.gradient.points.remove(i);
+        }
+
+        if ui.button("Add point").clicked() {
+            // Split the widest gap between the points
+            let mut positions: Vec<f32> = sorted.points.iter().map(|p| p.pos).collect();
+            positions.insert(0, 0.0);
+            positions.push(1.0);
+            let pos = positions
+                .windows(2)
+                .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+                .map(|gap| (gap[0] + gap[1]) / 2.0)
+                .unwrap_or(0.5);
+            self.gradient
+                .points
+                .push(ThermalGradientPoint::new(sorted.get_color(pos), pos));
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                if self.gradient.points.len() < 2 {
+                    self.error = Some("A gradient needs at least two points".to_string());
+                } else {
+                    let name = self.gradient.name.trim();
+                    action = GradientEditorAction::Save(ThermalGradient::new(
+                        self.gradient.uuid,
+                        if name.is_empty() {
+                            "Custom gradient".to_string()
+                        } else {
+                            name.to_string()
+                        },
+                        self.gradient.points.clone(),
+                    ));
+                }
+            }
+            if ui.button("Cancel").clicked() {
+                action = GradientEditorAction::Cancel;
+            }
+        });
+        if let Some(error) = self.error.as_ref() {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        action
+    }
+}
@@ -1,34 +1,51 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
 use eframe::{
     egui::{self, Grid, Response, Ui},
     epaint::{TextureHandle, Vec2},
 };
 
-use crate::thermal_gradient::{ThermalGradient, THERMAL_GRADIENTS};
+use crate::thermal_gradient::ThermalGradient;
 
 pub struct GradientSelectorView {
     preview_textures: Vec<TextureHandle>,
+    // Hash of the gradients the previews were created for
+    previews_hash: u64,
 }
 
 impl GradientSelectorView {
     pub fn new() -> Self {
         Self {
             preview_textures: vec![],
+            previews_hash: 0,
         }
     }
 
-    pub fn draw(&mut self, ui: &mut Ui, selected_gradient: &mut ThermalGradient) -> Response {
-        if self.preview_textures.len() != THERMAL_GRADIENTS.len() {
-            self.preview_textures = THERMAL_GRADIENTS
+    pub fn draw(
+        &mut self,
+        ui: &mut Ui,
+        selected_gradient: &mut ThermalGradient,
+        gradients: &[ThermalGradient],
+    ) -> Response {
+        let mut hasher = DefaultHasher::new();
+        gradients.hash(&mut hasher);
+        let gradients_hash = hasher.finish();
+        if self.preview_textures.len() != gradients.len() || self.previews_hash != gradients_hash {
+            self.preview_textures = gradients
                 .iter()
                 .map(|gradient| {
                     let gradient_image = gradient.create_demo_image(256, 32);
                     ui.ctx().load_texture(
-                        format!("gradient_{}", gradient.name),
+                        format!("gradient_{}", gradient.uuid),
                         gradient_image,
                         Default::default(),
                     )
                 })
                 .collect();
+            self.previews_hash = gradients_hash;
         }
 
         let mut selected_gradient_uuid = selected_gradient.uuid;
@@ -43,37 +60,35 @@ impl GradientSelectorView {
                     .striped(true)
                     .max_col_width(200.0)
                     .show(ui, |ui| {
-                        THERMAL_GRADIENTS
-                            .iter()
-                            .enumerate()
-                            .for_each(|(i, gradient)| {
-                                ui.radio_value(
-                                    &mut selected_gradient_uuid,
-                                    gradient.uuid,
-                                    gradient.name.clone(),
-                                );
-                                if ui
-                                    .add(
-                                        egui::Image::new(&self.preview_textures[i])
-                                            .fit_to_fraction(Vec2::new(1.0, 1.0)),
-                                    )
-                                    .clicked()
-                                {
-                                    selected_gradient_uuid = gradient.uuid;
-                                }
-                                ui.end_row();
-                            });
+                        gradients.iter().enumerate().for_each(|(i, gradient)| {
+                            ui.radio_value(
+                                &mut selected_gradient_uuid,
+                                gradient.uuid,
+                                gradient.name.clone(),
+                            );
+                            if ui
+                                .add(
+                                    egui::Image::new(&self.preview_textures[i])
+                                        .fit_to_fraction(Vec2::new(1.0, 1.0)),
+                                )
+                                .clicked()
+                            {
+                                selected_gradient_uuid = gradient.uuid;
+                            }
+                            ui.end_row();
+                        });
                     });
             })
             .response;
 
-        *selected_gradient = THERMAL_GRADIENTS
-            .iter()
-            .find(|gradient| gradient.uuid == selected_gradient_uuid)
-            .unwrap()
-            .clone();
         if prev_selected_gradient_uuid != selected_gradient_uuid {
-            resp.mark_changed();
+            if let Some(gradient) = gradients
+                .iter()
+                .find(|gradient| gradient.uuid == selected_gradient_uuid)
+            {
+                *selected_gradient = gradient.clone();
+                resp.mark_changed();
+            }
         }
 
         resp
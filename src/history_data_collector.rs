@@ -1,34 +1,100 @@
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, Write},
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Local};
 use uuid::Uuid;
 
-use crate::{gizmos::GizmoResult, temperature::Temp};
+use crate::{
+    gizmos::GizmoResult,
+    temperature::{Temp, TempRange, TemperatureUnit},
+};
 
 pub struct DataPoint {
     pub temperature: Temp,
     pub time: Instant,
 }
 
+// Statistics of a whole frame
+pub struct FrameStatsPoint {
+    pub min: Temp,
+    pub max: Temp,
+    pub avg: Temp,
+    pub time: Instant,
+}
+
 pub struct HistoryDataCollector {
     //
     // Stores data points for each gizmo UUID
     //
     pub stored_data: HashMap<Uuid, Vec<DataPoint>>,
+    pub frame_stats: Vec<FrameStatsPoint>,
 }
 
 impl HistoryDataCollector {
     pub fn new() -> Self {
         Self {
             stored_data: HashMap::new(),
+            frame_stats: vec![],
+        }
+    }
+
+    // Drops the data points captured before `cutoff`
+    fn prune(&mut self, cutoff: Instant) {
+        // Data points are stored in the order they were captured
+        for data_points in self.stored_data.values_mut() {
+            let expired = data_points.partition_point(|data_point| data_point.time < cutoff);
+            data_points.drain(..expired);
         }
+        // Forget the gizmos with no recent data, e.g. removed ones
+        self.stored_data
+            .retain(|_, data_points| !data_points.is_empty());
+        let expired = self
+            .frame_stats
+            .partition_point(|point| point.time < cutoff);
+        self.frame_stats.drain(..expired);
     }
 
+    pub fn add_frame_stats(&mut self, time: Instant, captured_range: TempRange, avg: Temp) {
+        self.frame_stats.push(FrameStatsPoint {
+            min: captured_range.min,
+            max: captured_range.max,
+            avg,
+            time,
+        });
+    }
+
+    pub fn for_each_frame_stats_point<F>(&self, from: Instant, to: Instant, f: F)
+    where
+        F: FnMut(&FrameStatsPoint),
+    {
+        let start = self.frame_stats.partition_point(|point| point.time < from);
+        self.frame_stats[start..]
+            .iter()
+            .take_while(|point| point.time <= to)
+            .for_each(f);
+    }
+
+    //
+    // Stores the results of a frame and drops the data points older than `retention`.
+    //
     pub fn add_from_gizmo_results(
         &mut self,
         time: Instant,
         gizmo_results: &HashMap<Uuid, GizmoResult>,
+        retention: Duration,
     ) -> Result<(), anyhow::Error> {
-        for (gizmo_uuid, gizmo_result) in gizmo_results {
+        if let Some(cutoff) = time.checked_sub(retention) {
+            self.prune(cutoff);
+        }
+
+        // Deltas are differences, not comparable with the temperatures
+        for (gizmo_uuid, gizmo_result) in gizmo_results
+            .iter()
+            .filter(|(_, result)| result.delta.is_none())
+        {
             let data_point = DataPoint {
                 temperature: gizmo_result.temperature,
                 time,
@@ -41,17 +107,372 @@ impl HistoryDataCollector {
         Ok(())
     }
 
-    pub fn for_each_data_point<F>(&self, gizmo_uuid: Uuid, from: Instant, to: Instant, mut f: F)
+    pub fn for_each_data_point<F>(&self, gizmo_uuid: Uuid, from: Instant, to: Instant, f: F)
     where
         F: FnMut(&DataPoint),
     {
-        // TODO: binary search
-        self.stored_data.get(&gizmo_uuid).inspect(|data_points| {
-            for data_point in data_points.iter() {
-                if data_point.time >= from && data_point.time <= to {
-                    f(data_point);
+        let Some(data_points) = self.stored_data.get(&gizmo_uuid) else {
+            return;
+        };
+        // Data points are stored in the order they were captured
+        let start = data_points.partition_point(|data_point| data_point.time < from);
+        data_points[start..]
+            .iter()
+            .take_while(|data_point| data_point.time <= to)
+            .for_each(f);
+    }
+
+    // Capture time of the oldest stored data point of a gizmo
+    pub fn oldest_time(&self, gizmo_uuid: Uuid) -> Option<Instant> {
+        self.stored_data
+            .get(&gizmo_uuid)
+            .and_then(|data_points| data_points.first())
+            .map(|data_point| data_point.time)
+    }
+
+    //
+    // Returns the average temperature of a gizmo over the given time span,
+    // along with the number of samples that were averaged.
+    //
+    pub fn average_temperature(
+        &self,
+        gizmo_uuid: Uuid,
+        from: Instant,
+        to: Instant,
+    ) -> Option<(Temp, usize)> {
+        let mut sum = Temp::new(0.0);
+        let mut count = 0;
+        self.for_each_data_point(gizmo_uuid, from, to, |data_point| {
+            sum = sum + data_point.temperature;
+            count += 1;
+        });
+        if count == 0 {
+            return None;
+        }
+        Some((sum / count as f32, count))
+    }
+
+    //
+    // Returns the rate of change of the temperature of a gizmo over the given time span
+    // in Kelvin per second (least squares fit), None if there are fewer than `min_samples`.
+    //
+    pub fn temperature_slope(
+        &self,
+        gizmo_uuid: Uuid,
+        from: Instant,
+        to: Instant,
+        min_samples: usize,
+    ) -> Option<f32> {
+        let mut samples = Vec::new();
+        self.for_each_data_point(gizmo_uuid, from, to, |data_point| {
+            samples.push((
+                data_point
+                    .time
+                    .saturating_duration_since(from)
+                    .as_secs_f32(),
+                data_point.temperature.to_unit(TemperatureUnit::Kelvin),
+            ));
+        });
+        if samples.len() < min_samples.max(2) {
+            return None;
+        }
+        let count = samples.len() as f32;
+        let mean_t = samples.iter().map(|(t, _)| t).sum::<f32>() / count;
+        let mean_temp = samples.iter().map(|(_, temp)| temp).sum::<f32>() / count;
+        let (covariance, variance) =
+            samples
+                .iter()
+                .fold((0.0, 0.0), |(covariance, variance), (t, temp)| {
+                    (
+                        covariance + (t - mean_t) * (temp - mean_temp),
+                        variance + (t - mean_t) * (t - mean_t),
+                    )
+                });
+        // All samples at the same time
+        if variance <= f32::EPSILON {
+            return None;
+        }
+        Some(covariance / variance)
+    }
+
+    //
+    // Writes the stored data points of the given gizmos (uuid, name) as CSV,
+    // one row per capture time with an empty cell where a gizmo has no sample.
+    // Capture times are converted to the local time using `now` and `now_local`,
+    // taken at the same moment.
+    //
+    pub fn write_csv<W: Write>(
+        &self,
+        mut writer: W,
+        gizmos: &[(Uuid, String)],
+        unit: TemperatureUnit,
+        now: Instant,
+        now_local: DateTime<Local>,
+    ) -> io::Result<()> {
+        let series: Vec<HashMap<Instant, Temp>> = gizmos
+            .iter()
+            .map(|(uuid, _)| {
+                self.stored_data
+                    .get(uuid)
+                    .map(|data_points| {
+                        data_points
+                            .iter()
+                            .map(|data_point| (data_point.time, data_point.temperature))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            })
+            .collect();
+        // Results of the same frame share the capture time
+        let times: BTreeSet<Instant> = series.iter().flat_map(|s| s.keys().copied()).collect();
+
+        write!(writer, "Time")?;
+        for (_, name) in gizmos {
+            write!(
+                writer,
+                ",\"{} ({})\"",
+                name.replace('"', "\"\""),
+                unit.suffix_ascii()
+            )?;
+        }
+        writeln!(writer)?;
+        for time in times {
+            let local_time = now_local
+                - chrono::Duration::from_std(now.saturating_duration_since(time))
+                    .unwrap_or_default();
+            write!(writer, "{}", local_time.format("%Y-%m-%d %H:%M:%S%.3f"))?;
+            for samples in series.iter() {
+                match samples.get(&time) {
+                    Some(temperature) => write!(writer, ",{:.2}", temperature.to_unit(unit))?,
+                    None => write!(writer, ",")?,
                 }
             }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Collector with one sample per second for `count` seconds after `start`, warming by 1 K per second
+    fn test_collector(gizmo_uuid: Uuid, start: Instant, count: u64) -> HistoryDataCollector {
+        let mut collector = HistoryDataCollector::new();
+        for i in 0..count {
+            let time = start + Duration::from_secs(i);
+            let temperature = Temp::new(300.0 + i as f32);
+            collector
+                .stored_data
+                .entry(gizmo_uuid)
+                .or_default()
+                .push(DataPoint { temperature, time });
+            collector.add_frame_stats(time, TempRange::new(temperature, temperature), temperature);
+        }
+        collector
+    }
+
+    fn collect_times(
+        collector: &HistoryDataCollector,
+        gizmo_uuid: Uuid,
+        start: Instant,
+        from: Instant,
+        to: Instant,
+    ) -> Vec<u64> {
+        let mut times = vec![];
+        collector.for_each_data_point(gizmo_uuid, from, to, |data_point| {
+            times.push((data_point.time - start).as_secs())
         });
+        times
+    }
+
+    #[test]
+    fn for_each_data_point_includes_the_boundaries() {
+        let uuid = Uuid::new_v4();
+        let start = Instant::now();
+        let collector = test_collector(uuid, start, 10);
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(
+            collect_times(&collector, uuid, start, at(2), at(5)),
+            [2, 3, 4, 5]
+        );
+        // Bounds between samples
+        assert_eq!(
+            collect_times(
+                &collector,
+                uuid,
+                start,
+                at(2) + Duration::from_millis(500),
+                at(5) - Duration::from_millis(500)
+            ),
+            [3, 4]
+        );
+        assert_eq!(collect_times(&collector, uuid, start, at(0), at(0)), [0]);
+        assert_eq!(collect_times(&collector, uuid, start, at(9), at(20)), [9]);
+        assert!(collect_times(&collector, uuid, start, at(10), at(20)).is_empty());
+        assert!(collect_times(&collector, uuid, start, at(5), at(2)).is_empty());
+        // Unknown gizmo
+        assert!(collect_times(&collector, Uuid::new_v4(), start, at(0), at(9)).is_empty());
+    }
+
+    #[test]
+    fn for_each_data_point_on_empty_history() {
+        let uuid = Uuid::new_v4();
+        let start = Instant::now();
+        let mut collector = HistoryDataCollector::new();
+        collector.stored_data.insert(uuid, vec![]);
+        assert!(collect_times(
+            &collector,
+            uuid,
+            start,
+            start,
+            start + Duration::from_secs(1)
+        )
+        .is_empty());
+        let mut count = 0;
+        collector.for_each_frame_stats_point(start, start + Duration::from_secs(1), |_| count += 1);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn prune_keeps_samples_at_the_cutoff() {
+        let uuid = Uuid::new_v4();
+        let start = Instant::now();
+        let mut collector = test_collector(uuid, start, 10);
+        let end = start + Duration::from_secs(100);
+
+        collector.prune(start + Duration::from_secs(4));
+        assert_eq!(
+            collect_times(&collector, uuid, start, start, end),
+            [4, 5, 6, 7, 8, 9]
+        );
+        assert_eq!(collector.frame_stats.len(), 6);
+
+        assert_eq!(
+            collector.oldest_time(uuid),
+            Some(start + Duration::from_secs(4))
+        );
+
+        // Cutoff between two samples
+        collector.prune(start + Duration::from_millis(6500));
+        assert_eq!(
+            collect_times(&collector, uuid, start, start, end),
+            [7, 8, 9]
+        );
+        assert_eq!(collector.frame_stats.len(), 3);
+
+        // Cutoff before all samples
+        collector.prune(start);
+        assert_eq!(
+            collect_times(&collector, uuid, start, start, end),
+            [7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn prune_everything() {
+        let uuid = Uuid::new_v4();
+        let start = Instant::now();
+        let mut collector = test_collector(uuid, start, 10);
+
+        collector.prune(start + Duration::from_secs(10));
+        // Gizmos without samples are forgotten
+        assert!(collector.stored_data.is_empty());
+        assert!(collector.frame_stats.is_empty());
+
+        assert_eq!(collector.oldest_time(uuid), None);
+
+        // Pruning an empty history is a no-op
+        collector.prune(start + Duration::from_secs(20));
+        assert!(collector.stored_data.is_empty());
+        assert!(collector.frame_stats.is_empty());
+    }
+
+    #[test]
+    fn temperature_slope_of_a_linear_series() {
+        let uuid = Uuid::new_v4();
+        let start = Instant::now();
+        let collector = test_collector(uuid, start, 10);
+        let end = start + Duration::from_secs(9);
+
+        let slope = collector.temperature_slope(uuid, start, end, 2).unwrap();
+        assert!((slope - 1.0).abs() < 1e-4, "{}", slope);
+        // Only part of the series
+        let slope = collector
+            .temperature_slope(uuid, start + Duration::from_secs(5), end, 2)
+            .unwrap();
+        assert!((slope - 1.0).abs() < 1e-4, "{}", slope);
+
+        assert_eq!(collector.temperature_slope(uuid, start, end, 11), None);
+        // A single sample has no slope
+        assert_eq!(collector.temperature_slope(uuid, start, start, 0), None);
+    }
+
+    #[test]
+    fn average_temperature_of_a_single_point() {
+        let uuid = Uuid::new_v4();
+        let start = Instant::now();
+        let collector = test_collector(uuid, start, 10);
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let (average, count) = collector.average_temperature(uuid, at(3), at(3)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(average, Temp::new(303.0));
+
+        let (average, count) = collector.average_temperature(uuid, at(0), at(9)).unwrap();
+        assert_eq!(count, 10);
+        assert!((average.to_unit(TemperatureUnit::Kelvin) - 304.5).abs() < 1e-3);
+
+        assert!(collector
+            .average_temperature(uuid, at(10), at(20))
+            .is_none());
+    }
+
+    #[test]
+    fn write_csv_header_and_units() {
+        let uuid_a = Uuid::new_v4();
+        let uuid_b = Uuid::new_v4();
+        let start = Instant::now();
+        let mut collector = test_collector(uuid_a, start, 2);
+        // Second gizmo only present in the last frame
+        collector.stored_data.insert(
+            uuid_b,
+            vec![DataPoint {
+                temperature: Temp::from_celsius(100.0),
+                time: start + Duration::from_secs(1),
+            }],
+        );
+        let gizmos = [
+            (uuid_a, "Spot".to_string()),
+            (uuid_b, "Quoted \"name\"".to_string()),
+        ];
+        let now = start + Duration::from_secs(1);
+        let now_local = Local::now();
+
+        let mut csv = vec![];
+        collector
+            .write_csv(
+                &mut csv,
+                &gizmos,
+                TemperatureUnit::Fahrenheit,
+                now,
+                now_local,
+            )
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "Time,\"Spot (F)\",\"Quoted \"\"name\"\" (F)\"");
+        let first: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(first[1..], ["80.33", ""]);
+        let last: Vec<&str> = lines[2].split(',').collect();
+        assert_eq!(
+            last[0],
+            now_local.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+        );
+        assert_eq!(last[1..], ["82.13", "212.00"]);
     }
 }
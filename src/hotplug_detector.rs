@@ -38,6 +38,22 @@ impl fmt::Display for HotplugEvent {
     }
 }
 
+impl HotplugEvent {
+    // USB (vendor_id, product_id) of the device which arrived or left
+    pub fn vid_pid(&self) -> (u16, u16) {
+        match *self {
+            HotplugEvent::DeviceArrived {
+                vendor_id,
+                product_id,
+            }
+            | HotplugEvent::DeviceLeft {
+                vendor_id,
+                product_id,
+            } => (vendor_id, product_id),
+        }
+    }
+}
+
 type HotplugtEventCallback = dyn Fn(HotplugEvent) + Send;
 
 pub struct HotplugDetector {
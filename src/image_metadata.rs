@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+
+//
+// Embeds textual metadata into already encoded JPEG and PNG files,
+// so that snapshots keep describing how they were captured.
+//
+
+// TIFF field types
+const TIFF_ASCII: u16 = 2;
+const TIFF_LONG: u16 = 4;
+const TIFF_UNDEFINED: u16 = 7;
+
+// TIFF / EXIF tags
+const TAG_MODEL: u16 = 0x0110;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_USER_COMMENT: u16 = 0x9286;
+
+pub struct ImageMetadata {
+    // Name of the camera the image was captured with
+    pub camera: String,
+    // Capture time, formatted as "YYYY:MM:DD HH:MM:SS" as required by EXIF
+    pub date_time: String,
+    // Free-form description of the capture, one "Key: value" per line
+    pub comment: String,
+}
+
+//
+// Inserts an APP1 EXIF segment with the camera model, capture time and
+// the comment (as UserComment) into a JPEG file.
+//
+pub fn embed_jpeg_metadata(jpeg: &[u8], metadata: &ImageMetadata) -> Result<Vec<u8>> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return Err(anyhow!("Not a JPEG file"));
+    }
+
+    let mut user_comment = b"ASCII\0\0\0".to_vec();
+    user_comment.extend(to_ascii(&metadata.comment).bytes());
+    let exif_entries = [
+        IfdEntry::ascii(TAG_DATE_TIME_ORIGINAL, &metadata.date_time),
+        IfdEntry::undefined(TAG_USER_COMMENT, user_comment),
+    ];
+    let mut ifd0_entries = vec![
+        IfdEntry::ascii(TAG_MODEL, &metadata.camera),
+        IfdEntry::ascii(TAG_DATE_TIME, &metadata.date_time),
+    ];
+    // The Exif IFD directly follows IFD0, which starts right after the 8 byte TIFF header
+    let exif_ifd_offset = 8 + ifd_size(&ifd0_entries) + 12;
+    ifd0_entries.push(IfdEntry::long(TAG_EXIF_IFD, exif_ifd_offset as u32));
+
+    // Little endian TIFF header, IFD0 at offset 8
+    let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+    write_ifd(&mut tiff, &ifd0_entries);
+    write_ifd(&mut tiff, &exif_entries);
+
+    let mut payload = b"Exif\0\0".to_vec();
+    payload.extend(tiff);
+    let segment_length = u16::try_from(payload.len() + 2)
+        .map_err(|_| anyhow!("The metadata does not fit in a JPEG segment"))?;
+
+    // Keep the JFIF segment first, if present
+    let mut insert_at = 2;
+    if jpeg.len() >= 6 && jpeg[2..4] == [0xFF, 0xE0] {
+        insert_at += 2 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+    }
+    if insert_at > jpeg.len() {
+        return Err(anyhow!("Truncated JPEG file"));
+    }
+
+    let mut out = Vec::with_capacity(jpeg.len() + payload.len() + 4);
+    out.extend(&jpeg[..insert_at]);
+    out.extend([0xFF, 0xE1]);
+    out.extend(segment_length.to_be_bytes());
+    out.extend(payload);
+    out.extend(&jpeg[insert_at..]);
+    Ok(out)
+}
+
+//
+// Inserts tEXt chunks with the camera (Source), capture time (Creation Time)
+// and the comment (Comment) right after the IHDR chunk of a PNG file.
+//
+pub fn embed_png_metadata(png: &[u8], metadata: &ImageMetadata) -> Result<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    // Signature, then the IHDR chunk: length, type, 13 bytes of data and the CRC
+    const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+    if !png.starts_with(SIGNATURE) || png.len() < IHDR_END || &png[12..16] != b"IHDR" {
+        return Err(anyhow!("Not a PNG file"));
+    }
+
+    let mut out = Vec::with_capacity(png.len() + metadata.comment.len() + 128);
+    out.extend(&png[..IHDR_END]);
+    for (keyword, text) in [
+        ("Source", metadata.camera.as_str()),
+        ("Creation Time", metadata.date_time.as_str()),
+        ("Comment", metadata.comment.as_str()),
+    ] {
+        let mut chunk = b"tEXt".to_vec();
+        chunk.extend(keyword.bytes());
+        chunk.push(0);
+        chunk.extend(to_ascii(text).bytes());
+        out.extend(((chunk.len() - 4) as u32).to_be_bytes());
+        out.extend(&chunk);
+        out.extend(crc32(&chunk).to_be_bytes());
+    }
+    out.extend(&png[IHDR_END..]);
+    Ok(out)
+}
+
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value: Vec<u8>,
+}
+
+impl IfdEntry {
+    fn ascii(tag: u16, text: &str) -> Self {
+        let mut value = to_ascii(text).into_bytes();
+        value.push(0);
+        Self {
+            tag,
+            field_type: TIFF_ASCII,
+            count: value.len() as u32,
+            value,
+        }
+    }
+
+    fn long(tag: u16, value: u32) -> Self {
+        Self {
+            tag,
+            field_type: TIFF_LONG,
+            count: 1,
+            value: value.to_le_bytes().to_vec(),
+        }
+    }
+
+    fn undefined(tag: u16, value: Vec<u8>) -> Self {
+        Self {
+            tag,
+            field_type: TIFF_UNDEFINED,
+            count: value.len() as u32,
+            value,
+        }
+    }
+
+    // Values longer than 4 bytes are stored after the IFD, padded to an even length
+    fn out_of_line_size(&self) -> usize {
+        if self.value.len() > 4 {
+            self.value.len() + self.value.len() % 2
+        } else {
+            0
+        }
+    }
+}
+
+// Size of an IFD with no next IFD, including the values stored after it
+fn ifd_size(entries: &[IfdEntry]) -> usize {
+    2 + entries.len() * 12
+        + 4
+        + entries
+            .iter()
+            .map(IfdEntry::out_of_line_size)
+            .sum::<usize>()
+}
+
+//
+// Appends an IFD and its values to `tiff`, which has to start with the TIFF header,
+// as the offsets are relative to it. Entries have to be sorted by tag.
+//
+fn write_ifd(tiff: &mut Vec<u8>, entries: &[IfdEntry]) {
+    let mut data_offset = tiff.len() + 2 + entries.len() * 12 + 4;
+    let mut data = vec![];
+    tiff.extend((entries.len() as u16).to_le_bytes());
+    for entry in entries {
+        tiff.extend(entry.tag.to_le_bytes());
+        tiff.extend(entry.field_type.to_le_bytes());
+        tiff.extend(entry.count.to_le_bytes());
+        if entry.value.len() > 4 {
+            tiff.extend((data_offset as u32).to_le_bytes());
+            data.extend(&entry.value);
+            data.resize(data.len() + entry.value.len() % 2, 0);
+            data_offset += entry.out_of_line_size();
+        } else {
+            let mut value = entry.value.clone();
+            value.resize(4, 0);
+            tiff.extend(value);
+        }
+    }
+    // No next IFD
+    tiff.extend(0u32.to_le_bytes());
+    tiff.extend(data);
+}
+
+// Both EXIF ASCII fields and PNG tEXt chunks are limited to (a superset of) ASCII
+fn to_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .collect()
+}
+
+// CRC-32 as used by PNG chunks
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
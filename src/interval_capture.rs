@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+//
+// Schedules snapshots taken at a fixed interval, optionally stopping after a number of shots.
+//
+pub struct IntervalCapture {
+    pub interval: Duration,
+    // None means unlimited
+    pub max_shots: Option<u32>,
+    pub shots_taken: u32,
+    next_shot: Instant,
+}
+
+impl IntervalCapture {
+    // The first shot is taken immediately
+    pub fn new(interval: Duration, max_shots: Option<u32>) -> Self {
+        Self {
+            interval,
+            max_shots,
+            shots_taken: 0,
+            next_shot: Instant::now(),
+        }
+    }
+
+    //
+    // Returns true if a snapshot should be taken now, and schedules the next one.
+    //
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.is_finished() || now < self.next_shot {
+            return false;
+        }
+        self.shots_taken += 1;
+        // Schedule relative to the planned time, so that the interval does not drift
+        self.next_shot += self.interval;
+        if self.next_shot < now {
+            self.next_shot = now + self.interval;
+        }
+        true
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.max_shots
+            .is_some_and(|max_shots| self.shots_taken >= max_shots)
+    }
+
+    pub fn time_to_next_shot(&self, now: Instant) -> Duration {
+        self.next_shot.saturating_duration_since(now)
+    }
+}
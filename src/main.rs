@@ -1,13 +1,29 @@
 #![deny(elided_lifetimes_in_paths)]
 
-use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::SystemTime};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::VecDeque,
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
 
+use alarm_monitor::AlarmMonitor;
+use anyhow::{anyhow, Result};
+use auto_display_range_controller::AutoRangeSettings;
+use auto_gradient_selector::AutoGradientSelector;
+use captures_watcher::CapturesWatcher;
 use chart_pane::ChartPane;
+use chrono::Local;
 use dynamic_range_curve::DynamicRangeCurve;
 use egui_dock::{DockArea, DockState, NodeIndex};
-use gizmos::{Gizmo, GizmoKind};
+use gizmos::{Gizmo, GizmoKind, CENTER_SPOT_UUID, MAX_TEMP_UUID, MIN_TEMP_UUID};
 use history_data_collector::HistoryDataCollector;
 use hotplug_detector::{run_hotplug_detector, HotplugDetector};
+use image::DynamicImage;
+use interval_capture::IntervalCapture;
 use log::error;
 
 use nokhwa::native_api_backend;
@@ -22,35 +38,66 @@ use panes::{
     capture_pane::CapturePane,
     gallery_pane::{GalleryElement, GalleryPane},
     histogram_pane::HistogramPane,
+    line_profile_pane::LineProfilePane,
     measurements_pane::MeasurementsPane,
     performance_stats_pane::PerformanceStatsPane,
     setup_pane::SetupPane,
     thermal_display_pane::ThermalDisplayPane,
     user_preferences_pane::UserPreferencesPane,
 };
-use recorders::recorder::RecorderState;
+use recorders::{
+    burst_image_recorder::BurstImageRecorder,
+    csv_recorder::CsvRecorder,
+    filename_template::{
+        FilenameTemplate, DEFAULT_SNAPSHOT_FILENAME_TEMPLATE, DEFAULT_VIDEO_FILENAME_TEMPLATE,
+    },
+    gif_recorder::GifRecorder,
+    image_recorder::{snapshot_image, CaptureOverlays, GizmoOverlay, ImageRecorder},
+    raw_recorder::RawRecorder,
+    recorder::{Recorder, RecorderState},
+    video_recorder::{Timelapse, VideoRecorder},
+};
+use strum::IntoEnumIterator;
 use temperature::{Temp, TempRange, TemperatureUnit};
-use thermal_capturer::{ThermalCapturer, ThermalCapturerResult, ThermalCapturerSettings};
-use types::image_rotation::ImageRotation;
+use thermal_capturer::{
+    AmbientSource, ThermalCapturer, ThermalCapturerResult, ThermalCapturerSettings,
+};
+use thermal_data::ThermalDataPos;
+use thermal_gradient::{all_gradients, ThermalGradient};
+use types::{
+    image_rotation::ImageRotation,
+    media_formats::{is_media_file, CsvDelimiter, ImageFormat, VideoEncodeSettings, VideoFormat},
+};
 use user_preferences::UserPreferences;
 use video_thumbnail_loader::VideoThumbnailLoader;
 
+mod alarm_monitor;
 mod auto_display_range_controller;
+mod auto_gradient_selector;
 mod camera_adapter;
 mod camera_enumerator;
+mod captures_watcher;
 mod chart_pane;
 mod dynamic_range_curve;
+mod emissivity_presets;
 mod gizmos;
+mod gradient_editor_widget;
 mod gradient_selector_widget;
 mod history_data_collector;
 mod hotplug_detector;
+mod image_metadata;
+mod interval_capture;
+#[cfg(feature = "osc")]
+mod osc_publisher;
 mod pane_dispatcher;
 mod panes;
 mod recorders;
+mod report_exporter;
 mod temperature;
 mod temperature_edit_field;
 mod thermal_capturer;
 mod thermal_data;
+mod thermal_data_source;
 mod thermal_gradient;
 mod types;
 mod user_preferences;
@@ -60,6 +107,9 @@ mod widgets;
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+
+    // The app state is not created yet, so the preferences needed at startup are read directly here
+    let startup_prefs = UserPreferences::load().unwrap_or_default();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([900.0, 600.0])
@@ -68,40 +118,89 @@ fn main() -> Result<(), eframe::Error> {
                     .unwrap(),
             ),
         renderer: eframe::Renderer::Wgpu,
+        // eframe restores the previous window geometry, keeping it on one of the connected monitors
+        persist_window: startup_prefs.remember_window_geometry,
 
         ..Default::default()
     };
     eframe::run_native(
         "Thermal Cat",
         options,
-        Box::new(|cc| {
+        Box::new(move |cc| {
             // This gives us image support:
             egui_extras::install_image_loaders(&cc.egui_ctx);
 
             cc.egui_ctx
-                .add_image_loader(std::sync::Arc::new(VideoThumbnailLoader::default()));
+                .add_image_loader(std::sync::Arc::new(VideoThumbnailLoader::new(
+                    startup_prefs.video_thumbnail_workers,
+                )));
 
             Ok(Box::<ThermalViewerApp>::default())
         }),
     )
 }
 
+// Frames kept for stepping while frozen, a few seconds of a typical camera (tens of MB)
+const MAX_BUFFERED_FRAMES: usize = 60;
+
 pub struct AppGlobalState {
     did_try_open_camera_at_startup: bool,
     should_try_open_camera_on_next_hotplug: bool,
+    // Camera the primary capturer streams from (EnumeratedCamera::preference_key), None for replays
+    opened_camera: Option<String>,
+    // Camera lost while streaming, reopened as soon as it is plugged back in
+    disconnected_camera: Option<String>,
 
     thermal_capturer_inst: Option<ThermalCapturer>,
     thermal_capturer_settings: ThermalCapturerSettings,
     last_thermal_capturer_result: Option<Box<ThermalCapturerResult>>,
+    // Holds the last result on screen, new frames are still received, recorded and monitored
+    // (history, alarms, OSC, frame buffer) but not shown
+    frozen: bool,
+    // Most recent results, stepped through while frozen
+    frame_buffer: VecDeque<Box<ThermalCapturerResult>>,
+    // Position of the shown frame in frame_buffer while frozen, None is the newest one
+    frame_buffer_pos: Option<usize>,
+
+    // Second camera shown as a picture-in-picture inset for comparison.
+    // It follows the display settings of the primary one, without gizmos and recorders.
+    secondary_capturer_inst: Option<ThermalCapturer>,
+    last_secondary_capturer_result: Option<Box<ThermalCapturerResult>>,
 
     hotplug_detector: Option<HotplugDetector>,
     history_data_collector: HistoryDataCollector,
 
+    #[cfg(feature = "osc")]
+    osc_publisher: Option<osc_publisher::OscPublisher>,
+    // (target, address prefix) the publisher could not be created for, retried once they change
+    #[cfg(feature = "osc")]
+    osc_failed_config: Option<(String, String)>,
+
     prefs: Option<UserPreferences>,
 
+    auto_gradient_selector: AutoGradientSelector,
+    // Set when the user picks a gradient manually while the automatic palette is enabled
+    auto_gradient_paused: bool,
+
+    // Active time-lapse capture started from the capture pane
+    interval_capture: Option<IntervalCapture>,
+
+    // Gizmos whose temperature is past their alarm thresholds
+    alarm_monitor: AlarmMonitor,
+
+    // Shown to the user when the preferences could not be saved
+    prefs_warning: Option<String>,
+
+    // Created on the first copy to the clipboard
+    clipboard: Option<arboard::Clipboard>,
+
     // Thumbnails shown in the "Capture tab"
     gallery: VecDeque<GalleryElement>,
     did_init_gallery: bool,
+    // Picks up files added to the captures directory by other programs
+    captures_watcher: Option<CapturesWatcher>,
+    // Number of snapshots and recordings started, substituted for {seq} in file names
+    capture_sequence: usize,
 }
 
 impl AppGlobalState {
@@ -111,6 +210,716 @@ impl AppGlobalState {
             .map(|p| p.temperature_unit)
             .unwrap_or_default()
     }
+
+    fn temperature_decimals(&self) -> usize {
+        self.prefs
+            .as_ref()
+            .map(|p| p.decimal_places as usize)
+            .unwrap_or(1)
+    }
+
+    // Number of files kept in the gallery
+    fn gallery_max_items(&self) -> usize {
+        self.prefs
+            .as_ref()
+            .map(|p| p.gallery_max_items)
+            .unwrap_or(20)
+    }
+
+    // File name template of the next snapshot or video, advancing the sequence number
+    fn next_filename_template(&mut self, video: bool) -> FilenameTemplate {
+        let template = self
+            .prefs
+            .as_ref()
+            .map(|p| {
+                if video {
+                    p.video_filename_template.clone()
+                } else {
+                    p.snapshot_filename_template.clone()
+                }
+            })
+            .unwrap_or_else(|| {
+                if video {
+                    DEFAULT_VIDEO_FILENAME_TEMPLATE.to_string()
+                } else {
+                    DEFAULT_SNAPSHOT_FILENAME_TEMPLATE.to_string()
+                }
+            });
+        self.capture_sequence += 1;
+        FilenameTemplate {
+            template,
+            sequence: self.capture_sequence,
+            unit: self.preferred_temperature_unit(),
+            decimals: self.temperature_decimals(),
+        }
+    }
+
+    // Overlays of captures enabled in the capture pane
+    fn capture_overlays(&self) -> CaptureOverlays {
+        let Some(prefs) = self.prefs.as_ref() else {
+            return CaptureOverlays::default();
+        };
+        let unit = self.preferred_temperature_unit();
+        let decimals = self.temperature_decimals();
+        let gizmos = prefs.overlay_measurements.then(|| GizmoOverlay {
+            gizmos: self
+                .thermal_capturer_settings
+                .gizmo
+                .children()
+                .map(|children| children.iter().filter(|g| g.enabled).cloned().collect())
+                .unwrap_or_default(),
+            unit,
+            decimals,
+        });
+        CaptureOverlays {
+            color_scale_unit: prefs.snapshot_color_scale.then_some(unit),
+            color_scale_decimals: decimals,
+            gizmos,
+            watermark: prefs.watermark_enabled.then(|| prefs.watermark.clone()),
+        }
+    }
+
+    // Built-in and custom gradients
+    fn available_gradients(&self) -> Vec<ThermalGradient> {
+        all_gradients(
+            self.prefs
+                .as_ref()
+                .map(|p| p.custom_gradients.as_slice())
+                .unwrap_or_default(),
+        )
+    }
+
+    //
+    // Unit and suffix to show readouts in. In the raw counts debug mode readouts are sensor counts,
+    // which are stored as Kelvin.
+    //
+    fn readout_unit(&self) -> (TemperatureUnit, String) {
+        if self
+            .last_thermal_capturer_result
+            .as_ref()
+            .is_some_and(|r| r.raw_counts)
+        {
+            (TemperatureUnit::Kelvin, "counts".to_string())
+        } else {
+            let unit = self.preferred_temperature_unit();
+            (unit, unit.suffix())
+        }
+    }
+
+    // Keeps the result for stepping through the recent frames once frozen
+    fn buffer_frame(&mut self, result: &ThermalCapturerResult) {
+        // While frozen the shown frame keeps its place as newer frames arrive
+        if self.frozen && self.frame_buffer_pos.is_none() && !self.frame_buffer.is_empty() {
+            self.frame_buffer_pos = Some(self.frame_buffer.len() - 1);
+        }
+        if self.frame_buffer.len() >= MAX_BUFFERED_FRAMES {
+            self.frame_buffer.pop_front();
+            self.frame_buffer_pos = self.frame_buffer_pos.map(|pos| pos.saturating_sub(1));
+        }
+        self.frame_buffer.push_back(Box::new(result.clone()));
+        if !self.frozen {
+            self.frame_buffer_pos = None;
+        }
+    }
+
+    //
+    // Shows an earlier (negative offset) or later buffered frame while frozen.
+    // All panes read the shown frame from last_thermal_capturer_result.
+    //
+    fn step_frozen_frame(&mut self, offset: isize) {
+        if !self.frozen || self.frame_buffer.is_empty() {
+            return;
+        }
+        let newest = self.frame_buffer.len() - 1;
+        let pos = (self.frame_buffer_pos.unwrap_or(newest) as isize + offset)
+            .clamp(0, newest as isize) as usize;
+        self.frame_buffer_pos = Some(pos);
+        self.last_thermal_capturer_result = Some(self.frame_buffer[pos].clone());
+    }
+
+    // (position, count) of the shown frame in the buffer, for the frame stepping controls
+    fn frozen_frame_position(&self) -> (usize, usize) {
+        let count = self.frame_buffer.len();
+        (
+            self.frame_buffer_pos.unwrap_or(count.saturating_sub(1)),
+            count,
+        )
+    }
+
+    //
+    // Changes the image rotation, moving the measurement points along with the image
+    // if the user wishes so.
+    //
+    fn set_rotation(&mut self, rotation: ImageRotation) {
+        let prev_rotation = self.thermal_capturer_settings.rotation;
+        let rotate_measurements = self
+            .prefs
+            .as_ref()
+            .map(|p| p.rotate_measurements_with_image)
+            .unwrap_or(true);
+        if rotate_measurements {
+            if let Some(size) = self
+                .last_thermal_capturer_result
+                .as_ref()
+                .map(|r| [r.thermal_data.width, r.thermal_data.height])
+            {
+                self.thermal_capturer_settings.gizmo.rotate_positions(
+                    prev_rotation,
+                    rotation,
+                    (size[0], size[1]),
+                );
+            }
+        }
+        self.thermal_capturer_settings.rotation = rotation;
+
+        let settings_clone = self.thermal_capturer_settings.clone();
+        if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+            thermal_capturer.set_settings(settings_clone);
+        }
+    }
+
+    //
+    // Changes the mirroring of the image, moving the measurement points along with the image
+    // if the user wishes so (same preference as for the rotation).
+    //
+    fn set_flip(&mut self, horizontal: bool, vertical: bool) {
+        let rotate_measurements = self
+            .prefs
+            .as_ref()
+            .map(|p| p.rotate_measurements_with_image)
+            .unwrap_or(true);
+        if rotate_measurements {
+            if let Some(size) = self
+                .last_thermal_capturer_result
+                .as_ref()
+                .map(|r| (r.thermal_data.width, r.thermal_data.height))
+            {
+                let mut flip_h = horizontal != self.thermal_capturer_settings.flip_horizontal;
+                let mut flip_v = vertical != self.thermal_capturer_settings.flip_vertical;
+                // The flip is applied before the rotation, so its axes are swapped in the rotated image
+                if self.thermal_capturer_settings.rotation.swaps_axes() {
+                    (flip_h, flip_v) = (flip_v, flip_h);
+                }
+                self.thermal_capturer_settings
+                    .gizmo
+                    .flip_positions(flip_h, flip_v, size);
+            }
+        }
+        self.thermal_capturer_settings.flip_horizontal = horizontal;
+        self.thermal_capturer_settings.flip_vertical = vertical;
+
+        let settings_clone = self.thermal_capturer_settings.clone();
+        if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+            thermal_capturer.set_settings(settings_clone);
+        }
+    }
+
+    //
+    // Saves the preferences, warning the user if they cannot be persisted.
+    //
+    fn save_prefs(&mut self) {
+        let Some(prefs) = self.prefs.as_ref() else {
+            return;
+        };
+        match prefs.save() {
+            Ok(()) => self.prefs_warning = None,
+            Err(err) => {
+                error!("Failed to save user preferences: {}", err);
+                self.prefs_warning = Some(format!(
+                    "Settings could not be saved and will be lost when the application is closed.\n\n{}",
+                    err
+                ));
+            }
+        }
+    }
+
+    //
+    // Hands a recorder over to the capturer, unless an active recorder already writes to the same file.
+    //
+    fn add_recorder(&mut self, recorder: Arc<Mutex<dyn Recorder>>) -> Result<()> {
+        let planned_path = recorder.lock().unwrap().planned_output_path();
+        if let Some(planned_path) = planned_path {
+            let conflict = self
+                .thermal_capturer_settings
+                .recorders
+                .iter()
+                .any(|other| {
+                    let other = other.lock().unwrap();
+                    other.state() != RecorderState::Done
+                        && other.planned_output_path().as_ref() == Some(&planned_path)
+                });
+            if conflict {
+                return Err(anyhow!(
+                    "Another recording is already writing to {}",
+                    planned_path.to_string_lossy()
+                ));
+            }
+        }
+
+        self.thermal_capturer_settings.recorders.push(recorder);
+
+        let settings_clone = self.thermal_capturer_settings.clone();
+        if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+            thermal_capturer.set_settings(settings_clone);
+        }
+        Ok(())
+    }
+
+    // Settings of the secondary capturer, derived from the primary ones
+    fn secondary_capturer_settings(&self) -> ThermalCapturerSettings {
+        ThermalCapturerSettings {
+            gizmo: Gizmo::new_root(vec![]),
+            // The flat field of the primary camera does not apply to the secondary one
+            nuc_offsets: None,
+            capture_flat_field: false,
+            recorders: vec![],
+            ..self.thermal_capturer_settings.clone()
+        }
+    }
+
+    // Whether a video or GIF recording is in progress
+    fn is_recording(&self) -> bool {
+        self.thermal_capturer_settings
+            .recorders
+            .iter()
+            .any(|recorder| {
+                let recorder = recorder.lock().unwrap();
+                recorder.is_continuous() && recorder.state() != RecorderState::Done
+            })
+    }
+
+    //
+    // Starts recording into the captures directory, as an animated GIF if that format is selected.
+    //
+    fn start_recording(&mut self, format: VideoFormat, timelapse: Option<Timelapse>) -> Result<()> {
+        let (captures_dir, gif_max_duration, gif_frame_skip, encode_settings) = self
+            .prefs
+            .as_ref()
+            .map(|prefs| {
+                (
+                    prefs.captures_directory.clone(),
+                    prefs.gif_max_duration_secs,
+                    prefs.gif_frame_skip,
+                    prefs.video_encode_settings,
+                )
+            })
+            .unwrap_or(("./".to_string(), 10, 1, VideoEncodeSettings::default()));
+        let captures_dir = PathBuf::from(captures_dir);
+
+        let recorder: Arc<Mutex<dyn Recorder>> = if format == VideoFormat::GIF {
+            Arc::new(Mutex::new(GifRecorder::new(
+                captures_dir,
+                "gif".to_string(),
+                Duration::from_secs(gif_max_duration as u64),
+                gif_frame_skip as usize,
+            )))
+        } else {
+            // The frame the recording starts on is not known yet, name it after the last one
+            let (camera, range) = self
+                .last_thermal_capturer_result
+                .as_ref()
+                .map(|r| (r.camera_short_name.clone(), Some(r.captured_range)))
+                .unwrap_or(("camera".to_string(), None));
+            let file_name =
+                self.next_filename_template(true)
+                    .expand(&camera, Local::now(), range, "video");
+            Arc::new(Mutex::new(VideoRecorder::new(
+                captures_dir,
+                &file_name,
+                format,
+                encode_settings,
+                timelapse,
+                self.capture_overlays(),
+            )))
+        };
+        self.add_recorder(recorder)
+    }
+
+    fn stop_recording(&mut self) {
+        let _ = self
+            .thermal_capturer_settings
+            .recorders
+            .iter()
+            .find(|recorder| {
+                let recorder = recorder.lock().unwrap();
+                recorder.is_continuous() && recorder.state() != RecorderState::Done
+            })
+            .ok_or(anyhow!("No active video recorder found to stop"))
+            .and_then(|rec| rec.lock().map_err(|_| anyhow!("Failed to lock recorder")))
+            .map(|mut rec| rec.stop())
+            .inspect_err(|err| error!("Failed to stop video recording: {}", err));
+    }
+
+    //
+    // Keyboard shortcuts: Space takes a snapshot, R starts or stops recording,
+    // and the number keys switch between the first nine gradients.
+    // Ignored while typing into a text field or without an open camera.
+    //
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if self.thermal_capturer_inst.is_none() || ctx.wants_keyboard_input() {
+            return;
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::Space)) {
+            let format = self
+                .prefs
+                .as_ref()
+                .map(|prefs| prefs.snapshot_format)
+                .unwrap_or(ImageFormat::Png);
+            self.take_snapshot(format);
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::R)) {
+            if self.is_recording() {
+                self.stop_recording();
+            } else {
+                let format = self
+                    .prefs
+                    .as_ref()
+                    .map(|prefs| prefs.video_format)
+                    .unwrap_or(VideoFormat::MP4_H264);
+                let _ = self
+                    .start_recording(format, None)
+                    .inspect_err(|err| error!("Failed to start recording: {}", err));
+            }
+        }
+
+        const GRADIENT_KEYS: [egui::Key; 9] = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ];
+        let pressed_gradient = GRADIENT_KEYS
+            .iter()
+            .position(|key| ctx.input(|i| i.key_pressed(*key)));
+        if let Some(gradient) =
+            pressed_gradient.and_then(|index| self.available_gradients().get(index).cloned())
+        {
+            if self.prefs.as_ref().is_some_and(|p| p.auto_gradient) {
+                self.auto_gradient_paused = true;
+            }
+            self.thermal_capturer_settings.gradient = gradient;
+            let settings_clone = self.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+    }
+
+    //
+    // Queues a snapshot of the next frame into the captures directory.
+    //
+    fn take_snapshot(&mut self, format: ImageFormat) {
+        let captures_dir = self
+            .prefs
+            .as_ref()
+            .map(|prefs| prefs.captures_directory.clone())
+            .unwrap_or("./".to_string());
+
+        let encode_settings = self
+            .prefs
+            .as_ref()
+            .map(|prefs| prefs.snapshot_encode_settings)
+            .unwrap_or_default();
+        let filename_template = self.next_filename_template(false);
+
+        let _ = self
+            .add_recorder(Arc::new(Mutex::new(ImageRecorder::new(
+                PathBuf::from(captures_dir),
+                format,
+                encode_settings,
+                filename_template,
+                self.capture_overlays(),
+            ))))
+            .inspect_err(|err| error!("Failed to take snapshot: {}", err));
+    }
+
+    //
+    // Takes `count` snapshots in quick succession, at most one every `interval`.
+    //
+    fn take_burst(&mut self, format: ImageFormat, count: usize, interval: Duration) {
+        let captures_dir = self
+            .prefs
+            .as_ref()
+            .map(|prefs| prefs.captures_directory.clone())
+            .unwrap_or("./".to_string());
+
+        let encode_settings = self
+            .prefs
+            .as_ref()
+            .map(|prefs| prefs.snapshot_encode_settings)
+            .unwrap_or_default();
+        let filename_template = self.next_filename_template(false);
+
+        let _ = self
+            .add_recorder(Arc::new(Mutex::new(BurstImageRecorder::new(
+                PathBuf::from(captures_dir),
+                format,
+                encode_settings,
+                count,
+                interval,
+                filename_template,
+                self.capture_overlays(),
+            ))))
+            .inspect_err(|err| error!("Failed to start the burst: {}", err));
+    }
+
+    //
+    // Puts the current frame on the system clipboard,
+    // with the overlays enabled for snapshots.
+    //
+    fn copy_snapshot_to_clipboard(&mut self) -> Result<()> {
+        let result = self
+            .last_thermal_capturer_result
+            .as_ref()
+            .ok_or(anyhow!("No frame to copy"))?;
+        let img =
+            DynamicImage::ImageRgb8(snapshot_image(result, &self.capture_overlays())?).into_rgba8();
+
+        // Kept around, on some platforms the contents are lost when the clipboard is dropped
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        self.clipboard
+            .as_mut()
+            .unwrap()
+            .set_image(arboard::ImageData {
+                width: img.width() as usize,
+                height: img.height() as usize,
+                bytes: Cow::Owned(img.into_raw()),
+            })?;
+        Ok(())
+    }
+
+    // Saves the temperatures of the next frame as a CSV file
+    fn take_csv_snapshot(&mut self, delimiter: CsvDelimiter) {
+        let captures_dir = self
+            .prefs
+            .as_ref()
+            .map(|prefs| prefs.captures_directory.clone())
+            .unwrap_or("./".to_string());
+
+        let _ = self
+            .add_recorder(Arc::new(Mutex::new(CsvRecorder::new(
+                PathBuf::from(captures_dir),
+                self.preferred_temperature_unit(),
+                delimiter,
+            ))))
+            .inspect_err(|err| error!("Failed to save CSV snapshot: {}", err));
+    }
+
+    // Saves the thermal data of the next frame as a binary file
+    fn take_raw_snapshot(&mut self) {
+        let captures_dir = self
+            .prefs
+            .as_ref()
+            .map(|prefs| prefs.captures_directory.clone())
+            .unwrap_or("./".to_string());
+
+        let _ = self
+            .add_recorder(Arc::new(Mutex::new(RawRecorder::new(PathBuf::from(
+                captures_dir,
+            )))))
+            .inspect_err(|err| error!("Failed to save raw data: {}", err));
+    }
+
+    //
+    // Takes a snapshot if the interval capture is due, and stops it after the last shot.
+    //
+    fn update_interval_capture(&mut self) {
+        let Some(interval_capture) = self.interval_capture.as_mut() else {
+            return;
+        };
+        if interval_capture.poll(Instant::now()) {
+            let format = self
+                .prefs
+                .as_ref()
+                .map(|prefs| prefs.snapshot_format)
+                .unwrap_or(ImageFormat::Png);
+            self.take_snapshot(format);
+        }
+        if self
+            .interval_capture
+            .as_ref()
+            .is_some_and(|c| c.is_finished())
+        {
+            self.interval_capture = None;
+        }
+    }
+
+    //
+    // Adds, moves or removes the spot measurement at the center of the image,
+    // following the preference and the size of the newest result.
+    //
+    fn update_center_spot(&mut self, result: &ThermalCapturerResult) {
+        let enabled = self
+            .prefs
+            .as_ref()
+            .map(|p| p.show_center_spot)
+            .unwrap_or(false);
+        let center = ThermalDataPos::new(
+            result.thermal_data.width / 2,
+            result.thermal_data.height / 2,
+        );
+        let Some(children) = self.thermal_capturer_settings.gizmo.children_mut() else {
+            return;
+        };
+
+        let changed = if !enabled {
+            let count = children.len();
+            children.retain(|gizmo| gizmo.uuid != CENTER_SPOT_UUID);
+            children.len() != count
+        } else {
+            match children
+                .iter_mut()
+                .find(|gizmo| gizmo.uuid == CENTER_SPOT_UUID)
+            {
+                Some(gizmo) => match &mut gizmo.kind {
+                    GizmoKind::TempAt { pos, .. } if *pos != center => {
+                        *pos = center;
+                        true
+                    }
+                    _ => false,
+                },
+                None => {
+                    let mut gizmo = Gizmo::new(
+                        GizmoKind::TempAt {
+                            pos: center,
+                            radius: 0,
+                        },
+                        "Center".to_string(),
+                        Color32::WHITE,
+                    );
+                    gizmo.uuid = CENTER_SPOT_UUID;
+                    gizmo.locked = true;
+                    children.push(gizmo);
+                    true
+                }
+            }
+        };
+
+        if changed {
+            let settings_clone = self.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+    }
+
+    //
+    // Checks the gizmo alarm thresholds against the newest result.
+    //
+    fn update_alarms(&mut self, result: &ThermalCapturerResult) {
+        let Some(gizmos) = self.thermal_capturer_settings.gizmo.children() else {
+            return;
+        };
+        self.alarm_monitor.update(gizmos, &result.gizmo_results);
+    }
+
+    //
+    // Keeps the flat field reference captured by the capturer, so that it survives settings updates.
+    //
+    fn update_flat_field(&mut self, offsets: Vec<Temp>) {
+        self.thermal_capturer_settings.nuc_offsets = Some(offsets);
+        self.thermal_capturer_settings.capture_flat_field = false;
+        let settings_clone = self.thermal_capturer_settings.clone();
+        if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+            thermal_capturer.set_settings(settings_clone);
+        }
+    }
+
+    //
+    // Switches the gradient based on the temperature span of the scene,
+    // if the automatic palette is enabled.
+    //
+    fn update_auto_gradient(&mut self) {
+        let Some(prefs) = self.prefs.as_ref().filter(|p| p.auto_gradient) else {
+            return;
+        };
+        if self.auto_gradient_paused {
+            return;
+        }
+        let Some(captured_range) = self
+            .last_thermal_capturer_result
+            .as_ref()
+            .map(|r| r.captured_range)
+        else {
+            return;
+        };
+
+        let target_uuid = if self.auto_gradient_selector.is_wide_span(
+            captured_range,
+            Temp::new(prefs.auto_gradient_span_threshold),
+        ) {
+            prefs.auto_gradient_wide
+        } else {
+            prefs.auto_gradient_narrow
+        };
+        if self.thermal_capturer_settings.gradient.uuid == target_uuid {
+            return;
+        }
+        if let Some(gradient) = self
+            .available_gradients()
+            .into_iter()
+            .find(|g| g.uuid == target_uuid)
+        {
+            self.thermal_capturer_settings.gradient = gradient;
+            let settings_clone = self.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = self.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+    }
+
+    //
+    // Sends the gizmo results over OSC, (re)creating the publisher if the preferences changed.
+    // A configuration which failed (e.g. an unresolvable target) is not retried until it changes.
+    //
+    #[cfg(feature = "osc")]
+    fn publish_osc(&mut self, result: &ThermalCapturerResult) {
+        let Some(prefs) = self.prefs.as_ref().filter(|p| p.osc_enabled) else {
+            self.osc_publisher = None;
+            self.osc_failed_config = None;
+            return;
+        };
+        let config = (prefs.osc_target.clone(), prefs.osc_address_prefix.clone());
+        if self.osc_failed_config.as_ref() == Some(&config) {
+            return;
+        }
+        if !self
+            .osc_publisher
+            .as_ref()
+            .is_some_and(|p| p.is_configured_for(&config.0, &config.1))
+        {
+            match osc_publisher::OscPublisher::new(config.0.clone(), config.1.clone()) {
+                Ok(publisher) => {
+                    self.osc_publisher = Some(publisher);
+                    self.osc_failed_config = None;
+                }
+                Err(err) => {
+                    error!("Failed to create OSC publisher: {}", err);
+                    self.osc_publisher = None;
+                    self.osc_failed_config = Some(config);
+                    return;
+                }
+            }
+        }
+        let unit = self.preferred_temperature_unit();
+        if let (Some(publisher), Some(gizmos)) = (
+            self.osc_publisher.as_ref(),
+            self.thermal_capturer_settings.gizmo.children_mut(),
+        ) {
+            let _ = publisher
+                .publish(gizmos, &result.gizmo_results, unit)
+                .inspect_err(|err| log::debug!("Failed to send OSC message: {}", err));
+        }
+    }
 }
 
 struct ThermalViewerApp {
@@ -138,6 +947,7 @@ impl ThermalViewerApp {
             vec![
                 Box::new(HistogramPane::new(self.global_state.clone())),
                 Box::new(ChartPane::new(self.global_state.clone())),
+                Box::new(LineProfilePane::new(self.global_state.clone())),
                 Box::new(GalleryPane::new(self.global_state.clone())),
             ],
         );
@@ -153,6 +963,24 @@ impl ThermalViewerApp {
             vec![Box::new(MeasurementsPane::new(self.global_state.clone()))],
         );
     }
+
+    fn is_any_tab_maximized(&mut self) -> bool {
+        self.dock_state
+            .iter_all_tabs_mut()
+            .any(|tab| tab.1.is_maximized())
+    }
+
+    fn exit_maximized(&mut self) {
+        self.dock_state
+            .iter_all_tabs_mut()
+            .for_each(|tab| tab.1.set_maximized(false));
+    }
+
+    // Opens a pane in a new window, leaving maximized mode so that the window is visible
+    fn add_window(&mut self, pane: Box<dyn Pane>) {
+        self.exit_maximized();
+        self.dock_state.add_window(vec![pane]);
+    }
 }
 
 impl Default for ThermalViewerApp {
@@ -161,34 +989,83 @@ impl Default for ThermalViewerApp {
         let global_state = AppGlobalState {
             did_try_open_camera_at_startup: false,
             should_try_open_camera_on_next_hotplug: true,
+            opened_camera: None,
+            disconnected_camera: None,
 
             prefs: None,
+            auto_gradient_selector: AutoGradientSelector::new(),
+            auto_gradient_paused: false,
+            interval_capture: None,
+            alarm_monitor: AlarmMonitor::default(),
+            prefs_warning: None,
             thermal_capturer_inst: None,
             thermal_capturer_settings: ThermalCapturerSettings {
                 rotation: ImageRotation::None,
+                flip_horizontal: false,
+                flip_vertical: false,
                 auto_range: true,
+                auto_range_ignore_background: false,
+                auto_range_settings: AutoRangeSettings::default(),
                 manual_range: TempRange::new(
                     Temp::from_unit(TemperatureUnit::Celsius, 0.0),
                     Temp::from_unit(TemperatureUnit::Celsius, 50.0),
                 ),
                 gradient: thermal_gradient::THERMAL_GRADIENTS[0].clone(),
+                invert_gradient: false,
                 gizmo: Gizmo::new_root(vec![
-                    Gizmo::new(GizmoKind::MaxTemp, "Max".to_string(), Color32::RED),
-                    Gizmo::new(
-                        GizmoKind::MinTemp,
-                        "Min".to_string(),
-                        Color32::from_rgb(72, 219, 251),
-                    ),
+                    Gizmo {
+                        uuid: MAX_TEMP_UUID,
+                        ..Gizmo::new(GizmoKind::MaxTemp, "Max".to_string(), Color32::RED)
+                    },
+                    Gizmo {
+                        uuid: MIN_TEMP_UUID,
+                        ..Gizmo::new(
+                            GizmoKind::MinTemp,
+                            "Min".to_string(),
+                            Color32::from_rgb(72, 219, 251),
+                        )
+                    },
                 ]),
+                ambient: Temp::from_celsius(20.0),
+                ambient_source: AmbientSource::Manual,
+                emissivity: 1.0,
+                reflected_temp: None,
+                clamp_to_camera_range: false,
+                show_raw_counts: false,
                 dynamic_range_curve: DynamicRangeCurve::default(),
+                nuc_offsets: None,
+                capture_flat_field: false,
+                isotherm: None,
+                target_fps: None,
+                stabilize_extremes: false,
+                stabilize_extremes_margin: Temp::new(0.5),
+                upscale_factor: 1,
+                denoise_strength: 0.0,
+                blur_kernel_size: 0,
+                blur_display_only: false,
+                histogram_buckets: 100,
+                edge_overlay: None,
                 recorders: vec![],
             },
             last_thermal_capturer_result: None,
+            frozen: false,
+            frame_buffer: VecDeque::with_capacity(MAX_BUFFERED_FRAMES),
+            frame_buffer_pos: None,
+            secondary_capturer_inst: None,
+            last_secondary_capturer_result: None,
             hotplug_detector: None,
             history_data_collector: HistoryDataCollector::new(),
 
+            #[cfg(feature = "osc")]
+            osc_publisher: None,
+            #[cfg(feature = "osc")]
+            osc_failed_config: None,
+
+            clipboard: None,
             gallery: VecDeque::new(),
             did_init_gallery: false,
+            captures_watcher: None,
+            capture_sequence: 0,
         };
 
         ThermalViewerApp {
@@ -201,24 +1078,36 @@ impl Default for ThermalViewerApp {
 }
 
 impl eframe::App for ThermalViewerApp {
+    // Only the window geometry is persisted by eframe, the rest lives in the user preferences
+    fn persist_egui_memory(&self) -> bool {
+        false
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame_egui: &mut eframe::Frame) {
         if !self.did_init {
             self.did_init = true;
-            self.set_default_dock_state();
-            let mut borrowed_global_state = self.global_state.borrow_mut();
-            borrowed_global_state.prefs = Some(
+            // Preferences are loaded before creating the panes, since some panes read them on creation
+            self.global_state.borrow_mut().prefs = Some(
                 UserPreferences::load()
                     .inspect_err(|err| {
                         error!(
                             "Failed to load user preferences from {}: {}",
                             UserPreferences::preferences_path()
-                                .to_string_lossy()
-                                .to_string(),
+                                .map(|path| path.to_string_lossy().to_string())
+                                .unwrap_or_default(),
                             err
                         )
                     })
                     .unwrap_or_default(),
             );
+            self.set_default_dock_state();
+            let mut borrowed_global_state = self.global_state.borrow_mut();
+            if UserPreferences::preferences_path().is_none() {
+                borrowed_global_state.prefs_warning = Some(
+                    "No location is available for storing settings, they will not be saved."
+                        .to_string(),
+                );
+            }
             let cloned_ctx = ctx.clone();
 
             borrowed_global_state.hotplug_detector = run_hotplug_detector(move |_| {
@@ -245,15 +1134,7 @@ impl eframe::App for ThermalViewerApp {
                     // Handle thermal capturer commands
                     if let Ok(r) = capturer.result_receiver.try_recv() {
                         match r {
-                            Ok(result) => {
-                                borrowed_global_state
-                                    .history_data_collector
-                                    .add_from_gizmo_results(
-                                        result.capture_time,
-                                        &result.gizmo_results,
-                                    )
-                                    .unwrap();
-
+                            Ok(mut result) => {
                                 // Add captured image to gallery if needed
                                 let mut gallery_tmp = vec![];
                                 borrowed_global_state.thermal_capturer_settings.recorders =
@@ -264,7 +1145,11 @@ impl eframe::App for ThermalViewerApp {
                                         .filter(|recorder| {
                                             let recorder = recorder.lock().unwrap();
                                             if recorder.state() == RecorderState::Done {
-                                                for file in recorder.files_created() {
+                                                for file in recorder
+                                                    .files_created()
+                                                    .into_iter()
+                                                    .filter(|file| is_media_file(file))
+                                                {
                                                     gallery_tmp.push(GalleryElement {
                                                         path: file,
                                                         created_at: SystemTime::now(),
@@ -276,13 +1161,64 @@ impl eframe::App for ThermalViewerApp {
                                         })
                                         .collect();
                                 borrowed_global_state.gallery.extend(gallery_tmp);
-                                borrowed_global_state.last_thermal_capturer_result = Some(result);
+                                let gallery_max_items = borrowed_global_state.gallery_max_items();
+                                while borrowed_global_state.gallery.len() > gallery_max_items {
+                                    borrowed_global_state.gallery.pop_front();
+                                }
+                                // A captured flat field is applied even while frozen
+                                if let Some(offsets) = result.flat_field_offsets.take() {
+                                    borrowed_global_state.update_flat_field(offsets);
+                                }
+                                borrowed_global_state.update_interval_capture();
+
+                                // Monitoring goes on while frozen, only the shown frame is held
+                                let history_retention = Duration::from_secs(
+                                    borrowed_global_state
+                                        .prefs
+                                        .as_ref()
+                                        .map(|p| p.history_retention_minutes)
+                                        .unwrap_or(30) as u64
+                                        * 60,
+                                );
+                                borrowed_global_state
+                                    .history_data_collector
+                                    .add_from_gizmo_results(
+                                        result.capture_time,
+                                        &result.gizmo_results,
+                                        history_retention,
+                                    )
+                                    .unwrap();
+                                borrowed_global_state
+                                    .history_data_collector
+                                    .add_frame_stats(
+                                        result.capture_time,
+                                        result.captured_range,
+                                        result.captured_avg,
+                                    );
+
+                                #[cfg(feature = "osc")]
+                                borrowed_global_state.publish_osc(&result);
+
+                                borrowed_global_state.buffer_frame(&result);
+                                borrowed_global_state.update_center_spot(&result);
+                                borrowed_global_state.update_alarms(&result);
+                                if !borrowed_global_state.frozen {
+                                    borrowed_global_state.last_thermal_capturer_result =
+                                        Some(result);
+                                    borrowed_global_state.update_auto_gradient();
+                                }
 
                                 had_result = true;
                             }
                             Err(e) => {
                                 error!("Thermal capturer error: {}", e);
                                 borrowed_global_state.thermal_capturer_inst = None;
+                                // Most likely unplugged, wait for it to come back
+                                if let Some(camera) = borrowed_global_state.opened_camera.take() {
+                                    borrowed_global_state.disconnected_camera = Some(camera);
+                                    borrowed_global_state.should_try_open_camera_on_next_hotplug =
+                                        true;
+                                }
                             }
                         }
                     }
@@ -290,38 +1226,124 @@ impl eframe::App for ThermalViewerApp {
 
                 had_result
             } {}
+
+            // Only the latest frame of the secondary camera is shown
+            let mut secondary_result = None;
+            if let Some(capturer) = borrowed_global_state.secondary_capturer_inst.as_mut() {
+                while let Ok(r) = capturer.result_receiver.try_recv() {
+                    secondary_result = Some(r);
+                }
+            }
+            match secondary_result {
+                Some(Ok(result)) => {
+                    borrowed_global_state.last_secondary_capturer_result = Some(result);
+                    // Keep up with the display settings of the primary camera
+                    let settings = borrowed_global_state.secondary_capturer_settings();
+                    if let Some(capturer) = borrowed_global_state.secondary_capturer_inst.as_mut() {
+                        capturer.set_settings(settings);
+                    }
+                }
+                Some(Err(e)) => {
+                    error!("Secondary thermal capturer error: {}", e);
+                    borrowed_global_state.secondary_capturer_inst = None;
+                    borrowed_global_state.last_secondary_capturer_result = None;
+                }
+                None => {}
+            }
         }
 
+        self.global_state.borrow_mut().handle_shortcuts(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("Preferences").clicked() {
                         // TODO: forbid opening multiple user preferences windows
-                        self.dock_state
-                            .add_window(vec![Box::new(UserPreferencesPane::new(
-                                self.global_state.clone(),
-                            ))]);
+                        self.add_window(Box::new(UserPreferencesPane::new(
+                            self.global_state.clone(),
+                        )));
                     }
                     ui.separator();
                     if ui.button("Quit").clicked() {
-                        self.global_state.borrow_mut().thermal_capturer_inst = None;
+                        let mut global_state = self.global_state.borrow_mut();
+                        global_state.thermal_capturer_inst = None;
+                        global_state.secondary_capturer_inst = None;
                         std::process::exit(0);
                     }
                 });
                 ui.menu_button("Window", |ui| {
                     if ui.button("Performance stats").clicked() {
-                        self.dock_state
-                            .add_window(vec![Box::new(PerformanceStatsPane::new(
-                                self.global_state.clone(),
-                            ))]);
+                        self.add_window(Box::new(PerformanceStatsPane::new(
+                            self.global_state.clone(),
+                        )));
+                    }
+                    if ui.button("Line profile").clicked() {
+                        self.add_window(Box::new(LineProfilePane::new(self.global_state.clone())));
+                    }
+                    if ui
+                        .add_enabled(
+                            self.is_any_tab_maximized(),
+                            egui::Button::new("Exit maximized view"),
+                        )
+                        .clicked()
+                    {
+                        self.exit_maximized();
                     }
                     if ui.button("Reset Layout").clicked() {
                         self.set_default_dock_state();
                     }
                 });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let mut global_state = self.global_state.borrow_mut();
+                    let Some(prefs) = global_state.prefs.as_mut() else {
+                        return;
+                    };
+                    let mut changed = false;
+                    // Laid out right to left, so iterate backwards to keep the usual order
+                    for unit in TemperatureUnit::iter().rev() {
+                        changed |= ui
+                            .selectable_value(&mut prefs.temperature_unit, unit, unit.suffix())
+                            .on_hover_text(unit.to_string())
+                            .changed();
+                    }
+                    if changed {
+                        global_state.save_prefs();
+                    }
+                });
             });
         });
 
+        let exit_on_escape = self
+            .global_state
+            .borrow()
+            .prefs
+            .as_ref()
+            .map(|p| p.exit_maximized_on_escape)
+            .unwrap_or(true);
+        if self.is_any_tab_maximized() {
+            // Don't steal Esc from open popups (color pickers, combo boxes etc.)
+            if exit_on_escape
+                && !ctx.memory(|mem| mem.any_popup_open())
+                && ctx.input(|i| i.key_pressed(egui::Key::Escape))
+            {
+                self.exit_maximized();
+            }
+        }
+
+        let prefs_warning = self.global_state.borrow().prefs_warning.clone();
+        if let Some(prefs_warning) = prefs_warning {
+            egui::Window::new("Settings not saved")
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.colored_label(ui.visuals().warn_fg_color, prefs_warning);
+                    if ui.button("Dismiss").clicked() {
+                        self.global_state.borrow_mut().prefs_warning = None;
+                    }
+                });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Either render a tab maximized, or render the egui_dock layout
             let fulscreen_tab = self
@@ -331,6 +1353,24 @@ impl eframe::App for ThermalViewerApp {
 
             if let Some(tab) = fulscreen_tab {
                 tab.1.ui(ui);
+
+                // Floating button, so that the maximized view can always be left
+                let mut exit_clicked = false;
+                egui::Area::new(egui::Id::new("exit_maximized_area"))
+                    .order(egui::Order::Foreground)
+                    .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+                    .show(ui.ctx(), |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            let mut exit_button = ui.button("Exit maximized view");
+                            if exit_on_escape {
+                                exit_button = exit_button.on_hover_text("You can also press Esc");
+                            }
+                            exit_clicked = exit_button.clicked();
+                        });
+                    });
+                if exit_clicked {
+                    tab.1.set_maximized(false);
+                }
             } else {
                 DockArea::new(&mut self.dock_state)
                     .style(egui_dock::Style::from_egui(ui.style().as_ref()))
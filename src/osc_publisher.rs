@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    net::{ToSocketAddrs, UdpSocket},
+};
+
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use crate::{
+    gizmos::{Gizmo, GizmoResult},
+    temperature::TemperatureUnit,
+    util::pathify_string,
+};
+
+///
+/// Sends gizmo temperatures as OSC messages over UDP.
+/// Each gizmo gets its own address: `<prefix>/<gizmo name>`, with a single float argument.
+///
+pub struct OscPublisher {
+    socket: UdpSocket,
+    target: String,
+    address_prefix: String,
+}
+
+impl OscPublisher {
+    pub fn new(target: String, address_prefix: String) -> Result<Self, anyhow::Error> {
+        let target_addr = target
+            .to_socket_addrs()?
+            .next()
+            .ok_or(anyhow!("Could not resolve OSC target {}", target))?;
+        let socket = UdpSocket::bind(if target_addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        })?;
+        socket.connect(target_addr)?;
+
+        Ok(Self {
+            socket,
+            target,
+            address_prefix,
+        })
+    }
+
+    // Returns true if the publisher was created with the given configuration
+    pub fn is_configured_for(&self, target: &str, address_prefix: &str) -> bool {
+        self.target == target && self.address_prefix == address_prefix
+    }
+
+    pub fn publish(
+        &self,
+        gizmos: &[Gizmo],
+        gizmo_results: &HashMap<Uuid, GizmoResult>,
+        unit: TemperatureUnit,
+    ) -> Result<(), anyhow::Error> {
+        for gizmo in gizmos {
+            if let Some(result) = gizmo_results.get(&gizmo.uuid) {
+                let address = format!(
+                    "{}/{}",
+                    self.address_prefix.trim_end_matches('/'),
+                    pathify_string(gizmo.name.clone())
+                );
+                let value = match result.delta {
+                    Some(delta) => delta.diff_to_unit(unit),
+                    None => result.temperature.to_unit(unit),
+                };
+                self.socket.send(&encode_float_message(&address, value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Encodes an OSC message with a single float32 argument
+fn encode_float_message(address: &str, value: f32) -> Vec<u8> {
+    let mut buf = vec![];
+    push_osc_string(&mut buf, address);
+    push_osc_string(&mut buf, ",f");
+    buf.extend_from_slice(&value.to_be_bytes());
+    buf
+}
+
+// OSC strings are null terminated and padded to a multiple of 4 bytes
+fn push_osc_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    while buf.len() % 4 != 0 {
+        buf.push(0);
+    }
+}
@@ -11,6 +11,9 @@ pub trait Pane {
     fn is_maximized(&self) -> bool {
         false
     }
+
+    // Called when the maximized state is changed from outside the pane (e.g. by pressing Esc).
+    fn set_maximized(&mut self, _maximized: bool) {}
 }
 
 pub struct PaneDispatcher {}
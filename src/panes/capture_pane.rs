@@ -2,15 +2,23 @@ use std::{
     cell::RefCell,
     path::PathBuf,
     rc::Rc,
-    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-use eframe::egui::{self, Align, Button, Color32, Layout, Vec2};
+use eframe::egui::{self, Align, Button, Color32, DragValue, Grid, Layout, Slider, TextEdit, Vec2};
 
 use crate::{
+    interval_capture::IntervalCapture,
     pane_dispatcher::Pane,
-    recorders::{image_recorder::ImageRecorder, video_recorder::VideoRecorder},
-    types::media_formats::{ImageFormat, VideoFormat},
+    recorders::video_recorder::Timelapse,
+    report_exporter,
+    types::{
+        media_formats::{
+            CsvDelimiter, ImageEncodeSettings, ImageFormat, PngCompression, VideoEncodeSettings,
+            VideoFormat, VideoPreset,
+        },
+        watermark::{WatermarkCorner, WatermarkSettings, WATERMARK_TOKENS},
+    },
     AppGlobalState,
 };
 
@@ -18,14 +26,316 @@ pub struct CapturePane {
     global_state: Rc<RefCell<AppGlobalState>>,
     snapshot_format: ImageFormat,
     video_format: VideoFormat,
+    video_encode_settings: VideoEncodeSettings,
+    csv_delimiter: CsvDelimiter,
+    snapshot_color_scale: bool,
+    overlay_measurements: bool,
+    watermark_enabled: bool,
+    watermark: WatermarkSettings,
+    snapshot_encode_settings: ImageEncodeSettings,
+    interval_capture_secs: f32,
+    // 0 means unlimited
+    interval_capture_count: u32,
+    // Record a video frame every timelapse_interval_secs instead of every frame
+    timelapse: bool,
+    timelapse_interval_secs: f32,
+    timelapse_output_fps: usize,
+    // Number of snapshots of a burst and the minimum time between them
+    burst_count: usize,
+    burst_interval_secs: f32,
+    // Shown when a recording could not be started
+    capture_error: Option<String>,
+    report_description: String,
+    // Outcome of the last report export
+    report_message: Option<String>,
 }
 
 impl CapturePane {
     pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> CapturePane {
+        let (
+            snapshot_format,
+            video_format,
+            csv_delimiter,
+            snapshot_color_scale,
+            overlay_measurements,
+            snapshot_encode_settings,
+            video_encode_settings,
+        ) = global_state
+            .borrow()
+            .prefs
+            .as_ref()
+            .map(|prefs| {
+                (
+                    prefs.snapshot_format,
+                    prefs.video_format,
+                    prefs.csv_delimiter,
+                    prefs.snapshot_color_scale,
+                    prefs.overlay_measurements,
+                    prefs.snapshot_encode_settings,
+                    prefs.video_encode_settings,
+                )
+            })
+            .unwrap_or((
+                ImageFormat::Png,
+                VideoFormat::MP4_H264,
+                CsvDelimiter::Comma,
+                false,
+                false,
+                ImageEncodeSettings::default(),
+                VideoEncodeSettings::default(),
+            ));
+        let (watermark_enabled, watermark) = global_state
+            .borrow()
+            .prefs
+            .as_ref()
+            .map(|prefs| (prefs.watermark_enabled, prefs.watermark.clone()))
+            .unwrap_or_default();
         CapturePane {
             global_state,
-            snapshot_format: ImageFormat::Png,
-            video_format: VideoFormat::MP4_H264,
+            snapshot_format,
+            video_format,
+            video_encode_settings,
+            csv_delimiter,
+            snapshot_color_scale,
+            overlay_measurements,
+            watermark_enabled,
+            watermark,
+            snapshot_encode_settings,
+            interval_capture_secs: 10.0,
+            interval_capture_count: 0,
+            timelapse: false,
+            timelapse_interval_secs: 5.0,
+            timelapse_output_fps: 30,
+            burst_count: 5,
+            burst_interval_secs: 0.5,
+            capture_error: None,
+            report_description: String::new(),
+            report_message: None,
+        }
+    }
+
+    fn report_ui(&mut self, ui: &mut egui::Ui, global_state: &mut AppGlobalState) {
+        ui.add(
+            TextEdit::multiline(&mut self.report_description)
+                .hint_text("Report description (optional)")
+                .desired_rows(2),
+        );
+        if ui
+            .button("Export PDF report")
+            .on_hover_text(
+                "Saves the current frame, measurements and histogram to the captures directory",
+            )
+            .clicked()
+        {
+            if let Some(result) = global_state.last_thermal_capturer_result.as_ref() {
+                let captures_dir = global_state
+                    .prefs
+                    .as_ref()
+                    .map(|prefs| prefs.captures_directory.clone())
+                    .unwrap_or("./".to_string());
+                let exported = report_exporter::export_pdf_report(
+                    &PathBuf::from(captures_dir),
+                    result,
+                    &global_state.thermal_capturer_settings,
+                    global_state.preferred_temperature_unit(),
+                    global_state.temperature_decimals(),
+                    &self.report_description,
+                );
+                self.report_message = Some(match exported {
+                    Ok(path) => format!("Saved to {}", path.to_string_lossy()),
+                    Err(err) => {
+                        log::error!("Failed to export report: {}", err);
+                        format!("Export failed: {}", err)
+                    }
+                });
+            }
+        }
+        if let Some(message) = self.report_message.as_ref() {
+            ui.weak(message);
+        }
+    }
+
+    fn interval_capture_ui(&mut self, ui: &mut egui::Ui, global_state: &mut AppGlobalState) {
+        ui.add_enabled_ui(global_state.interval_capture.is_none(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Every");
+                ui.add(
+                    DragValue::new(&mut self.interval_capture_secs)
+                        .range(0.5..=3600.0)
+                        .speed(0.5)
+                        .suffix(" s"),
+                );
+                ui.label("Shots");
+                ui.add(DragValue::new(&mut self.interval_capture_count).range(0..=10_000))
+                    .on_hover_text("0 takes snapshots until stopped");
+            });
+        });
+
+        let now = Instant::now();
+        if let Some(interval_capture) = global_state.interval_capture.as_ref() {
+            let shots = match interval_capture.max_shots {
+                Some(max_shots) => format!("{}/{}", interval_capture.shots_taken, max_shots),
+                None => interval_capture.shots_taken.to_string(),
+            };
+            ui.label(format!(
+                "Next shot in {:.1} s ({} taken)",
+                interval_capture.time_to_next_shot(now).as_secs_f32(),
+                shots
+            ));
+            if ui.button("Stop interval capture").clicked() {
+                global_state.interval_capture = None;
+            }
+            // Keep the countdown moving even if no frames arrive
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        } else if ui
+            .button("Start interval capture")
+            .on_hover_text("Takes snapshots automatically in the selected format")
+            .clicked()
+        {
+            global_state.interval_capture = Some(IntervalCapture::new(
+                Duration::from_secs_f32(self.interval_capture_secs),
+                Some(self.interval_capture_count).filter(|count| *count > 0),
+            ));
+        }
+    }
+
+    fn timelapse_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.timelapse, "Timelapse")
+            .on_hover_text("Record one frame every interval, played back at the output rate");
+        if self.timelapse {
+            ui.horizontal(|ui| {
+                ui.label("Every");
+                ui.add(
+                    DragValue::new(&mut self.timelapse_interval_secs)
+                        .range(0.1..=3600.0)
+                        .speed(0.5)
+                        .suffix(" s"),
+                );
+                ui.label("Playback");
+                ui.add(
+                    DragValue::new(&mut self.timelapse_output_fps)
+                        .range(1..=60)
+                        .suffix(" fps"),
+                );
+            });
+        }
+    }
+
+    fn watermark_settings_ui(&mut self, ui: &mut egui::Ui) {
+        let watermark = &mut self.watermark;
+        Grid::new("capture_pane_watermark_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Text");
+                ui.text_edit_singleline(&mut watermark.template)
+                    .on_hover_ui(|ui| {
+                        ui.label("Available tokens:");
+                        Grid::new("capture_pane_watermark_tokens").show(ui, |ui| {
+                            for (token, description) in WATERMARK_TOKENS {
+                                ui.monospace(token);
+                                ui.label(description);
+                                ui.end_row();
+                            }
+                        });
+                        ui.label("Letters are drawn in uppercase.");
+                    });
+                ui.end_row();
+
+                ui.label("Corner");
+                WatermarkCorner::egui_combo_box(
+                    ui,
+                    "capture_pane_watermark_corner",
+                    &mut watermark.corner,
+                    100.0,
+                );
+                ui.end_row();
+
+                ui.label("Font size");
+                ui.add(DragValue::new(&mut watermark.font_size).range(1..=8))
+                    .on_hover_text("Size of a pixel of the font, in image pixels");
+                ui.end_row();
+
+                ui.label("Opacity");
+                ui.add(Slider::new(&mut watermark.opacity, 0.1..=1.0));
+                ui.end_row();
+            });
+        if ui.button("Reset to defaults").clicked() {
+            *watermark = WatermarkSettings::default();
+        }
+    }
+
+    fn video_encode_settings_ui(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.video_encode_settings;
+        Grid::new("capture_pane_video_encode_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let mut use_quality = settings.constant_quality.is_some();
+                ui.label("Rate control");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut use_quality, false, "Bitrate");
+                    ui.radio_value(&mut use_quality, true, "Constant quality");
+                });
+                ui.end_row();
+                match (use_quality, settings.constant_quality) {
+                    (true, None) => settings.constant_quality = Some(23),
+                    (false, Some(_)) => settings.constant_quality = None,
+                    _ => {}
+                }
+
+                if let Some(quality) = settings.constant_quality.as_mut() {
+                    ui.label("Quality (CRF)");
+                    ui.add(DragValue::new(quality).range(0..=51))
+                        .on_hover_text("Lower values give better quality and larger files");
+                } else {
+                    ui.label("Bitrate");
+                    ui.add(
+                        DragValue::new(&mut settings.bitrate_kbps)
+                            .range(100..=50_000)
+                            .suffix(" kbit/s"),
+                    );
+                }
+                ui.end_row();
+
+                ui.label("Preset")
+                    .on_hover_text("Used for H.264, slower presets give smaller files");
+                VideoPreset::egui_combo_box(
+                    ui,
+                    "capture_pane_video_preset",
+                    &mut settings.preset,
+                    100.0,
+                );
+                ui.end_row();
+            });
+        if ui.button("Reset to defaults").clicked() {
+            *settings = VideoEncodeSettings::default();
+        }
+    }
+
+    // Stores the selected formats in the preferences, so that they are restored on the next launch
+    fn persist_formats(&self, global_state: &mut AppGlobalState) {
+        if let Some(prefs) = global_state.prefs.as_mut() {
+            if prefs.snapshot_format == self.snapshot_format
+                && prefs.video_format == self.video_format
+                && prefs.video_encode_settings == self.video_encode_settings
+                && prefs.csv_delimiter == self.csv_delimiter
+                && prefs.snapshot_color_scale == self.snapshot_color_scale
+                && prefs.overlay_measurements == self.overlay_measurements
+                && prefs.watermark_enabled == self.watermark_enabled
+                && prefs.watermark == self.watermark
+                && prefs.snapshot_encode_settings == self.snapshot_encode_settings
+            {
+                return;
+            }
+            prefs.snapshot_format = self.snapshot_format;
+            prefs.video_format = self.video_format;
+            prefs.video_encode_settings = self.video_encode_settings;
+            prefs.csv_delimiter = self.csv_delimiter;
+            prefs.snapshot_color_scale = self.snapshot_color_scale;
+            prefs.overlay_measurements = self.overlay_measurements;
+            prefs.watermark_enabled = self.watermark_enabled;
+            prefs.watermark = self.watermark.clone();
+            prefs.snapshot_encode_settings = self.snapshot_encode_settings;
+            global_state.save_prefs();
         }
     }
 }
@@ -50,37 +360,123 @@ impl Pane for CapturePane {
                         &mut self.snapshot_format,
                         available_width / 2.0 - 5.0,
                     );
+                    match self.snapshot_format {
+                        ImageFormat::Jpeg => {
+                            ui.add(
+                                Slider::new(
+                                    &mut self.snapshot_encode_settings.jpeg_quality,
+                                    1..=100,
+                                )
+                                .text("Quality"),
+                            );
+                        }
+                        ImageFormat::Png => {
+                            PngCompression::egui_combo_box(
+                                ui,
+                                "capture_pane_png_compression",
+                                &mut self.snapshot_encode_settings.png_compression,
+                                available_width / 2.0 - 5.0,
+                            );
+                        }
+                    }
 
                     if ui
                         .add(
                             Button::image_and_text(
                                 egui::include_image!("../icons/camera.svg"),
-                                "Snapshot",
+                                "Snapshot (Space)",
                             )
                             .min_size(Vec2::new(0.0, 25.0)),
                         )
                         .clicked()
                     {
-                        let captures_dir = global_state
-                            .prefs
-                            .as_ref()
-                            .map(|prefs| prefs.captures_directory.clone())
-                            .unwrap_or("./".to_string());
-
-                        global_state
-                            .thermal_capturer_settings
-                            .recorders
-                            .push(Arc::new(Mutex::new(ImageRecorder::new(
-                                PathBuf::from(captures_dir),
-                                self.snapshot_format,
-                            ))));
-
-                        let settings_clone = global_state.thermal_capturer_settings.clone();
-                        if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut()
+                        global_state.take_snapshot(self.snapshot_format);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!global_state.is_recording(), Button::new("Burst"))
+                            .on_hover_text("Take several snapshots in quick succession")
+                            .clicked()
                         {
-                            thermal_capturer.set_settings(settings_clone);
+                            global_state.take_burst(
+                                self.snapshot_format,
+                                self.burst_count,
+                                Duration::from_secs_f32(self.burst_interval_secs),
+                            );
                         }
+                        ui.add(
+                            DragValue::new(&mut self.burst_count)
+                                .range(2..=100)
+                                .suffix("×"),
+                        );
+                        ui.add(
+                            DragValue::new(&mut self.burst_interval_secs)
+                                .range(0.0..=10.0)
+                                .speed(0.05)
+                                .suffix(" s"),
+                        )
+                        .on_hover_text("Time between the snapshots, 0 takes consecutive frames");
+                    });
+                    ui.checkbox(&mut self.snapshot_color_scale, "Color scale")
+                        .on_hover_text(
+                            "Add a color scale with the minimum and maximum to snapshots",
+                        );
+                    ui.checkbox(&mut self.overlay_measurements, "Measurements")
+                        .on_hover_text(
+                            "Draw the measurements and their temperatures on snapshots and videos",
+                        );
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.watermark_enabled, "Watermark")
+                            .on_hover_text(
+                                "Burn a text, e.g. the capture time, into snapshots and videos",
+                            );
+                        ui.add_enabled_ui(self.watermark_enabled, |ui| {
+                            ui.menu_button("Settings", |ui| {
+                                self.watermark_settings_ui(ui);
+                            });
+                        });
+                    });
+                    if ui
+                        .add_enabled(
+                            global_state.last_thermal_capturer_result.is_some(),
+                            Button::new("Copy to clipboard").min_size(Vec2::new(0.0, 25.0)),
+                        )
+                        .on_hover_text("Copies the current frame as an image")
+                        .clicked()
+                    {
+                        self.capture_error = global_state
+                            .copy_snapshot_to_clipboard()
+                            .err()
+                            .map(|err| format!("Failed to copy to the clipboard: {}", err));
                     }
+
+                    CsvDelimiter::egui_combo_box(
+                        ui,
+                        "capture_pane_csv_delimiter",
+                        &mut self.csv_delimiter,
+                        available_width / 2.0 - 5.0,
+                    );
+                    if ui
+                        .add(Button::new("Save temperatures").min_size(Vec2::new(0.0, 25.0)))
+                        .on_hover_text(
+                            "Saves the temperature of every pixel of the next frame as a CSV file",
+                        )
+                        .clicked()
+                    {
+                        global_state.take_csv_snapshot(self.csv_delimiter);
+                    }
+                    if ui
+                        .add(Button::new("Save raw data").min_size(Vec2::new(0.0, 25.0)))
+                        .on_hover_text(
+                            "Saves the thermal data of the next frame as a binary file \
+                            (Kelvin as f32 and the sensor counts as u16, if available)",
+                        )
+                        .clicked()
+                    {
+                        global_state.take_raw_snapshot();
+                    }
+
+                    self.interval_capture_ui(ui, &mut global_state);
                 });
 
                 ui.with_layout(Layout::top_down_justified(Align::Min), |ui| {
@@ -90,16 +486,15 @@ impl Pane for CapturePane {
                         &mut self.video_format,
                         available_width / 2.0 - 5.0,
                     );
-                    let is_recording = global_state
-                        .thermal_capturer_settings
-                        .recorders
-                        .iter_mut()
-                        .any(|recorder| {
-                            let recorder = recorder.lock().unwrap();
-                            recorder.is_continuous()
-                                && recorder.state()
-                                    != crate::recorders::recorder::RecorderState::Done
+                    if self.video_format != VideoFormat::GIF {
+                        ui.add_enabled_ui(!global_state.is_recording(), |ui| {
+                            ui.menu_button("Encoder settings", |ui| {
+                                self.video_encode_settings_ui(ui);
+                            });
+                            self.timelapse_ui(ui);
                         });
+                    }
+                    let is_recording = global_state.is_recording();
 
                     if is_recording {
                         ui.scope(|ui| {
@@ -113,67 +508,59 @@ impl Pane for CapturePane {
                                 .add(
                                     Button::image_and_text(
                                         egui::include_image!("../icons/video.svg"),
-                                        "Stop recording",
+                                        "Stop recording (R)",
                                     )
                                     .min_size(Vec2::new(available_width / 2.0 - 5.0, 25.0)),
                                 )
                                 .clicked()
                             {
-                                let _ = global_state
-                                    .thermal_capturer_settings
-                                    .recorders
-                                    .iter()
-                                    .find(|recorder| {
-                                        let recorder = recorder.lock().unwrap();
-                                        recorder.is_continuous()
-                                            && recorder.state()
-                                                != crate::recorders::recorder::RecorderState::Done
-                                    })
-                                    .ok_or(anyhow::anyhow!(
-                                        "No active video recorder found to stop"
-                                    ))
-                                    .and_then(|rec| {
-                                        rec.lock()
-                                            .map_err(|_| anyhow::anyhow!("Failed to lock recorder"))
-                                    })
-                                    .map(|mut rec| rec.stop())
-                                    .inspect_err(|err| {
-                                        log::error!("Failed to stop video recording: {}", err)
-                                    });
+                                global_state.stop_recording();
                             }
                         });
-                    } else if ui
-                        .add(
+                    } else {
+                        let gif_max_duration = global_state
+                            .prefs
+                            .as_ref()
+                            .map(|prefs| prefs.gif_max_duration_secs)
+                            .unwrap_or(10);
+
+                        let mut record_button = ui.add(
                             Button::image_and_text(
                                 egui::include_image!("../icons/video.svg"),
-                                "Record video",
+                                "Record video (R)",
                             )
                             .min_size(Vec2::new(available_width / 2.0 - 5.0, 25.0)),
-                        )
-                        .clicked()
-                    {
-                        let captures_dir = global_state
-                            .prefs
-                            .as_ref()
-                            .map(|prefs| prefs.captures_directory.clone())
-                            .unwrap_or("./".to_string());
-
-                        global_state
-                            .thermal_capturer_settings
-                            .recorders
-                            .push(Arc::new(Mutex::new(VideoRecorder::new(
-                                PathBuf::from(captures_dir),
-                                "video".to_string(),
-                                self.video_format,
-                            ))));
-                        let settings_clone = global_state.thermal_capturer_settings.clone();
-                        if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut()
-                        {
-                            thermal_capturer.set_settings(settings_clone);
+                        );
+                        if self.video_format == VideoFormat::GIF {
+                            record_button = record_button.on_hover_text(format!(
+                                "Records a short animated GIF (at most {} s)",
+                                gif_max_duration
+                            ));
+                        }
+                        if record_button.clicked() {
+                            let timelapse = (self.timelapse
+                                && self.video_format != VideoFormat::GIF)
+                                .then(|| Timelapse {
+                                    interval: Duration::from_secs_f32(self.timelapse_interval_secs),
+                                    output_framerate: self.timelapse_output_fps,
+                                });
+                            self.capture_error = global_state
+                                .start_recording(self.video_format, timelapse)
+                                .err()
+                                .map(|err| err.to_string());
                         }
                     }
                 });
             });
+
+            if let Some(capture_error) = self.capture_error.as_ref() {
+                ui.colored_label(ui.visuals().error_fg_color, capture_error);
+            }
+
+            ui.separator();
+            self.report_ui(ui, &mut global_state);
         });
+
+        self.persist_formats(&mut global_state);
     }
 }
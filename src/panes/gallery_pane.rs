@@ -3,14 +3,33 @@ use std::{
     collections::VecDeque,
     path::{Path, PathBuf},
     rc::Rc,
+    time::{Duration, Instant},
 };
 
-use eframe::egui::{self, scroll_area::ScrollBarVisibility, Align, Image, Layout, Ui, Vec2};
+use eframe::egui::{
+    self, scroll_area::ScrollBarVisibility, Align, Button, Image, Layout, Sense, Ui, Vec2,
+};
 
 use crate::{
-    pane_dispatcher::Pane, types::media_formats::all_media_file_extensions, AppGlobalState,
+    captures_watcher::CapturesWatcher,
+    pane_dispatcher::Pane,
+    types::media_formats::{all_media_file_extensions, is_video_file},
+    util::{open_in_system, reveal_in_folder},
+    AppGlobalState,
 };
 
+// How often the gallery drops files which have been deleted outside of the application
+const EXISTENCE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+enum GalleryAction {
+    View,
+    OpenInSystem,
+    RevealInFolder,
+    CopyPath,
+    // Asks for a confirmation first
+    Delete,
+}
+
 #[derive(Debug, Clone)]
 pub struct GalleryElement {
     pub path: PathBuf,
@@ -19,11 +38,24 @@ pub struct GalleryElement {
 
 pub struct GalleryPane {
     global_state: Rc<RefCell<AppGlobalState>>,
+    // File shown in the viewer window
+    viewed: Option<PathBuf>,
+    // File waiting for the confirmation of its deletion
+    pending_delete: Option<PathBuf>,
+    // Shown when opening, revealing or deleting a file failed
+    action_error: Option<String>,
+    last_existence_check: Instant,
 }
 
 impl GalleryPane {
     pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> GalleryPane {
-        GalleryPane { global_state }
+        GalleryPane {
+            global_state,
+            viewed: None,
+            pending_delete: None,
+            action_error: None,
+            last_existence_check: Instant::now(),
+        }
     }
 }
 
@@ -33,14 +65,23 @@ impl Pane for GalleryPane {
     }
 
     fn ui(&mut self, ui: &mut egui::Ui) {
-        if let Err(err) = self.init_gallery() {
-            eprintln!("Failed to initialize gallery: {:?}", err);
+        if let Err(err) = self.init_gallery(ui.ctx()) {
+            log::error!("Failed to initialize gallery: {:?}", err);
         }
         let global_state_clone = self.global_state.clone();
-        let global_state = global_state_clone.as_ref().borrow_mut();
+        let mut global_state = global_state_clone.as_ref().borrow_mut();
+
+        if self.last_existence_check.elapsed() >= EXISTENCE_CHECK_INTERVAL {
+            self.last_existence_check = Instant::now();
+            global_state.gallery.retain(|elem| elem.path.exists());
+            if self.viewed.as_ref().is_some_and(|path| !path.exists()) {
+                self.viewed = None;
+            }
+        }
 
         // Width of each element in the gallery
         const ELEM_WIDTH: f32 = 150.0;
+        let mut action = None;
 
         egui::ScrollArea::vertical()
             .scroll_bar_visibility(ScrollBarVisibility::AlwaysVisible)
@@ -61,13 +102,50 @@ impl Pane for GalleryPane {
 
                             ui.add_sized(Vec2::new(container_width, 110.0), |ui: &mut Ui| {
                                 ui.vertical_centered(|ui| {
-                                    ui.add(
-                                        Image::new(
-                                            "file://".to_string() + elem.path.to_str().unwrap(),
+                                    let thumbnail = ui
+                                        .add(
+                                            Image::new(
+                                                "file://".to_string() + elem.path.to_str().unwrap(),
+                                            )
+                                            .fit_to_exact_size(Vec2::new(ELEM_WIDTH, 100.0))
+                                            .maintain_aspect_ratio(true)
+                                            .sense(Sense::click()),
                                         )
-                                        .fit_to_exact_size(Vec2::new(ELEM_WIDTH, 100.0))
-                                        .maintain_aspect_ratio(true),
-                                    );
+                                        .on_hover_text("Click to view, right click for more");
+                                    if thumbnail.clicked() {
+                                        action = Some((GalleryAction::View, elem.path.clone()));
+                                    }
+                                    thumbnail.context_menu(|ui| {
+                                        if ui.button("View").clicked() {
+                                            action = Some((GalleryAction::View, elem.path.clone()));
+                                            ui.close_menu();
+                                        }
+                                        if ui.button("Open in system viewer").clicked() {
+                                            action = Some((
+                                                GalleryAction::OpenInSystem,
+                                                elem.path.clone(),
+                                            ));
+                                            ui.close_menu();
+                                        }
+                                        if ui.button("Open containing folder").clicked() {
+                                            action = Some((
+                                                GalleryAction::RevealInFolder,
+                                                elem.path.clone(),
+                                            ));
+                                            ui.close_menu();
+                                        }
+                                        if ui.button("Copy path").clicked() {
+                                            action =
+                                                Some((GalleryAction::CopyPath, elem.path.clone()));
+                                            ui.close_menu();
+                                        }
+                                        ui.separator();
+                                        if ui.button("Delete").clicked() {
+                                            action =
+                                                Some((GalleryAction::Delete, elem.path.clone()));
+                                            ui.close_menu();
+                                        }
+                                    });
                                     ui.label(base_name);
                                     ui.add_space(2.0);
                                 })
@@ -76,72 +154,223 @@ impl Pane for GalleryPane {
                         }
                     },
                 );
+                if let Some(error) = self.action_error.as_ref() {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
             });
+
+        if let Some(path) = self.viewed.clone() {
+            let mut open = true;
+            egui::Window::new(
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            )
+            .id(egui::Id::new("gallery_viewer"))
+            .open(&mut open)
+            .default_size([640.0, 480.0])
+            .resizable(true)
+            .show(ui.ctx(), |ui| {
+                ui.horizontal(|ui| {
+                    let open_label = if is_video_file(&path) {
+                        "Open in system player"
+                    } else {
+                        "Open in system viewer"
+                    };
+                    if ui.button(open_label).clicked() {
+                        action = Some((GalleryAction::OpenInSystem, path.clone()));
+                    }
+                    if ui.button("Open containing folder").clicked() {
+                        action = Some((GalleryAction::RevealInFolder, path.clone()));
+                    }
+                    if ui
+                        .add(Button::new(
+                            egui::RichText::new("Delete").color(ui.visuals().error_fg_color),
+                        ))
+                        .clicked()
+                    {
+                        action = Some((GalleryAction::Delete, path.clone()));
+                    }
+                });
+                if is_video_file(&path) {
+                    ui.weak("Videos are played in the system player, this is a preview.");
+                }
+                // The image loaders show the first frame of videos
+                ui.add(Image::new("file://".to_string() + &path.to_string_lossy()).shrink_to_fit());
+            });
+            if !open {
+                self.viewed = None;
+            }
+        }
+
+        if let Some(path) = self.pending_delete.clone() {
+            let mut confirmed = None;
+            egui::Window::new("Delete file?")
+                .id(egui::Id::new("gallery_delete_confirmation"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!(
+                        "{} will be deleted from the disk.",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add(Button::new(
+                                egui::RichText::new("Delete").color(ui.visuals().error_fg_color),
+                            ))
+                            .clicked()
+                        {
+                            confirmed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            confirmed = Some(false);
+                        }
+                    });
+                });
+            match confirmed {
+                Some(true) => {
+                    self.pending_delete = None;
+                    self.action_error = None;
+                    match std::fs::remove_file(&path) {
+                        Ok(()) => {
+                            // Keep the gallery in sync, so that the element disappears immediately
+                            global_state.gallery.retain(|elem| elem.path != path);
+                            if self.viewed.as_ref() == Some(&path) {
+                                self.viewed = None;
+                            }
+                        }
+                        Err(err) => self.action_error = Some(err.to_string()),
+                    }
+                }
+                Some(false) => self.pending_delete = None,
+                None => {}
+            }
+        }
+
+        if let Some((action, path)) = action {
+            self.action_error = None;
+            let result = match action {
+                GalleryAction::View => {
+                    self.viewed = Some(path);
+                    Ok(())
+                }
+                GalleryAction::OpenInSystem => open_in_system(&path),
+                GalleryAction::RevealInFolder => reveal_in_folder(&path),
+                GalleryAction::CopyPath => {
+                    ui.output_mut(|o| o.copied_text = path.to_string_lossy().to_string());
+                    Ok(())
+                }
+                GalleryAction::Delete => {
+                    self.pending_delete = Some(path);
+                    Ok(())
+                }
+            };
+            if let Err(err) = result {
+                self.action_error = Some(err.to_string());
+            }
+        }
     }
 }
 
 impl GalleryPane {
     // Loads files from the captures directory and initializes the gallery
-    fn init_gallery(&mut self) -> Result<(), anyhow::Error> {
+    fn init_gallery(&mut self, ctx: &egui::Context) -> Result<(), anyhow::Error> {
         let global_state_clone = self.global_state.clone();
         let mut global_state = global_state_clone.as_ref().borrow_mut();
 
-        if global_state.did_init_gallery {
-            return Ok(());
-        }
-        global_state.did_init_gallery = true;
+        let captures_dir = PathBuf::from(
+            global_state
+                .prefs
+                .as_ref()
+                .map(|prefs| prefs.captures_directory.clone())
+                .unwrap_or("./".to_string()),
+        );
 
-        let captures_dir = global_state
-            .prefs
+        // (Re)start watching when the gallery is first shown or the captures directory changes
+        let watching_current_dir = global_state
+            .captures_watcher
             .as_ref()
-            .map(|prefs| prefs.captures_directory.clone())
-            .unwrap_or("./".to_string());
+            .is_some_and(|watcher| watcher.directory() == captures_dir);
+        if !watching_current_dir {
+            let ctx = ctx.clone();
+            global_state.captures_watcher =
+                Some(CapturesWatcher::new(captures_dir.clone(), move || {
+                    ctx.request_repaint()
+                }));
+            global_state.did_init_gallery = false;
+        }
 
-        let captures_dir = Path::new(&captures_dir);
+        let directory_changed = global_state
+            .captures_watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.take_change());
 
-        if !captures_dir.exists() {
+        if global_state.did_init_gallery && !directory_changed {
             return Ok(());
         }
-        let all_known_extensions = all_media_file_extensions();
-        let mut gallery_vec: Vec<GalleryElement> = captures_dir
-            .read_dir()?
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                let ext = path.extension()?.to_string_lossy().to_string();
-
-                // Only generate thumbnails for:
-                // - files
-                // - files with known extensions
-                // - files that are at least 256 bytes in size, to avoid generating thumbnails for empty and corrupt files
-                let size_ok = entry
-                    .metadata()
-                    .ok()
-                    .map(|metadata| metadata.len() >= 256)
-                    .unwrap_or(false);
-                if path.is_file() && all_known_extensions.contains(&ext) && size_ok {
-                    let metadata = entry.metadata().ok()?;
-
-                    Some(GalleryElement {
-                        path,
-                        created_at: metadata.created().ok()?,
-                    })
-                } else {
-                    None
+
+        let mut gallery_vec = scan_captures_dir(&captures_dir)?;
+        if global_state.did_init_gallery {
+            // Merge with the current elements, which may have been saved to a different directory
+            for elem in global_state.gallery.drain(..) {
+                if elem.path.exists()
+                    && !gallery_vec.iter().any(|scanned| scanned.path == elem.path)
+                {
+                    gallery_vec.push(elem);
                 }
-            })
-            .collect();
+            }
+        }
+        global_state.did_init_gallery = true;
 
         gallery_vec.sort_by(|a, b| a.created_at.cmp(&b.created_at));
 
-        // Limit the vector to the last 20 items
-        let last_items = gallery_vec.iter().rev().take(20).collect::<Vec<_>>();
-
-        global_state.gallery = VecDeque::with_capacity(20);
-        for item in last_items {
-            global_state.gallery.push_back(item.clone());
-        }
+        // Keep only the newest items, oldest first like the captures appended after recording
+        let max_items = global_state.gallery_max_items();
+        let skipped = gallery_vec.len().saturating_sub(max_items);
+        global_state.gallery = gallery_vec
+            .into_iter()
+            .skip(skipped)
+            .collect::<VecDeque<_>>();
 
         Ok(())
     }
 }
+
+// Lists the media files in the captures directory, in no particular order
+fn scan_captures_dir(captures_dir: &Path) -> Result<Vec<GalleryElement>, anyhow::Error> {
+    if !captures_dir.exists() {
+        return Ok(vec![]);
+    }
+    let all_known_extensions = all_media_file_extensions();
+    Ok(captures_dir
+        .read_dir()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            let ext = path.extension()?.to_string_lossy().to_string();
+
+            // Only generate thumbnails for:
+            // - files
+            // - files with known extensions
+            // - files that are at least 256 bytes in size, to avoid generating thumbnails for empty and corrupt files
+            let size_ok = entry
+                .metadata()
+                .ok()
+                .map(|metadata| metadata.len() >= 256)
+                .unwrap_or(false);
+            if path.is_file() && all_known_extensions.contains(&ext) && size_ok {
+                let metadata = entry.metadata().ok()?;
+
+                Some(GalleryElement {
+                    path,
+                    created_at: metadata.created().ok()?,
+                })
+            } else {
+                None
+            }
+        })
+        .collect())
+}
@@ -1,20 +1,52 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fs::File, io::BufWriter, path::PathBuf, rc::Rc};
 
+use anyhow::Result;
+use chrono::{DateTime, Local};
 use eframe::{egui, emath::Vec2b, epaint::Color32};
 use egui_plot::{Bar, BarChart, Plot, VLine};
 
-use crate::{pane_dispatcher::Pane, temperature::TemperatureUnit, AppGlobalState};
+use crate::{
+    pane_dispatcher::Pane,
+    temperature::{format_temperature_value, Temp, TempRange, TemperatureUnit},
+    thermal_data::ThermalDataHistogram,
+    util::unique_path,
+    AppGlobalState,
+};
 
 pub struct HistogramPane {
     global_state: Rc<RefCell<AppGlobalState>>,
+    // Outcome of the last CSV export, shown next to the export button
+    last_export_message: Option<String>,
+    // Start and end of the range being drag selected on the plot, in the preferred unit
+    drag_selection: Option<(f64, f64)>,
 }
 
 impl HistogramPane {
     pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> HistogramPane {
-        HistogramPane { global_state }
+        HistogramPane {
+            global_state,
+            last_export_message: None,
+            drag_selection: None,
+        }
     }
 }
 
+fn export_histogram_csv(
+    histogram: &ThermalDataHistogram,
+    destination_folder: PathBuf,
+    unit: TemperatureUnit,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(&destination_folder)?;
+    let current_local: DateTime<Local> = Local::now();
+    let path = unique_path(
+        &destination_folder,
+        &format!("histogram_{}", current_local.format("%Y-%m-%d_%H-%M-%S")),
+        "csv",
+    );
+    histogram.write_csv(BufWriter::new(File::create(&path)?), unit)?;
+    Ok(path)
+}
+
 impl Pane for HistogramPane {
     fn title(&self) -> egui::WidgetText {
         "Histogram".into()
@@ -22,7 +54,63 @@ impl Pane for HistogramPane {
 
     fn ui(&mut self, ui: &mut egui::Ui) {
         let global_state_clone = self.global_state.clone();
-        let global_state = global_state_clone.as_ref().borrow_mut();
+        let mut global_state = global_state_clone.as_ref().borrow_mut();
+
+        ui.horizontal(|ui| {
+            let histogram = global_state
+                .last_thermal_capturer_result
+                .as_ref()
+                .map(|r| &r.histogram);
+            if ui
+                .add_enabled(
+                    histogram.is_some(),
+                    egui::Button::new("Export histogram CSV"),
+                )
+                .on_hover_text("Saves the current histogram to the captures directory")
+                .clicked()
+            {
+                if let Some(histogram) = histogram {
+                    let captures_dir = global_state
+                        .prefs
+                        .as_ref()
+                        .map(|prefs| prefs.captures_directory.clone())
+                        .unwrap_or("./".to_string());
+                    self.last_export_message = Some(
+                        match export_histogram_csv(
+                            histogram,
+                            PathBuf::from(captures_dir),
+                            global_state.preferred_temperature_unit(),
+                        ) {
+                            Ok(path) => format!("Saved to {}", path.to_string_lossy()),
+                            Err(err) => {
+                                log::error!("Failed to export histogram: {}", err);
+                                format!("Export failed: {}", err)
+                            }
+                        },
+                    );
+                }
+            }
+            if let Some(message) = self.last_export_message.as_ref() {
+                ui.weak(message);
+            }
+        });
+        if ui
+            .add(
+                egui::Slider::new(
+                    &mut global_state.thermal_capturer_settings.histogram_buckets,
+                    32..=512,
+                )
+                .logarithmic(true)
+                .text("Buckets"),
+            )
+            .changed()
+        {
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+        ui.weak("Drag on the histogram to set a manual color range");
 
         let default_vec = vec![];
         let temperature_points = global_state
@@ -66,7 +154,21 @@ impl Pane for HistogramPane {
                 })
                 .collect(),
         );
-        let unit_suffix = global_state.preferred_temperature_unit().suffix();
+        let unit = global_state.preferred_temperature_unit();
+        let unit_suffix = unit.suffix();
+        let decimals = global_state.temperature_decimals();
+
+        // Selections are clamped to the temperatures present in the histogram
+        let histogram_extent = temperature_points
+            .first()
+            .zip(temperature_points.last())
+            .map(|(first, last)| {
+                (
+                    first.temperature.to_unit(unit) as f64,
+                    last.temperature.to_unit(unit) as f64,
+                )
+            });
+        let mut selected_range = None;
 
         Plot::new("Temperature distribution plot")
             .auto_bounds(Vec2b::TRUE)
@@ -79,30 +181,59 @@ impl Pane for HistogramPane {
             .include_y(30.0)
             .y_axis_formatter(|grid_mark, _range| format!("{:.0}%", grid_mark.value))
             .x_axis_formatter(move |grid_mark, _range| {
-                format!("{:.0} {}", grid_mark.value, unit_suffix)
+                format!(
+                    "{} {}",
+                    format_temperature_value(grid_mark.value, decimals),
+                    unit_suffix
+                )
             })
+            .allow_drag(false)
             .show(ui, |plot_ui| {
                 plot_ui.bar_chart(chart);
-                if !color_mapping_range.is_default() {
-                    plot_ui.vline(
-                        VLine::new(
-                            color_mapping_range
-                                .min
-                                .to_unit(global_state.preferred_temperature_unit())
-                                as f64,
-                        )
-                        .color(Color32::GRAY),
-                    );
-                    plot_ui.vline(
-                        VLine::new(
-                            color_mapping_range
-                                .max
-                                .to_unit(global_state.preferred_temperature_unit())
-                                as f64,
+
+                let response = plot_ui.response().clone();
+                let pointer_x = plot_ui
+                    .pointer_coordinate()
+                    .zip(histogram_extent)
+                    .map(|(pointer, (min, max))| pointer.x.clamp(min, max));
+                if response.drag_started_by(egui::PointerButton::Primary) {
+                    self.drag_selection = pointer_x.map(|x| (x, x));
+                } else if let (Some((_, end)), Some(x)) = (self.drag_selection.as_mut(), pointer_x)
+                {
+                    *end = x;
+                }
+                let selection = self
+                    .drag_selection
+                    .map(|(start, end)| (start.min(end), start.max(end)));
+                if response.drag_stopped_by(egui::PointerButton::Primary) {
+                    self.drag_selection = None;
+                    selected_range = selection.filter(|(min, max)| max - min > f64::EPSILON);
+                }
+
+                let marked_range = selection.or_else(|| {
+                    (!color_mapping_range.is_default()).then(|| {
+                        (
+                            color_mapping_range.min.to_unit(unit) as f64,
+                            color_mapping_range.max.to_unit(unit) as f64,
                         )
-                        .color(Color32::GRAY),
-                    );
+                    })
+                });
+                if let Some((min, max)) = marked_range {
+                    plot_ui.vline(VLine::new(min).color(Color32::GRAY));
+                    plot_ui.vline(VLine::new(max).color(Color32::GRAY));
                 }
             });
+
+        if let Some((min, max)) = selected_range {
+            global_state.thermal_capturer_settings.manual_range = TempRange::new(
+                Temp::from_unit(unit, min as f32),
+                Temp::from_unit(unit, max as f32),
+            );
+            global_state.thermal_capturer_settings.auto_range = false;
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
     }
 }
@@ -0,0 +1,100 @@
+use std::{cell::RefCell, rc::Rc};
+
+use eframe::{egui, emath::Vec2b};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+use crate::{
+    gizmos::GizmoKind, pane_dispatcher::Pane, temperature::format_temperature_value, AppGlobalState,
+};
+
+//
+// Plots the temperature along each line gizmo, against the distance from its start in pixels.
+//
+pub struct LineProfilePane {
+    global_state: Rc<RefCell<AppGlobalState>>,
+}
+
+impl LineProfilePane {
+    pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> LineProfilePane {
+        LineProfilePane { global_state }
+    }
+}
+
+impl Pane for LineProfilePane {
+    fn title(&self) -> egui::WidgetText {
+        "Line profile".into()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let global_state_clone = self.global_state.clone();
+        let global_state = global_state_clone.as_ref().borrow_mut();
+
+        let (temp_unit, unit_suffix) = global_state.readout_unit();
+        let decimals = global_state.temperature_decimals();
+        let gizmo_results = global_state
+            .last_thermal_capturer_result
+            .as_ref()
+            .map(|r| &r.gizmo_results);
+
+        let lines: Vec<_> = global_state
+            .thermal_capturer_settings
+            .gizmo
+            .children()
+            .unwrap()
+            .iter()
+            .filter(|gizmo| gizmo.enabled)
+            .filter_map(|gizmo| {
+                let GizmoKind::Line { start, end } = gizmo.kind else {
+                    return None;
+                };
+                let samples = &gizmo_results?.get(&gizmo.uuid)?.line_samples;
+                let length = ((end.x as f64 - start.x as f64).powi(2)
+                    + (end.y as f64 - start.y as f64).powi(2))
+                .sqrt();
+                let spacing = if samples.len() > 1 {
+                    length / (samples.len() - 1) as f64
+                } else {
+                    0.0
+                };
+                let points: Vec<[f64; 2]> = samples
+                    .iter()
+                    .enumerate()
+                    .map(|(i, temp)| [i as f64 * spacing, temp.to_unit(temp_unit) as f64])
+                    .collect();
+                Some(
+                    Line::new(PlotPoints::new(points))
+                        .color(gizmo.color)
+                        .name(gizmo.name.clone()),
+                )
+            })
+            .collect();
+
+        if lines.is_empty() {
+            ui.weak("Use the Line tool on the image to add a temperature profile.");
+            return;
+        }
+
+        let label_suffix = unit_suffix.clone();
+        Plot::new("Line profile plot")
+            .auto_bounds(Vec2b::TRUE)
+            .legend(Legend::default())
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_boxed_zoom(false)
+            .x_axis_label("Distance (px)")
+            .y_axis_label(format!("Temperature ({})", unit_suffix))
+            .label_formatter(move |name, p| {
+                format!(
+                    "{}\n{:.0} px: {} {}",
+                    name,
+                    p.x,
+                    format_temperature_value(p.y, decimals),
+                    label_suffix
+                )
+            })
+            .show(ui, |plot_ui| {
+                lines.into_iter().for_each(|line| plot_ui.line(line));
+            });
+    }
+}
@@ -1,23 +1,224 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashSet, rc::Rc, time::Duration};
 
 use eframe::{
     egui::{
         self,
         color_picker::{color_picker_color32, Alpha},
-        Area, Frame, Grid, Image, ImageButton, Key, Order, Response, TextEdit, Ui, Widget,
+        Area, Button, ComboBox, DragValue, Frame, Grid, Id, Image, ImageButton, Key, Order,
+        Response, RichText, TextEdit, Ui, Widget,
     },
     epaint::Color32,
 };
+use uuid::Uuid;
 
-use crate::{gizmos::GizmoKind, pane_dispatcher::Pane, AppGlobalState};
+use crate::{
+    alarm_monitor::alarm_pulse_color,
+    emissivity_presets::{emissivity_preset_combo, preset_for},
+    gizmos::{GizmoKind, MeasurementPreset, CENTER_SPOT_UUID},
+    pane_dispatcher::Pane,
+    temperature::{Temp, TemperatureUnit},
+    temperature_edit_field::{emissivity_edit_field, temperature_edit_field},
+    AppGlobalState,
+};
 
 pub struct MeasurementsPane {
     global_state: Rc<RefCell<AppGlobalState>>,
+    // Gizmos selected for a new delta measurement
+    delta_a: Option<Uuid>,
+    delta_b: Option<Uuid>,
+    // Rect gizmos for which "Custom" has been chosen in the emissivity presets
+    custom_emissivity: HashSet<Uuid>,
+    // Name under which the current measurements are saved as a preset
+    preset_name: String,
+    // Preset chosen in the presets combo box
+    selected_preset: Option<String>,
 }
 
 impl MeasurementsPane {
     pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> MeasurementsPane {
-        MeasurementsPane { global_state }
+        MeasurementsPane {
+            global_state,
+            delta_a: None,
+            delta_b: None,
+            custom_emissivity: HashSet::new(),
+            preset_name: String::new(),
+            selected_preset: None,
+        }
+    }
+
+    //
+    // Saving the user measurements as a named preset and loading them back.
+    //
+    fn presets_ui(&mut self, ui: &mut Ui, global_state: &mut AppGlobalState) {
+        let preset_names: Vec<String> = global_state
+            .prefs
+            .as_ref()
+            .map(|p| {
+                p.measurement_presets
+                    .iter()
+                    .map(|preset| preset.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if self
+            .selected_preset
+            .as_ref()
+            .is_some_and(|name| !preset_names.contains(name))
+        {
+            self.selected_preset = None;
+        }
+
+        let mut prefs_changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Preset");
+            ComboBox::from_id_source("measurement_preset")
+                .selected_text(self.selected_preset.clone().unwrap_or("Select".to_string()))
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    for name in preset_names.iter() {
+                        ui.selectable_value(&mut self.selected_preset, Some(name.clone()), name);
+                    }
+                });
+            let selected = self.selected_preset.clone().and_then(|name| {
+                global_state
+                    .prefs
+                    .as_ref()?
+                    .measurement_presets
+                    .iter()
+                    .find(|preset| preset.name == name)
+                    .cloned()
+            });
+            if ui
+                .add_enabled(selected.is_some(), Button::new("Load"))
+                .on_hover_text("Replace the measurements with the preset, Max and Min are kept")
+                .clicked()
+            {
+                if let Some(preset) = selected.as_ref() {
+                    preset.apply_to(&mut global_state.thermal_capturer_settings.gizmo);
+                    self.delta_a = None;
+                    self.delta_b = None;
+                    let settings_clone = global_state.thermal_capturer_settings.clone();
+                    if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                        thermal_capturer.set_settings(settings_clone);
+                    }
+                }
+            }
+            if ui
+                .add_enabled(selected.is_some(), Button::new("Delete"))
+                .clicked()
+            {
+                if let Some(prefs) = global_state.prefs.as_mut() {
+                    prefs
+                        .measurement_presets
+                        .retain(|preset| Some(&preset.name) != self.selected_preset.as_ref());
+                    prefs_changed = true;
+                }
+                self.selected_preset = None;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.preset_name)
+                    .hint_text("Preset name")
+                    .desired_width(100.0),
+            );
+            let name = self.preset_name.trim().to_string();
+            let overwrite = preset_names.contains(&name);
+            if ui
+                .add_enabled(
+                    !name.is_empty(),
+                    Button::new(if overwrite { "Overwrite" } else { "Save" }),
+                )
+                .on_hover_text("Save the measurements, except Max and Min, as a preset")
+                .clicked()
+            {
+                let preset = MeasurementPreset::from_root(
+                    name.clone(),
+                    &global_state.thermal_capturer_settings.gizmo,
+                );
+                if let Some(prefs) = global_state.prefs.as_mut() {
+                    match prefs
+                        .measurement_presets
+                        .iter_mut()
+                        .find(|existing| existing.name == name)
+                    {
+                        Some(existing) => *existing = preset,
+                        None => prefs.measurement_presets.push(preset),
+                    }
+                    prefs_changed = true;
+                }
+                self.selected_preset = Some(name);
+                self.preset_name.clear();
+            }
+        });
+        ui.weak("Positions are stored in image pixels, presets assume the same resolution and rotation.");
+
+        if prefs_changed {
+            global_state.save_prefs();
+        }
+    }
+
+    //
+    // Row for adding a measurement of the difference between two other gizmos.
+    //
+    fn add_delta_ui(&mut self, ui: &mut Ui, global_state: &mut AppGlobalState) {
+        let candidates: Vec<(Uuid, String)> = global_state
+            .thermal_capturer_settings
+            .gizmo
+            .children()
+            .unwrap()
+            .iter()
+            .filter(|gizmo| !matches!(gizmo.kind, GizmoKind::Delta { .. }))
+            .map(|gizmo| (gizmo.uuid, gizmo.name.clone()))
+            .collect();
+        let name_of = |uuid: Option<Uuid>| {
+            candidates
+                .iter()
+                .find(|(candidate, _)| Some(*candidate) == uuid)
+                .map(|(_, name)| name.clone())
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Δ");
+            for (id, selected) in [
+                ("delta_a", &mut self.delta_a),
+                ("delta_b", &mut self.delta_b),
+            ] {
+                ComboBox::from_id_source(id)
+                    .selected_text(name_of(*selected).unwrap_or("Select".to_string()))
+                    .width(100.0)
+                    .show_ui(ui, |ui| {
+                        for (uuid, name) in candidates.iter() {
+                            ui.selectable_value(selected, Some(*uuid), name.as_str());
+                        }
+                    });
+            }
+            let (Some(a), Some(b)) = (self.delta_a, self.delta_b) else {
+                ui.add_enabled(false, Button::new("Add delta"));
+                return;
+            };
+            if ui
+                .add_enabled(
+                    a != b && name_of(Some(a)).is_some() && name_of(Some(b)).is_some(),
+                    Button::new("Add delta"),
+                )
+                .on_hover_text("Measure the difference between the two temperatures")
+                .clicked()
+            {
+                global_state.thermal_capturer_settings.gizmo.push_child(
+                    GizmoKind::Delta { a, b },
+                    format!(
+                        "{} − {}",
+                        name_of(Some(a)).unwrap_or_default(),
+                        name_of(Some(b)).unwrap_or_default()
+                    ),
+                );
+                let settings_clone = global_state.thermal_capturer_settings.clone();
+                if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                    thermal_capturer.set_settings(settings_clone);
+                }
+            }
+        });
     }
 }
 
@@ -32,11 +233,30 @@ impl Pane for MeasurementsPane {
 
         Grid::new("measurements_pane_grid")
             .striped(true)
-            .num_columns(5)
+            .num_columns(9)
             .min_col_width(40.0)
             .show(ui, |ui| {
+                let (temp_unit, unit_suffix) = global_state.readout_unit();
+                let decimals = global_state.temperature_decimals();
+                let units_in_header = global_state
+                    .prefs
+                    .as_ref()
+                    .map(|p| p.measurement_units_in_header)
+                    .unwrap_or(false);
+                // Suffix appended to each readout, empty when the unit is shown in the header
+                let readout_suffix = if units_in_header {
+                    String::new()
+                } else {
+                    format!(" {}", unit_suffix)
+                };
+
                 ui.label("");
-                ui.label("Value");
+                ui.label("");
+                if units_in_header {
+                    ui.label(format!("Temp. ({})", unit_suffix));
+                } else {
+                    ui.label("Value");
+                }
                 ui.label("Value");
                 ui.end_row();
 
@@ -46,10 +266,44 @@ impl Pane for MeasurementsPane {
                     .map(|r| r.gizmo_results.clone())
                     .clone();
 
-                let temp_unit = global_state.preferred_temperature_unit();
+                let averaging_window = Duration::from_millis(
+                    global_state
+                        .prefs
+                        .as_ref()
+                        .map(|p| p.readout_averaging_window_ms)
+                        .unwrap_or_default(),
+                );
+                let last_capture_time = global_state
+                    .last_thermal_capturer_result
+                    .as_ref()
+                    .map(|r| r.capture_time);
 
                 let mut gizmo_uuid_to_remove = Option::None;
+                // Whether the capturer needs the updated gizmos
+                let mut gizmos_changed = false;
 
+                let allow_expressions = global_state
+                    .prefs
+                    .as_ref()
+                    .map(|p| p.temperature_expressions)
+                    .unwrap_or(true);
+                let alarm_color = alarm_pulse_color(ui.input(|i| i.time));
+                if global_state.alarm_monitor.any_active() {
+                    ui.ctx().request_repaint();
+                }
+
+                let global_state = &mut *global_state;
+                // Used to tell if the gizmos referenced by a delta still exist
+                let gizmo_uuids: Vec<Uuid> = global_state
+                    .thermal_capturer_settings
+                    .gizmo
+                    .children()
+                    .unwrap()
+                    .iter()
+                    .map(|gizmo| gizmo.uuid)
+                    .collect();
+                let history_data_collector = &global_state.history_data_collector;
+                let alarm_monitor = &global_state.alarm_monitor;
                 global_state
                     .thermal_capturer_settings
                     .gizmo
@@ -60,38 +314,214 @@ impl Pane for MeasurementsPane {
                         let icon = Image::new(match gizmo.kind {
                             GizmoKind::MaxTemp => egui::include_image!("../icons/flame.svg"),
                             GizmoKind::MinTemp => egui::include_image!("../icons/snowflake.svg"),
-                            GizmoKind::TempAt { pos: _ } => {
+                            GizmoKind::TempAt { .. } => {
                                 egui::include_image!("../icons/crosshair_center.svg")
                             }
+                            GizmoKind::IntegratedAboveAmbient { region: _ } => {
+                                egui::include_image!("../icons/maximize.svg")
+                            }
+                            GizmoKind::Line { start: _, end: _ } => {
+                                egui::include_image!("../icons/spline.svg")
+                            }
+                            GizmoKind::Rect { .. } => egui::include_image!("../icons/square.svg"),
                             _ => egui::include_image!("../icons/flame.svg"),
                         });
 
-                        color_icon_rgb(
-                            ui,
-                            ImageButton::new(icon.tint(gizmo.color)).frame(false),
-                            &mut gizmo.color,
-                            Alpha::Opaque,
-                        );
+                        if let GizmoKind::Delta { .. } = gizmo.kind {
+                            color_icon_rgb(
+                                ui,
+                                Button::new(RichText::new("Δ").color(gizmo.color).strong())
+                                    .frame(false),
+                                &mut gizmo.color,
+                                Alpha::Opaque,
+                            );
+                        } else {
+                            color_icon_rgb(
+                                ui,
+                                ImageButton::new(icon.tint(gizmo.color)).frame(false),
+                                &mut gizmo.color,
+                                Alpha::Opaque,
+                            );
+                        }
+
+                        gizmos_changed |= ui
+                            .checkbox(&mut gizmo.enabled, "")
+                            .on_hover_text(
+                                "Enable the measurement. \
+                                Disabled measurements are kept, but not computed or recorded.",
+                            )
+                            .changed();
 
-                        ui.label(
-                            gizmo_results
+                        // Averaged readout, if averaging is enabled and there is history to average
+                        let averaged = last_capture_time
+                            .filter(|_| gizmo.enabled && !averaging_window.is_zero())
+                            .and_then(|now| {
+                                // The window may reach before the clock started (shortly after boot)
+                                let from = now
+                                    .checked_sub(averaging_window)
+                                    .or_else(|| history_data_collector.oldest_time(gizmo.uuid))?;
+                                history_data_collector.average_temperature(gizmo.uuid, from, now)
+                            });
+                        let readout = match averaged {
+                            Some((temperature, _)) => Some(format!(
+                                "~{}{}",
+                                temperature.format(temp_unit, decimals),
+                                readout_suffix
+                            )),
+                            None => gizmo_results
                                 .as_ref()
                                 .and_then(|gr| gr.get(&gizmo.uuid))
                                 .map(|r| {
                                     format!(
-                                        "{:.1} {}",
-                                        r.temperature.to_unit(temp_unit),
-                                        temp_unit.suffix()
+                                        "{}{}",
+                                        r.temperature.format(temp_unit, decimals),
+                                        readout_suffix
                                     )
+                                }),
+                        };
+                        let gizmo_result =
+                            gizmo_results.as_ref().and_then(|gr| gr.get(&gizmo.uuid));
+                        let trend = last_capture_time.filter(|_| gizmo.enabled).and_then(|now| {
+                            let from = now
+                                .checked_sub(TREND_WINDOW)
+                                .or_else(|| history_data_collector.oldest_time(gizmo.uuid))?;
+                            history_data_collector.temperature_slope(
+                                gizmo.uuid,
+                                from,
+                                now,
+                                TREND_MIN_SAMPLES,
+                            )
+                        });
+                        if let GizmoKind::Delta { a, b } = gizmo.kind {
+                            let referenced_exist =
+                                gizmo_uuids.contains(&a) && gizmo_uuids.contains(&b);
+                            let delta = gizmo_result.and_then(|r| r.delta);
+                            let mut readout_text = RichText::new(match delta {
+                                Some(delta) if referenced_exist => format!(
+                                    "{}{}",
+                                    delta.format_diff(temp_unit, decimals),
+                                    readout_suffix
+                                ),
+                                _ if !referenced_exist => "—".to_string(),
+                                _ => " - ".to_string(),
+                            });
+                            if gizmo_result.map_or(false, |r| r.clamped) {
+                                readout_text = readout_text.color(ui.visuals().warn_fg_color);
+                            }
+                            ui.label(readout_text).on_hover_text(if referenced_exist {
+                                "Difference between the two measurements"
+                            } else {
+                                "A measurement used by this difference was deleted"
+                            });
+                        } else if let Some(integrated_above_ambient) =
+                            gizmo_result.and_then(|r| r.integrated_above_ambient)
+                        {
+                            ui.label(format!("{:.0} K·px", integrated_above_ambient))
+                                .on_hover_text(INTEGRATED_ABOVE_AMBIENT_HELP);
+                        } else if let (GizmoKind::Rect { .. }, Some(r)) =
+                            (&gizmo.kind, gizmo_result)
+                        {
+                            let mut readout_text = RichText::new(format!(
+                                "{} / {} / {}{}",
+                                r.min.format(temp_unit, decimals),
+                                r.avg.format(temp_unit, decimals),
+                                r.max.format(temp_unit, decimals),
+                                readout_suffix
+                            ));
+                            if r.clamped {
+                                readout_text = readout_text.color(ui.visuals().warn_fg_color);
+                            }
+                            if alarm_monitor.is_active(gizmo.uuid) {
+                                readout_text = readout_text.color(alarm_color).strong();
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label(readout_text)
+                                    .on_hover_text("Minimum / average / maximum in the rectangle");
+                                trend_label(ui, trend, temp_unit);
+                            });
+                        } else {
+                            let clamped = gizmo_result.map_or(false, |r| r.clamped);
+                            let mut readout_text =
+                                RichText::new(readout.clone().unwrap_or(" - ".to_string()));
+                            if clamped {
+                                readout_text = readout_text.color(ui.visuals().warn_fg_color);
+                            }
+                            if alarm_monitor.is_active(gizmo.uuid) {
+                                readout_text = readout_text.color(alarm_color).strong();
+                            }
+                            let readout_response = ui
+                                .horizontal(|ui| {
+                                    let response = ui.label(readout_text);
+                                    trend_label(ui, trend, temp_unit);
+                                    response
                                 })
-                                .unwrap_or(" - ".to_string()),
-                        );
+                                .inner;
+                            if readout.is_some() {
+                                let mut hover_text = match averaged {
+                                    Some((_, samples)) => format!(
+                                        "Averaged over the last {:.1} s ({} samples)",
+                                        averaging_window.as_secs_f32(),
+                                        samples
+                                    ),
+                                    None => "Instantaneous reading".to_string(),
+                                };
+                                if clamped {
+                                    hover_text += "\nClamped to the valid range of the camera";
+                                }
+                                readout_response.on_hover_text(hover_text);
+                            }
+                        }
 
                         ui.add_sized(
                             [100.0, 20.0],
                             TextEdit::singleline(&mut gizmo.name).desired_width(100.0),
                         );
 
+                        if let GizmoKind::TempAt { radius, .. } = &mut gizmo.kind {
+                            gizmos_changed |= ui
+                                .add(DragValue::new(radius).range(0..=20).suffix(" px"))
+                                .on_hover_text(
+                                    "Averaging radius, 0 reads the temperature of a single pixel",
+                                )
+                                .changed();
+                        } else if let GizmoKind::Rect { emissivity, .. } = &mut gizmo.kind {
+                            ui.horizontal(|ui| {
+                                let mut overridden = emissivity.is_some();
+                                if ui
+                                    .checkbox(&mut overridden, "")
+                                    .on_hover_text(
+                                        "Override the emissivity of the region. \
+                                        Uses the reflected temperature set in the Setup pane.",
+                                    )
+                                    .changed()
+                                {
+                                    *emissivity = overridden.then_some(0.95);
+                                    gizmos_changed = true;
+                                }
+                                if let Some(emissivity) = emissivity.as_mut() {
+                                    let mut is_custom =
+                                        self.custom_emissivity.contains(&gizmo.uuid);
+                                    gizmos_changed |= emissivity_preset_combo(
+                                        ui,
+                                        ("emissivity_preset", gizmo.uuid),
+                                        emissivity,
+                                        &mut is_custom,
+                                    );
+                                    if is_custom {
+                                        self.custom_emissivity.insert(gizmo.uuid);
+                                    } else {
+                                        self.custom_emissivity.remove(&gizmo.uuid);
+                                    }
+                                    if is_custom || preset_for(*emissivity).is_none() {
+                                        gizmos_changed |=
+                                            emissivity_edit_field(ui, emissivity).changed();
+                                    }
+                                }
+                            });
+                        } else {
+                            ui.label("");
+                        }
+
                         match gizmo.kind {
                             GizmoKind::MaxTemp => {
                                 ui.label("");
@@ -99,6 +529,10 @@ impl Pane for MeasurementsPane {
                             GizmoKind::MinTemp => {
                                 ui.label("");
                             }
+                            // Managed by the center spot preference
+                            _ if gizmo.uuid == CENTER_SPOT_UUID => {
+                                ui.label("");
+                            }
                             _ => {
                                 if ui
                                     .add(
@@ -140,6 +574,40 @@ impl Pane for MeasurementsPane {
                             gizmo.show_temperature_label = !gizmo.show_temperature_label;
                         }
 
+                        if ui
+                            .add(
+                                ImageButton::new(
+                                    Image::new(egui::include_image!("../icons/lock.svg")).tint(
+                                        if gizmo.locked {
+                                            ui.style().visuals.widgets.active.fg_stroke.color
+                                        } else {
+                                            ui.style().visuals.widgets.inactive.fg_stroke.color
+                                        },
+                                    ),
+                                )
+                                .frame(false),
+                            )
+                            .on_hover_text("Lock the position on the image")
+                            .clicked()
+                        {
+                            gizmo.locked = !gizmo.locked;
+                        }
+
+                        // Alarms compare absolute temperatures
+                        if let GizmoKind::Delta { .. } = gizmo.kind {
+                            ui.label("");
+                        } else {
+                            let current_temperature = gizmo_result.map(|r| r.temperature);
+                            alarm_icon(
+                                ui,
+                                &mut gizmo.alarm_above,
+                                &mut gizmo.alarm_below,
+                                temp_unit,
+                                allow_expressions,
+                                current_temperature,
+                            );
+                        }
+
                         ui.end_row();
                     });
 
@@ -150,45 +618,161 @@ impl Pane for MeasurementsPane {
                         .children_mut()
                         .unwrap()
                         .retain(|gizmo| gizmo.uuid != *uuid);
+                    gizmos_changed = true;
                 });
+
+                if gizmos_changed {
+                    let settings_clone = global_state.thermal_capturer_settings.clone();
+                    if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                        thermal_capturer.set_settings(settings_clone);
+                    }
+                }
             });
+
+        self.add_delta_ui(ui, &mut global_state);
+        ui.separator();
+        self.presets_ui(ui, &mut global_state);
     }
 }
 
+// Span of the history the trend arrows are computed from
+const TREND_WINDOW: Duration = Duration::from_secs(5);
+// Fewer samples are too noisy to tell a trend
+const TREND_MIN_SAMPLES: usize = 5;
+// Rate of change (K/s) below which a temperature is considered stable, 1 K per minute
+const TREND_THRESHOLD: f32 = 1.0 / 60.0;
+
+//
+// Arrow showing whether a temperature is rising, falling or stable, given its rate of change.
+//
+fn trend_label(ui: &mut Ui, slope: Option<f32>, unit: TemperatureUnit) {
+    let Some(slope) = slope else {
+        return;
+    };
+    let (arrow, color) = if slope > TREND_THRESHOLD {
+        ("↑", Color32::from_rgb(255, 110, 80))
+    } else if slope < -TREND_THRESHOLD {
+        ("↓", Color32::from_rgb(90, 160, 255))
+    } else {
+        ("→", ui.visuals().weak_text_color())
+    };
+    ui.label(RichText::new(arrow).color(color).strong())
+        .on_hover_text(format!(
+            "{} {}/min over the last {} s",
+            Temp::new(slope * 60.0).format_diff(unit, 2),
+            unit.suffix(),
+            TREND_WINDOW.as_secs()
+        ));
+}
+
+const INTEGRATED_ABOVE_AMBIENT_HELP: &str =
+    "Sum of the temperature above ambient over the region. \
+    Useful for comparing areas, not an absolute energy measurement.";
+
+//
+// Bell icon opening a popup to edit the alarm thresholds of a gizmo.
+// `current_temperature` is used as the initial threshold when an alarm is enabled.
+//
+fn alarm_icon(
+    ui: &mut Ui,
+    alarm_above: &mut Option<Temp>,
+    alarm_below: &mut Option<Temp>,
+    unit: TemperatureUnit,
+    allow_expressions: bool,
+    current_temperature: Option<Temp>,
+) -> Response {
+    let popup_id = ui.auto_id_with("alarm_popup");
+    let has_alarm = alarm_above.is_some() || alarm_below.is_some();
+    let button_response = ui
+        .add(
+            ImageButton::new(Image::new(egui::include_image!("../icons/bell.svg")).tint(
+                if has_alarm {
+                    ui.style().visuals.widgets.active.fg_stroke.color
+                } else {
+                    ui.style().visuals.widgets.inactive.fg_stroke.color
+                },
+            ))
+            .frame(false),
+        )
+        .on_hover_text("Alarm thresholds");
+
+    button_popup(ui, popup_id, &button_response, |ui| {
+        Grid::new("alarm_thresholds_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (label, threshold) in
+                    [("Above", &mut *alarm_above), ("Below", &mut *alarm_below)]
+                {
+                    let mut enabled = threshold.is_some();
+                    if ui.checkbox(&mut enabled, label).changed() {
+                        *threshold =
+                            enabled.then(|| current_temperature.unwrap_or(Temp::from_celsius(0.0)));
+                    }
+                    match threshold.as_mut() {
+                        Some(threshold) => {
+                            temperature_edit_field(ui, unit, allow_expressions, threshold);
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+
+    button_response
+}
+
 pub fn color_icon_rgb(ui: &mut Ui, icon: impl Widget, rgb: &mut Color32, alpha: Alpha) -> Response {
     let popup_id = ui.auto_id_with("popup");
-    let _open = ui.memory(|mem| mem.is_popup_open(popup_id));
     let mut button_response = ui.add(icon);
     if ui.style().explanation_tooltips {
         button_response = button_response.on_hover_text("Click to edit color");
     }
 
+    const COLOR_SLIDER_WIDTH: f32 = 210.0;
+    let changed = button_popup(ui, popup_id, &button_response, |ui| {
+        ui.spacing_mut().slider_width = COLOR_SLIDER_WIDTH;
+        color_picker_color32(ui, rgb, alpha)
+    });
+    if changed == Some(true) {
+        button_response.mark_changed();
+    }
+
+    button_response
+}
+
+//
+// Toggles a popup at the corner of the button when it is clicked, the popup also closes
+// on Escape or a click elsewhere.
+// Returns the result of `add_contents` when the popup is open.
+//
+fn button_popup<R>(
+    ui: &Ui,
+    popup_id: Id,
+    button_response: &Response,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> Option<R> {
     if button_response.clicked() {
         ui.memory_mut(|mem| mem.toggle_popup(popup_id));
     }
+    if !ui.memory(|mem| mem.is_popup_open(popup_id)) {
+        return None;
+    }
 
-    const COLOR_SLIDER_WIDTH: f32 = 210.0;
-    if ui.memory(|mem| mem.is_popup_open(popup_id)) {
-        let area_response = Area::new(popup_id)
-            .order(Order::Foreground)
-            .fixed_pos(button_response.rect.max)
-            .constrain(true)
-            .show(ui.ctx(), |ui| {
-                ui.spacing_mut().slider_width = COLOR_SLIDER_WIDTH;
-                Frame::popup(ui.style()).show(ui, |ui| {
-                    if color_picker_color32(ui, rgb, alpha) {
-                        button_response.mark_changed();
-                    }
-                });
-            })
-            .response;
+    let area = Area::new(popup_id)
+        .order(Order::Foreground)
+        .fixed_pos(button_response.rect.max)
+        .constrain(true)
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style()).show(ui, add_contents).inner
+        });
 
-        if !button_response.clicked()
-            && (ui.input(|i| i.key_pressed(Key::Escape)) || area_response.clicked_elsewhere())
-        {
-            ui.memory_mut(|mem| mem.close_popup());
-        }
+    if !button_response.clicked()
+        && (ui.input(|i| i.key_pressed(Key::Escape)) || area.response.clicked_elsewhere())
+    {
+        ui.memory_mut(|mem| mem.close_popup());
     }
-
-    button_response
+    Some(area.inner)
 }
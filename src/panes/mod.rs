@@ -1,6 +1,7 @@
 pub mod capture_pane;
 pub mod gallery_pane;
 pub mod histogram_pane;
+pub mod line_profile_pane;
 pub mod measurements_pane;
 pub mod performance_stats_pane;
 pub mod setup_pane;
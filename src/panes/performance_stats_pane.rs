@@ -12,6 +12,8 @@ pub static EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
 pub struct PerformanceStatsPane {
     global_state: Rc<RefCell<AppGlobalState>>,
     fps_chart_data: Vec<[f64; 2]>,
+    // Highest sharpness seen since the last reset, the focus bar is relative to it
+    peak_sharpness: f32,
 }
 
 impl PerformanceStatsPane {
@@ -19,6 +21,7 @@ impl PerformanceStatsPane {
         Self {
             global_state,
             fps_chart_data: vec![[0.0, 0.0]; 100],
+            peak_sharpness: 0.0,
         }
     }
 }
@@ -73,6 +76,33 @@ impl Pane for PerformanceStatsPane {
                 );
                 ui.end_row();
 
+                let sharpness = global_state
+                    .last_thermal_capturer_result
+                    .as_ref()
+                    .map(|r| r.sharpness);
+                if let Some(sharpness) = sharpness {
+                    self.peak_sharpness = self.peak_sharpness.max(sharpness);
+                }
+                ui.label("Focus")
+                    .on_hover_text("Sharpness of the image, adjust the focus to maximize it");
+                ui.horizontal(|ui| {
+                    ui.label(
+                        sharpness
+                            .map(|s| format!("{:.3}", s))
+                            .unwrap_or("-".to_string()),
+                    );
+                    let fraction = match sharpness {
+                        Some(s) if self.peak_sharpness > 0.0 => s / self.peak_sharpness,
+                        _ => 0.0,
+                    };
+                    ui.add(egui::ProgressBar::new(fraction).desired_width(100.0))
+                        .on_hover_text("Relative to the sharpest frame seen");
+                    if ui.small_button("Reset").clicked() {
+                        self.peak_sharpness = 0.0;
+                    }
+                });
+                ui.end_row();
+
                 ui.label("Chart");
 
                 let reported_fps = global_state
@@ -1,21 +1,33 @@
 use std::cell::RefCell;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
 
 use anyhow::Error;
-use eframe::egui::{self, Button, CollapsingHeader};
+use eframe::egui::{self, Button, CollapsingHeader, Color32, DragValue, Grid};
 use eframe::egui::{RichText, WidgetText};
 use eframe::epaint::text::LayoutJob;
 use nokhwa::utils::CameraIndex;
 use nokhwa::Camera;
 
+use crate::auto_display_range_controller::auto_range_settings_editor;
+use crate::camera_adapter::{is_supported_usb_device, CAMERA_ADAPTERS};
 use crate::camera_enumerator::{enumerate_cameras, EnumeratedCamera};
 use crate::dynamic_range_curve::dynamic_curve_editor;
+use crate::emissivity_presets::{emissivity_preset_combo, preset_for};
+use crate::gizmos::GizmoKind;
+use crate::gradient_editor_widget::{GradientEditorAction, GradientEditorView};
 use crate::gradient_selector_widget::GradientSelectorView;
 use crate::pane_dispatcher::Pane;
 
-use crate::temperature_edit_field::temperature_range_edit_field;
-use crate::thermal_capturer::ThermalCapturer;
+use crate::temperature::{Temp, TempRange};
+use crate::temperature_edit_field::{
+    emissivity_edit_field, temperature_edit_field, temperature_range_edit_field,
+};
+use crate::thermal_capturer::{
+    AmbientSource, EdgeOverlaySettings, ThermalCapturer, ThermalCapturerSettings,
+};
+use crate::thermal_data_source::{CameraSource, FileReplaySource, ThermalDataSource};
 use crate::types::image_rotation::ImageRotation;
 use crate::AppGlobalState;
 
@@ -25,34 +37,231 @@ pub struct SetupPane {
     global_state: Rc<RefCell<AppGlobalState>>,
     cameras: Result<Vec<EnumeratedCamera>, Error>,
     selected_camera_index: CameraIndex,
+    // Camera shown as a picture-in-picture inset next to the primary one
+    secondary_camera_index: Option<CameraIndex>,
     open_camera_error: Option<String>,
     gradient_selector: GradientSelectorView,
+    // Open while a custom gradient is being created or edited
+    gradient_editor: Option<GradientEditorView>,
+    // Band and color of the isotherm, kept while it is disabled
+    isotherm: (TempRange, Color32),
+    // Raw thermal file to replay instead of a camera
+    replay_path: String,
+    replay_loop: bool,
+    // "Custom" has been chosen in the emissivity presets
+    custom_emissivity: bool,
+}
+
+//
+// The preferred camera if it is connected and supported, otherwise the first supported camera.
+//
+fn default_camera_index(
+    cameras: &Result<Vec<EnumeratedCamera>, Error>,
+    preferred_camera: Option<&str>,
+) -> CameraIndex {
+    let Ok(cameras) = cameras.as_ref() else {
+        return CameraIndex::Index(0);
+    };
+    let supported = || cameras.iter().filter(|camera| camera.adapter.is_some());
+    preferred_camera
+        .and_then(|key| supported().find(|camera| camera.preference_key() == key))
+        .or_else(|| supported().next())
+        .map(|camera| camera.info.index().clone())
+        .unwrap_or(CameraIndex::Index(0))
 }
 
 impl SetupPane {
     pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> SetupPane {
         let cameras = enumerate_cameras().inspect_err(|err| {
-            eprintln!("Failed to enumerate cameras: {:#}", err);
+            log::error!("Failed to enumerate cameras: {:#}", err);
         });
+        let preferred_camera = global_state
+            .as_ref()
+            .borrow()
+            .prefs
+            .as_ref()
+            .and_then(|p| p.preferred_camera.clone());
 
         SetupPane {
+            selected_camera_index: default_camera_index(&cameras, preferred_camera.as_deref()),
             global_state,
-            selected_camera_index: cameras
-                .as_ref()
-                .ok()
-                .and_then(|cameras| {
-                    cameras
-                        .iter()
-                        .find(|camera| camera.adapter.is_some())
-                        .map(|camera| camera.info.index().clone())
-                })
-                .unwrap_or(CameraIndex::Index(0)),
             cameras,
+            secondary_camera_index: None,
             open_camera_error: None,
             gradient_selector: GradientSelectorView::new(),
+            gradient_editor: None,
+            isotherm: (
+                TempRange::new(Temp::from_celsius(30.0), Temp::from_celsius(40.0)),
+                Color32::from_rgb(0, 255, 0),
+            ),
+            replay_path: String::new(),
+            replay_loop: true,
+            custom_emissivity: false,
+        }
+    }
+
+    fn isotherm_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        global_state: &mut AppGlobalState,
+        allow_expressions: bool,
+    ) {
+        let mut enabled = global_state.thermal_capturer_settings.isotherm.is_some();
+        let (mut band, mut color) = global_state
+            .thermal_capturer_settings
+            .isotherm
+            .unwrap_or(self.isotherm);
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut enabled, "Isotherm")
+                .on_hover_text("Highlight the temperatures inside the band with a solid color")
+                .changed();
+            changed |= ui.color_edit_button_srgba(&mut color).changed();
+        });
+        changed |= temperature_range_edit_field(
+            ui,
+            "isotherm_range",
+            enabled,
+            global_state.preferred_temperature_unit(),
+            allow_expressions,
+            &mut band,
+        )
+        .changed();
+
+        if changed {
+            self.isotherm = (band, color);
+            global_state.thermal_capturer_settings.isotherm = enabled.then_some((band, color));
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+    }
+
+    fn custom_gradients_ui(&mut self, ui: &mut egui::Ui, global_state: &mut AppGlobalState) {
+        if let Some(editor) = self.gradient_editor.as_mut() {
+            match editor.draw(ui) {
+                GradientEditorAction::Save(gradient) => {
+                    if let Some(prefs) = global_state.prefs.as_mut() {
+                        match prefs
+                            .custom_gradients
+                            .iter_mut()
+                            .find(|g| g.uuid == gradient.uuid)
+                        {
+                            Some(existing) => *existing = gradient.clone(),
+                            None => prefs.custom_gradients.push(gradient.clone()),
+                        }
+                        global_state.save_prefs();
+                    }
+                    // Apply the changes right away if the edited gradient is in use
+                    if global_state.thermal_capturer_settings.gradient.uuid == gradient.uuid {
+                        global_state.thermal_capturer_settings.gradient = gradient;
+                        let settings_clone = global_state.thermal_capturer_settings.clone();
+                        if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut()
+                        {
+                            thermal_capturer.set_settings(settings_clone);
+                        }
+                    }
+                    self.gradient_editor = None;
+                }
+                GradientEditorAction::Cancel => {
+                    self.gradient_editor = None;
+                }
+                GradientEditorAction::None => {}
+            }
+            return;
+        }
+
+        let mut gradient_to_delete = None;
+        if let Some(prefs) = global_state.prefs.as_ref() {
+            for gradient in prefs.custom_gradients.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(gradient.name.clone());
+                    if ui.button("Edit").clicked() {
+                        self.gradient_editor = Some(GradientEditorView::new(gradient.clone()));
+                    }
+                    if ui.button("Delete").clicked() {
+                        gradient_to_delete = Some(gradient.uuid);
+                    }
+                });
+            }
+        }
+        if let Some(uuid) = gradient_to_delete {
+            if let Some(prefs) = global_state.prefs.as_mut() {
+                prefs.custom_gradients.retain(|g| g.uuid != uuid);
+                global_state.save_prefs();
+            }
+        }
+        if ui.button("New gradient").clicked() {
+            self.gradient_editor = Some(GradientEditorView::new_gradient());
         }
     }
 
+    fn secondary_camera_ui(&mut self, ui: &mut egui::Ui, global_state: &mut AppGlobalState) {
+        ui.label("Comparison camera");
+        if global_state.secondary_capturer_inst.is_some() {
+            if ui.button("Close comparison camera").clicked() {
+                global_state.secondary_capturer_inst = None;
+                global_state.last_secondary_capturer_result = None;
+            }
+            return;
+        }
+
+        let Ok(cameras) = self.cameras.as_ref() else {
+            return;
+        };
+        // Any other camera with an adapter can be opened for comparison
+        let candidates: Vec<(CameraIndex, LayoutJob, LayoutJob)> = cameras
+            .iter()
+            .filter(|camera| {
+                camera.adapter.is_some() && camera.info.index() != &self.selected_camera_index
+            })
+            .map(|camera| {
+                (
+                    camera.info.index().clone(),
+                    camera.rich_text_name(true),
+                    camera.rich_text_name(false),
+                )
+            })
+            .collect();
+        if candidates.is_empty() {
+            ui.weak("Connect a second supported camera to compare them side by side.");
+            return;
+        }
+        if !candidates
+            .iter()
+            .any(|(index, ..)| Some(index) == self.secondary_camera_index.as_ref())
+        {
+            self.secondary_camera_index = Some(candidates[0].0.clone());
+        }
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("secondary_camera_combo")
+                .selected_text(
+                    candidates
+                        .iter()
+                        .find(|(index, ..)| Some(index) == self.secondary_camera_index.as_ref())
+                        .map(|(_, short_name, _)| short_name.clone())
+                        .unwrap_or_default(),
+                )
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    for (index, _, name) in candidates.iter() {
+                        ui.selectable_value(
+                            &mut self.secondary_camera_index,
+                            Some(index.clone()),
+                            name.clone(),
+                        );
+                    }
+                });
+            if ui.button("Open").clicked() {
+                let _ = self.open_secondary_camera(ui.ctx(), global_state);
+            }
+        });
+    }
+
     fn selected_camera_info(&self) -> Option<&EnumeratedCamera> {
         self.cameras.as_ref().ok().and_then(|cameras| {
             cameras
@@ -61,43 +270,180 @@ impl SetupPane {
         })
     }
 
+    // Opens the camera with the given index through its adapter
+    fn camera_source(&self, index: &CameraIndex) -> Result<CameraSource> {
+        let adapter = self
+            .cameras
+            .as_ref()
+            .ok()
+            .and_then(|cameras| cameras.iter().find(|camera| camera.info.index() == index))
+            .and_then(|i| i.adapter.as_ref())
+            .context("No camera selected")?;
+        let camera = Camera::new(index.clone(), adapter.requested_format())
+            .context("Failed to open camera")?;
+        Ok(CameraSource::new(camera, adapter.clone()))
+    }
+
     fn open_selected_camera(
         &mut self,
         ctx: &egui::Context,
         global_state: &mut AppGlobalState,
     ) -> Result<()> {
-        let adapter = self
-            .selected_camera_info()
-            .and_then(|i| i.adapter.as_ref())
-            .context("No camera selected")?;
-        let cloned_ctx = ctx.clone();
-        let cloned_adapter = adapter.clone();
+        self.camera_source(&self.selected_camera_index)
+            .map(|source| {
+                global_state.thermal_capturer_inst = Some(start_capturer(
+                    ctx,
+                    global_state.thermal_capturer_settings.clone(),
+                    Box::new(source),
+                ));
+                global_state.opened_camera = self
+                    .selected_camera_info()
+                    .map(|camera| camera.preference_key());
+                global_state.disconnected_camera = None;
+                self.open_camera_error = None;
+            })
+            .inspect_err(|err| {
+                self.open_camera_error = Some(format!("{:#}", err));
+            })
+    }
 
-        Camera::new(
-            self.selected_camera_index.clone(),
-            adapter.requested_format(),
-        )
-        .map(|cam| {
-            // Create thermal capturer
-
-            global_state.thermal_capturer_inst = Some(ThermalCapturer::new(
-                cam,
-                cloned_adapter,
-                global_state.thermal_capturer_settings.clone(),
-                Arc::new(move || {
-                    cloned_ctx.request_repaint(); // repaint so that the result can be read out
-                }),
-            ))
-            .map(|mut capturer| {
-                capturer.start();
-                capturer
-            });
-            self.open_camera_error = None;
-        })
-        .inspect_err(|err| {
-            self.open_camera_error = Some(format!("Failed to open camera: {}", err));
-        })
-        .context("Failed to open camera")
+    fn open_secondary_camera(
+        &mut self,
+        ctx: &egui::Context,
+        global_state: &mut AppGlobalState,
+    ) -> Result<()> {
+        let index = self
+            .secondary_camera_index
+            .clone()
+            .context("No comparison camera selected")?;
+        self.camera_source(&index)
+            .map(|source| {
+                global_state.secondary_capturer_inst = Some(start_capturer(
+                    ctx,
+                    global_state.secondary_capturer_settings(),
+                    Box::new(source),
+                ));
+                self.open_camera_error = None;
+            })
+            .inspect_err(|err| {
+                self.open_camera_error = Some(format!("{:#}", err));
+            })
+    }
+
+    fn open_replay(
+        &mut self,
+        ctx: &egui::Context,
+        global_state: &mut AppGlobalState,
+    ) -> Result<()> {
+        FileReplaySource::new(Path::new(self.replay_path.trim()), self.replay_loop)
+            .map(|source| {
+                global_state.thermal_capturer_inst = Some(start_capturer(
+                    ctx,
+                    global_state.thermal_capturer_settings.clone(),
+                    Box::new(source),
+                ));
+                global_state.opened_camera = None;
+                global_state.disconnected_camera = None;
+                self.open_camera_error = None;
+            })
+            .inspect_err(|err| {
+                self.open_camera_error = Some(format!("Failed to open the file: {:#}", err));
+            })
+    }
+}
+
+// Creates and starts the thermal capturer reading from the given source
+fn start_capturer(
+    ctx: &egui::Context,
+    settings: ThermalCapturerSettings,
+    source: Box<dyn ThermalDataSource>,
+) -> ThermalCapturer {
+    let cloned_ctx = ctx.clone();
+    let mut capturer = ThermalCapturer::new(
+        source,
+        settings,
+        Arc::new(move || {
+            cloned_ctx.request_repaint(); // repaint so that the result can be read out
+        }),
+    );
+    capturer.start();
+    capturer
+}
+
+//
+// Captures or clears the flat field reference used for the non-uniformity correction.
+//
+fn flat_field_ui(ui: &mut egui::Ui, global_state: &mut AppGlobalState) {
+    let settings = &mut global_state.thermal_capturer_settings;
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                global_state.thermal_capturer_inst.is_some() && !settings.capture_flat_field,
+                Button::new("Capture flat field"),
+            )
+            .on_hover_text(
+                "Point the camera at a uniform surface (e.g. a lens cap) and click. \
+                The deviation of each pixel from the mean is subtracted from the following frames.",
+            )
+            .clicked()
+        {
+            settings.capture_flat_field = true;
+            changed = true;
+        }
+        if settings.nuc_offsets.is_some() && ui.button("Clear").clicked() {
+            settings.nuc_offsets = None;
+            changed = true;
+        }
+    });
+    if settings.capture_flat_field {
+        ui.label("Capturing the flat field...");
+    } else if settings.nuc_offsets.is_some() {
+        ui.label("Flat field correction active");
+    }
+
+    if changed {
+        let settings_clone = global_state.thermal_capturer_settings.clone();
+        if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+            thermal_capturer.set_settings(settings_clone);
+        }
+    }
+}
+
+//
+// Limits the rate the frames are processed at, to save CPU and the size of long recordings.
+//
+fn frame_rate_limit_ui(ui: &mut egui::Ui, global_state: &mut AppGlobalState) {
+    let settings = &mut global_state.thermal_capturer_settings;
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut limited = settings.target_fps.is_some();
+        if ui
+            .checkbox(&mut limited, "Limit frame rate")
+            .on_hover_text("Drop frames to process and record no more than the given frame rate")
+            .changed()
+        {
+            settings.target_fps = limited.then_some(5.0);
+            changed = true;
+        }
+        if let Some(target_fps) = settings.target_fps.as_mut() {
+            changed |= ui
+                .add(
+                    DragValue::new(target_fps)
+                        .range(1.0..=60.0)
+                        .speed(0.1)
+                        .max_decimals(1)
+                        .suffix(" FPS"),
+                )
+                .changed();
+        }
+    });
+
+    if changed {
+        let settings_clone = global_state.thermal_capturer_settings.clone();
+        if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+            thermal_capturer.set_settings(settings_clone);
+        }
     }
 }
 
@@ -116,35 +462,52 @@ impl Pane for SetupPane {
             }
         }
 
-        if let Some(_evt) = global_state
+        // Unrelated USB devices are ignored, enumerating the cameras can stall the UI
+        let supported_device_changed = global_state
             .hotplug_detector
             .as_mut()
-            .and_then(|r| r.receiver.try_recv().ok())
-        {
+            .map(|r| {
+                r.receiver
+                    .try_iter()
+                    .filter(|evt| {
+                        let (vendor_id, product_id) = evt.vid_pid();
+                        is_supported_usb_device(vendor_id, product_id)
+                    })
+                    .count()
+                    > 0
+            })
+            .unwrap_or(false);
+        if supported_device_changed {
             self.cameras = enumerate_cameras().inspect_err(|err| {
-                eprintln!("Failed to enumerate cameras: {:#}", err);
+                log::error!("Failed to enumerate cameras: {:#}", err);
             });
             if global_state.should_try_open_camera_on_next_hotplug
                 && global_state.thermal_capturer_inst.is_none()
             {
-                // select a camera with an adapter if possible
-                if !self
-                    .selected_camera_info()
-                    .as_ref()
-                    .map(|i| i.adapter.is_some())
-                    .unwrap_or(false)
-                {
-                    self.selected_camera_index = self
-                        .cameras
+                // select the disconnected camera, the preferred camera,
+                // or a camera with an adapter if possible
+                let preferred_camera = global_state.disconnected_camera.clone().or_else(|| {
+                    global_state
+                        .prefs
                         .as_ref()
-                        .ok()
-                        .and_then(|cameras| {
-                            cameras
-                                .iter()
-                                .find(|camera| camera.adapter.is_some())
-                                .map(|camera| camera.info.index().clone())
+                        .and_then(|p| p.preferred_camera.clone())
+                });
+                let preferred_connected = preferred_camera.as_ref().is_some_and(|key| {
+                    self.cameras.as_ref().is_ok_and(|cameras| {
+                        cameras.iter().any(|camera| {
+                            camera.adapter.is_some() && camera.preference_key() == *key
                         })
-                        .unwrap_or(CameraIndex::Index(0));
+                    })
+                });
+                if preferred_connected
+                    || !self
+                        .selected_camera_info()
+                        .as_ref()
+                        .map(|i| i.adapter.is_some())
+                        .unwrap_or(false)
+                {
+                    self.selected_camera_index =
+                        default_camera_index(&self.cameras, preferred_camera.as_deref());
                 }
 
                 // try to open the camera
@@ -196,8 +559,56 @@ impl Pane for SetupPane {
                 );
             }
         }
+        if let Some(selected_key) = self
+            .selected_camera_info()
+            .filter(|camera| camera.adapter.is_some())
+            .map(|camera| camera.preference_key())
+        {
+            let mut is_default = global_state
+                .prefs
+                .as_ref()
+                .is_some_and(|p| p.preferred_camera.as_ref() == Some(&selected_key));
+            if ui
+                .checkbox(&mut is_default, "Default camera")
+                .on_hover_text(
+                    "Select this camera on startup when several supported cameras are connected",
+                )
+                .changed()
+            {
+                if let Some(prefs) = global_state.prefs.as_mut() {
+                    prefs.preferred_camera = is_default.then_some(selected_key);
+                    global_state.save_prefs();
+                }
+            }
+        }
 
-        if global_state.should_try_open_camera_on_next_hotplug
+        let show_stream_info = global_state
+            .prefs
+            .as_ref()
+            .map(|p| p.show_stream_info)
+            .unwrap_or(true);
+        if let Some(capturer) = global_state
+            .thermal_capturer_inst
+            .as_ref()
+            .filter(|_| show_stream_info)
+        {
+            stream_info_ui(ui, capturer);
+        }
+
+        let no_supported_camera = self
+            .cameras
+            .as_ref()
+            .map(|cameras| cameras.iter().all(|camera| camera.adapter.is_none()))
+            .unwrap_or(false);
+        let show_onboarding = global_state
+            .prefs
+            .as_ref()
+            .map(|p| p.show_camera_onboarding && !p.show_unsupported_cameras)
+            .unwrap_or(false);
+
+        if no_supported_camera && show_onboarding && global_state.thermal_capturer_inst.is_none() {
+            no_supported_camera_ui(ui);
+        } else if global_state.should_try_open_camera_on_next_hotplug
             && global_state.thermal_capturer_inst.is_none()
         {
             ui.colored_label(
@@ -220,9 +631,31 @@ impl Pane for SetupPane {
                 let _ = self.open_selected_camera(ui.ctx(), &mut global_state);
                 global_state.should_try_open_camera_on_next_hotplug = true;
             }
-        } else if ui.button("Close Camera").clicked() {
-            global_state.thermal_capturer_inst = None;
-            global_state.should_try_open_camera_on_next_hotplug = false;
+
+            ui.label("Or replay a raw thermal file (.bin)");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.replay_path)
+                        .hint_text("Path to file")
+                        .desired_width(200.0),
+                );
+                ui.checkbox(&mut self.replay_loop, "Loop");
+                if ui
+                    .add_enabled(!self.replay_path.trim().is_empty(), Button::new("Replay"))
+                    .clicked()
+                {
+                    let _ = self.open_replay(ui.ctx(), &mut global_state);
+                }
+            });
+        } else {
+            if ui.button("Close Camera").clicked() {
+                global_state.thermal_capturer_inst = None;
+                global_state.secondary_capturer_inst = None;
+                global_state.last_secondary_capturer_result = None;
+                global_state.should_try_open_camera_on_next_hotplug = false;
+                global_state.opened_camera = None;
+            }
+            self.secondary_camera_ui(ui, &mut global_state);
         }
 
         if let Some(error) = &self.open_camera_error {
@@ -231,60 +664,220 @@ impl Pane for SetupPane {
         ui.separator();
         ui.label("Rotation");
         ui.horizontal(|ui| {
+            let mut rotation = global_state.thermal_capturer_settings.rotation;
             if ui
-                .selectable_value(
-                    &mut global_state.thermal_capturer_settings.rotation,
-                    ImageRotation::None,
-                    "None",
-                )
+                .selectable_value(&mut rotation, ImageRotation::None, "None")
                 .changed()
                 || ui
-                    .selectable_value(
-                        &mut global_state.thermal_capturer_settings.rotation,
-                        ImageRotation::Clockwise90,
-                        "90°",
-                    )
+                    .selectable_value(&mut rotation, ImageRotation::Clockwise90, "90°")
                     .changed()
                 || ui
-                    .selectable_value(
-                        &mut global_state.thermal_capturer_settings.rotation,
-                        ImageRotation::Clockwise180,
-                        "180°",
-                    )
+                    .selectable_value(&mut rotation, ImageRotation::Clockwise180, "180°")
                     .changed()
                 || ui
-                    .selectable_value(
-                        &mut global_state.thermal_capturer_settings.rotation,
-                        ImageRotation::Clockwise270,
-                        "270°",
-                    )
+                    .selectable_value(&mut rotation, ImageRotation::Clockwise270, "270°")
                     .changed()
             {
-                let settings_clone = global_state.thermal_capturer_settings.clone();
-                if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
-                    thermal_capturer.set_settings(settings_clone);
-                }
+                global_state.set_rotation(rotation);
             }
         });
+        frame_rate_limit_ui(ui, &mut global_state);
         ui.separator();
 
+        let current_range = global_state
+            .last_thermal_capturer_result
+            .as_ref()
+            .map(|res| res.image_range);
+        let mut auto_range_changed = false;
+        ui.horizontal(|ui| {
+            auto_range_changed = ui
+                .checkbox(
+                    &mut global_state.thermal_capturer_settings.auto_range,
+                    "Auto Range",
+                )
+                .changed();
+            // Same as unchecking auto range, in one click
+            if ui
+                .add_enabled(
+                    global_state.thermal_capturer_settings.auto_range && current_range.is_some(),
+                    egui::Button::new("Lock range"),
+                )
+                .on_hover_text("Stop adjusting the range and keep the current one")
+                .clicked()
+            {
+                global_state.thermal_capturer_settings.auto_range = false;
+                auto_range_changed = true;
+            }
+        });
+        if auto_range_changed {
+            // auto range has been disabled, copy the current range to the manual range
+            if !global_state.thermal_capturer_settings.auto_range {
+                if let Some(range) = current_range {
+                    global_state.thermal_capturer_settings.manual_range = range;
+                }
+            }
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+        if !global_state.thermal_capturer_settings.auto_range {
+            let unit = global_state.preferred_temperature_unit();
+            let decimals = global_state.temperature_decimals();
+            let locked_range = global_state.thermal_capturer_settings.manual_range;
+            ui.label(format!(
+                "Locked range: {} – {} {}",
+                locked_range.min.format(unit, decimals),
+                locked_range.max.format(unit, decimals),
+                unit.suffix()
+            ));
+        }
+        let auto_range = global_state.thermal_capturer_settings.auto_range;
+        if ui
+            .add_enabled(
+                auto_range,
+                egui::Checkbox::new(
+                    &mut global_state
+                        .thermal_capturer_settings
+                        .auto_range_ignore_background,
+                    "Ignore background",
+                ),
+            )
+            .on_hover_text(
+                "Fit the auto range to the warmer part of the scene, \
+                so that a cold background (e.g. the sky) does not reduce the contrast of the subject.",
+            )
+            .changed()
+        {
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
         if ui
             .checkbox(
-                &mut global_state.thermal_capturer_settings.auto_range,
-                "Auto Range",
+                &mut global_state.thermal_capturer_settings.clamp_to_camera_range,
+                "Clamp to camera range",
+            )
+            .on_hover_text(
+                "Limit measurements and the auto range to the temperatures the camera can measure. \
+                Clamped readouts are highlighted.",
             )
             .changed()
         {
-            // auto range has been disabled, copy the current range to the manual range
-            if !global_state.thermal_capturer_settings.auto_range {
-                let range_to_copy = global_state
-                    .last_thermal_capturer_result
-                    .as_ref()
-                    .map(|res| res.image_range);
-                if let Some(range) = range_to_copy {
-                    global_state.thermal_capturer_settings.manual_range = range;
-                }
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+        let unit = global_state.preferred_temperature_unit();
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(
+                    &mut global_state.thermal_capturer_settings.stabilize_extremes,
+                    "Stabilize Max/Min",
+                )
+                .on_hover_text(
+                    "Keep the Max and Min markers in place until another spot \
+                    is hotter (colder) by more than the margin, instead of following sensor noise.",
+                )
+                .changed();
+            let settings = &mut global_state.thermal_capturer_settings;
+            let mut margin = settings.stabilize_extremes_margin.diff_to_unit(unit);
+            if ui
+                .add_enabled(
+                    settings.stabilize_extremes,
+                    egui::DragValue::new(&mut margin)
+                        .range(0.0..=20.0)
+                        .speed(0.1)
+                        .suffix(format!(" {}", unit.suffix())),
+                )
+                .on_hover_text("Margin")
+                .changed()
+            {
+                settings.stabilize_extremes_margin = Temp::from_diff_unit(unit, margin);
+                changed = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Denoise");
+            changed |= ui
+                .add(
+                    egui::Slider::new(
+                        &mut global_state.thermal_capturer_settings.denoise_strength,
+                        0.0..=0.95,
+                    )
+                    .fixed_decimals(2),
+                )
+                .on_hover_text(
+                    "Blend every frame with the previous ones to reduce the sensor noise. \
+                    Higher values are smoother, but slower to follow changes. 0 disables it.",
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Blur");
+            for (kernel_size, label) in [(0, "Off"), (3, "3x3"), (5, "5x5")] {
+                changed |= ui
+                    .selectable_value(
+                        &mut global_state.thermal_capturer_settings.blur_kernel_size,
+                        kernel_size,
+                        label,
+                    )
+                    .on_hover_text("Average every pixel with its neighbours")
+                    .changed();
             }
+            changed |= ui
+                .add_enabled(
+                    global_state.thermal_capturer_settings.blur_kernel_size > 1,
+                    egui::Checkbox::new(
+                        &mut global_state.thermal_capturer_settings.blur_display_only,
+                        "Display only",
+                    ),
+                )
+                .on_hover_text(
+                    "Only blur the displayed image, measurements use the unblurred temperatures",
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Upscale");
+            for factor in 1..=4 {
+                changed |= ui
+                    .selectable_value(
+                        &mut global_state.thermal_capturer_settings.upscale_factor,
+                        factor,
+                        format!("{}x", factor),
+                    )
+                    .on_hover_text(
+                        "Render the image from bilinearly interpolated temperatures. \
+                        Measurements keep using the pixels of the camera.",
+                    )
+                    .changed();
+            }
+        });
+        ui.horizontal(|ui| {
+            let settings = &mut global_state.thermal_capturer_settings;
+            let mut edge_overlay_enabled = settings.edge_overlay.is_some();
+            if ui
+                .checkbox(&mut edge_overlay_enabled, "Edge overlay")
+                .on_hover_text(
+                    "Outline the edges of the scene over the image to help with orientation. \
+                    The edges are detected in the thermal image itself.",
+                )
+                .changed()
+            {
+                settings.edge_overlay = edge_overlay_enabled.then(EdgeOverlaySettings::default);
+                changed = true;
+            }
+            if let Some(edge_overlay) = settings.edge_overlay.as_mut() {
+                changed |= ui
+                    .add(egui::Slider::new(&mut edge_overlay.opacity, 0.1..=1.0).text("Opacity"))
+                    .changed();
+            }
+        });
+        if changed {
             let settings_clone = global_state.thermal_capturer_settings.clone();
             if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
                 thermal_capturer.set_settings(settings_clone);
@@ -296,11 +889,17 @@ impl Pane for SetupPane {
             .last_thermal_capturer_result
             .as_ref()
             .map(|r| r.image_range);
+        let allow_expressions = global_state
+            .prefs
+            .as_ref()
+            .map(|p| p.temperature_expressions)
+            .unwrap_or(true);
         if temperature_range_edit_field(
             ui,
             "range",
             !global_state.thermal_capturer_settings.auto_range,
             global_state.preferred_temperature_unit(),
+            allow_expressions,
             range_copy
                 .as_mut()
                 .and_then(|r| {
@@ -320,6 +919,167 @@ impl Pane for SetupPane {
             }
         }
 
+        ui.horizontal(|ui| {
+            ui.label("Ambient from");
+            // Rectangle measurements which can be used as the ambient reference
+            let rects: Vec<(uuid::Uuid, String)> = global_state
+                .thermal_capturer_settings
+                .gizmo
+                .children()
+                .map(|children| {
+                    children
+                        .iter()
+                        .filter(|g| matches!(g.kind, GizmoKind::Rect { .. }))
+                        .map(|g| (g.uuid, g.name.clone()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let source = &mut global_state.thermal_capturer_settings.ambient_source;
+            let prev_source = *source;
+            egui::ComboBox::from_id_source("ambient_source")
+                .selected_text(match *source {
+                    AmbientSource::Manual => "Manual".to_string(),
+                    AmbientSource::FromGizmo(uuid) => rects
+                        .iter()
+                        .find(|(rect_uuid, _)| *rect_uuid == uuid)
+                        .map(|(_, name)| name.clone())
+                        .unwrap_or("Missing measurement".to_string()),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(source, AmbientSource::Manual, "Manual");
+                    for (uuid, name) in rects.iter() {
+                        ui.selectable_value(source, AmbientSource::FromGizmo(*uuid), name.as_str());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Use the average of a rectangle measurement viewing a surface \
+                    at ambient temperature (e.g. a wall). Add one with the Rect tool.",
+                );
+            if *source != prev_source {
+                let settings_clone = global_state.thermal_capturer_settings.clone();
+                if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                    thermal_capturer.set_settings(settings_clone);
+                }
+            }
+        });
+
+        let ambient_from_gizmo = matches!(
+            global_state.thermal_capturer_settings.ambient_source,
+            AmbientSource::FromGizmo(_)
+        );
+        if ambient_from_gizmo {
+            if let Some(result) = global_state.last_thermal_capturer_result.as_ref() {
+                let unit = global_state.preferred_temperature_unit();
+                ui.label(format!(
+                    "Sensed ambient: {} {}",
+                    result
+                        .ambient
+                        .format(unit, global_state.temperature_decimals()),
+                    unit.suffix()
+                ));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(if ambient_from_gizmo {
+                "Fallback ambient"
+            } else {
+                "Ambient"
+            });
+            let unit = global_state.preferred_temperature_unit();
+            if temperature_edit_field(
+                ui,
+                unit,
+                allow_expressions,
+                &mut global_state.thermal_capturer_settings.ambient,
+            )
+            .on_hover_text("Ambient temperature, used by measurements relative to ambient")
+            .changed()
+            {
+                let settings_clone = global_state.thermal_capturer_settings.clone();
+                if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                    thermal_capturer.set_settings(settings_clone);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let emissivity = &mut global_state.thermal_capturer_settings.emissivity;
+            ui.label("Emissivity")
+                .on_hover_text("Emissivity of the scene, rectangle measurements may override it");
+            let mut changed = emissivity_preset_combo(
+                ui,
+                "scene_emissivity",
+                emissivity,
+                &mut self.custom_emissivity,
+            );
+            if self.custom_emissivity || preset_for(*emissivity).is_none() {
+                changed |= emissivity_edit_field(ui, emissivity).changed();
+            }
+            if changed {
+                let settings_clone = global_state.thermal_capturer_settings.clone();
+                if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                    thermal_capturer.set_settings(settings_clone);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let unit = global_state.preferred_temperature_unit();
+            let settings = &mut global_state.thermal_capturer_settings;
+            let mut separate = settings.reflected_temp.is_some();
+            let mut changed = ui
+                .checkbox(&mut separate, "Reflected")
+                .on_hover_text(
+                    "Reflected apparent temperature, used by the emissivity correction \
+                    of the measurements. When unchecked the ambient temperature is used.",
+                )
+                .changed();
+            if changed {
+                settings.reflected_temp = separate.then_some(settings.ambient);
+            }
+            if let Some(reflected_temp) = settings.reflected_temp.as_mut() {
+                changed |=
+                    temperature_edit_field(ui, unit, allow_expressions, reflected_temp).changed();
+            }
+            if changed {
+                let settings_clone = global_state.thermal_capturer_settings.clone();
+                if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                    thermal_capturer.set_settings(settings_clone);
+                }
+            }
+        });
+
+        let auto_range_heading = if global_state
+            .thermal_capturer_settings
+            .auto_range_settings
+            .is_default()
+        {
+            RichText::new("Auto Range Tuning")
+        } else {
+            RichText::new("Auto Range Tuning *").strong()
+        };
+        CollapsingHeader::new(auto_range_heading)
+            .id_source("auto_range_settings_header")
+            .show(ui, |ui| {
+                if auto_range_settings_editor(
+                    ui,
+                    &mut global_state.thermal_capturer_settings.auto_range_settings,
+                ) {
+                    let settings_clone = global_state.thermal_capturer_settings.clone();
+                    if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                        thermal_capturer.set_settings(settings_clone);
+                    }
+                }
+            });
+
+        ui.separator();
+        self.isotherm_ui(ui, &mut global_state, allow_expressions);
+
+        ui.separator();
+        flat_field_ui(ui, &mut global_state);
+
         ui.separator();
 
         // Curve editor
@@ -363,15 +1123,165 @@ impl Pane for SetupPane {
 
         ui.separator();
 
+        let auto_gradient = global_state
+            .prefs
+            .as_ref()
+            .map(|p| p.auto_gradient)
+            .unwrap_or(false);
+        if auto_gradient {
+            ui.horizontal(|ui| {
+                if global_state.auto_gradient_paused {
+                    ui.label("Automatic palette paused");
+                    if ui.button("Resume").clicked() {
+                        global_state.auto_gradient_paused = false;
+                    }
+                } else {
+                    ui.colored_label(
+                        egui::Color32::GREEN,
+                        format!(
+                            "Palette chosen automatically: {}",
+                            global_state.thermal_capturer_settings.gradient.name
+                        ),
+                    )
+                    .on_hover_text(
+                        "The palette follows the temperature span of the scene. \
+                        Select a palette to override it.",
+                    );
+                }
+            });
+        }
+
+        if ui
+            .toggle_value(
+                &mut global_state.thermal_capturer_settings.invert_gradient,
+                "⇅ Invert gradient",
+            )
+            .on_hover_text("Map cold temperatures to the warm end of the gradient")
+            .changed()
+        {
+            let settings_clone = global_state.thermal_capturer_settings.clone();
+            if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                thermal_capturer.set_settings(settings_clone);
+            }
+        }
+
+        let gradients = global_state.available_gradients();
         if self
             .gradient_selector
-            .draw(ui, &mut global_state.thermal_capturer_settings.gradient)
+            .draw(
+                ui,
+                &mut global_state.thermal_capturer_settings.gradient,
+                &gradients,
+            )
+            .on_hover_text("Press 1-9 to switch between the first nine gradients")
             .changed()
         {
+            if auto_gradient {
+                global_state.auto_gradient_paused = true;
+            }
             let settings_clone = global_state.thermal_capturer_settings.clone();
             if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
                 thermal_capturer.set_settings(settings_clone.clone());
             }
         }
+
+        CollapsingHeader::new("Custom gradients")
+            .id_source("custom_gradients_header")
+            .show(ui, |ui| {
+                self.custom_gradients_ui(ui, &mut global_state);
+            });
+
+        ui.separator();
+
+        CollapsingHeader::new("Developer")
+            .id_source("developer_header")
+            .show(ui, |ui| {
+                if ui
+                    .checkbox(
+                        &mut global_state.thermal_capturer_settings.show_raw_counts,
+                        "Show raw sensor counts (debug)",
+                    )
+                    .on_hover_text(
+                        "Measure and colorize the unprocessed sensor values instead of temperatures. \
+                        Useful when developing support for new cameras.",
+                    )
+                    .changed()
+                {
+                    let settings_clone = global_state.thermal_capturer_settings.clone();
+                    if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
+                        thermal_capturer.set_settings(settings_clone);
+                    }
+                }
+            });
+    }
+}
+
+// Onboarding panel shown when none of the connected cameras is supported
+fn no_supported_camera_ui(ui: &mut egui::Ui) {
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.strong("No supported camera detected");
+        ui.label("Plug in one of the supported cameras to start preview:");
+        for adapter in CAMERA_ADAPTERS.iter() {
+            let ids: Vec<String> = adapter
+                .usb_vid_pids()
+                .iter()
+                .map(|(vid, pid)| format!("{:04x}:{:04x}", vid, pid))
+                .collect();
+            ui.label(format!("• {} (USB {})", adapter.name(), ids.join(", ")));
+        }
+        ui.add_space(4.0);
+        ui.label("Troubleshooting:");
+        ui.label("• Connect the camera directly, avoid hubs and charge-only cables.");
+        if cfg!(target_os = "linux") {
+            ui.label("• Make sure your user can access /dev/video* (e.g. is in the video group).");
+        } else if cfg!(target_os = "macos") {
+            ui.label("• Allow camera access in System Settings → Privacy & Security.");
+        } else if cfg!(target_os = "windows") {
+            ui.label("• Allow desktop apps to access the camera in Privacy settings.");
+        }
+        ui.label("• Enable \"Show unsupported cameras\" in preferences to list all cameras.");
+    });
+}
+
+// Read-only block describing the stream negotiated with the opened camera
+fn stream_info_ui(ui: &mut egui::Ui, capturer: &ThermalCapturer) {
+    let Some((negotiated, expected)) = capturer.stream_formats else {
+        ui.weak("Replaying a recorded file.");
+        return;
+    };
+    Grid::new("stream_info_grid")
+        .num_columns(2)
+        .spacing([10.0, 2.0])
+        .show(ui, |ui| {
+            ui.weak("Resolution");
+            ui.weak(format!(
+                "{}x{}",
+                negotiated.resolution().width(),
+                negotiated.resolution().height()
+            ));
+            ui.end_row();
+
+            ui.weak("Frame format");
+            ui.weak(format!("{:?}", negotiated.format()));
+            ui.end_row();
+
+            ui.weak("Frame rate");
+            ui.weak(format!("{} FPS", negotiated.frame_rate()));
+            ui.end_row();
+        });
+
+    if negotiated.resolution() == expected.resolution() && negotiated.format() == expected.format()
+    {
+        ui.colored_label(egui::Color32::GREEN, "Stream matches the camera adapter.");
+    } else {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!(
+                "Stream does not match the camera adapter (expected {}x{} {:?}).",
+                expected.resolution().width(),
+                expected.resolution().height(),
+                expected.format()
+            ),
+        );
     }
 }
@@ -8,18 +8,45 @@ use eframe::{
     emath::Align2,
     epaint::{Color32, Vec2},
 };
-use egui_plot::{MarkerShape, Plot, PlotBounds, PlotImage, PlotPoint, Points, Text};
+use egui_plot::{
+    Line, LineStyle, MarkerShape, Plot, PlotBounds, PlotImage, PlotPoint, PlotTransform, PlotUi,
+    Points, Text,
+};
 
 use crate::{
-    gizmos::GizmoKind, pane_dispatcher::Pane, thermal_data::ThermalDataPos,
-    widgets::selectable_image_label::SelectableImageLabel, AppGlobalState,
+    alarm_monitor::alarm_pulse_color,
+    gizmos::GizmoKind,
+    pane_dispatcher::Pane,
+    thermal_data::{ThermalDataPos, ThermalDataRect},
+    widgets::selectable_image_label::SelectableImageLabel,
+    AppGlobalState,
 };
 
+// What happens when the user clicks or drags on the image
+#[derive(Clone, Copy, PartialEq)]
+enum GizmoTool {
+    // Clicking adds a temperature point
+    Point,
+    // Dragging adds a region measuring the area above ambient
+    AreaAboveAmbient,
+    // A crosshair follows the cursor, clicking adds it as a temperature point
+    Probe,
+    // Dragging adds a line measuring the temperature profile along it
+    Line,
+    // Dragging adds a rectangle measuring the minimum, maximum and average
+    Rect,
+}
+
+// Width of the secondary camera inset, relative to the width of the view
+const SECONDARY_INSET_FRACTION: f64 = 0.3;
+
 pub struct ThermalDisplayPane {
     global_state: Rc<RefCell<AppGlobalState>>,
 
     camera_texture: Option<egui::TextureHandle>,
     camera_image_size: Option<(usize, usize)>,
+    // Picture-in-picture inset showing the secondary camera
+    secondary_texture: Option<egui::TextureHandle>,
 
     zoom_to_fit: bool,
     external_zoom_factor: f64,
@@ -28,22 +55,50 @@ pub struct ThermalDisplayPane {
     // Uuid of the gizmo which currently has its context menu open
     gizmo_context_menu_uuid: Option<uuid::Uuid>,
 
+    gizmo_tool: GizmoTool,
+    // Where the user started dragging out a new region or line
+    drag_start: Option<ThermalDataPos>,
+    // Uuid of the point gizmo which is being moved by dragging
+    dragged_gizmo_uuid: Option<uuid::Uuid>,
+    // Corner which stays in place while a corner of a rect gizmo is dragged
+    rect_drag_anchor: Option<ThermalDataPos>,
+    // Prevents moving any gizmo, regardless of their own lock
+    lock_all_gizmos: bool,
+    // Show the temperature under the cursor next to it
+    cursor_readout: bool,
+    // Show the plot axes and grid, labelled in image pixels
+    show_grid: bool,
+
     maximized: bool,
 }
 
 impl ThermalDisplayPane {
     pub fn new(global_state: Rc<RefCell<AppGlobalState>>) -> ThermalDisplayPane {
+        let lock_all_gizmos = global_state
+            .borrow()
+            .prefs
+            .as_ref()
+            .is_some_and(|prefs| prefs.lock_all_gizmos);
         ThermalDisplayPane {
             global_state,
             camera_texture: None,
 
             camera_image_size: None,
+            secondary_texture: None,
             zoom_to_fit: true,
             external_zoom_factor: 1.0,
             external_zoom_factor_changed: false,
             maximized: false,
 
             gizmo_context_menu_uuid: None,
+
+            gizmo_tool: GizmoTool::Point,
+            drag_start: None,
+            dragged_gizmo_uuid: None,
+            rect_drag_anchor: None,
+            lock_all_gizmos,
+            cursor_readout: true,
+            show_grid: false,
         }
     }
 
@@ -91,12 +146,8 @@ impl ThermalDisplayPane {
                     ))
                     .clicked()
                 {
-                    global_state.thermal_capturer_settings.rotation =
-                        global_state.thermal_capturer_settings.rotation.next();
-                    let settings_clone = global_state.thermal_capturer_settings.clone();
-                    if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
-                        thermal_capturer.set_settings(settings_clone);
-                    }
+                    let rotation = global_state.thermal_capturer_settings.rotation.next();
+                    global_state.set_rotation(rotation);
                 }
 
                 if ui
@@ -108,13 +159,111 @@ impl ThermalDisplayPane {
                     ))
                     .clicked()
                 {
-                    global_state.thermal_capturer_settings.rotation =
-                        global_state.thermal_capturer_settings.rotation.prev();
-                    let settings_clone = global_state.thermal_capturer_settings.clone();
-                    if let Some(thermal_capturer) = global_state.thermal_capturer_inst.as_mut() {
-                        thermal_capturer.set_settings(settings_clone);
+                    let rotation = global_state.thermal_capturer_settings.rotation.prev();
+                    global_state.set_rotation(rotation);
+                }
+
+                let flip_horizontal = global_state.thermal_capturer_settings.flip_horizontal;
+                let flip_vertical = global_state.thermal_capturer_settings.flip_vertical;
+                if ui
+                    .selectable_label(flip_horizontal, "↔")
+                    .on_hover_text("Mirror the image horizontally")
+                    .clicked()
+                {
+                    global_state.set_flip(!flip_horizontal, flip_vertical);
+                }
+                if ui
+                    .selectable_label(flip_vertical, "↕")
+                    .on_hover_text("Mirror the image vertically")
+                    .clicked()
+                {
+                    global_state.set_flip(flip_horizontal, !flip_vertical);
+                }
+
+                ui.add_space(8.0);
+
+                ui.selectable_value(&mut self.gizmo_tool, GizmoTool::Point, "Point")
+                    .on_hover_text("Click on the image to add a temperature measurement");
+                ui.selectable_value(&mut self.gizmo_tool, GizmoTool::AreaAboveAmbient, "Area")
+                    .on_hover_text("Drag on the image to measure the area above ambient");
+                ui.selectable_value(&mut self.gizmo_tool, GizmoTool::Probe, "Probe")
+                    .on_hover_text("Show the temperature under the cursor, click to keep it");
+                ui.selectable_value(&mut self.gizmo_tool, GizmoTool::Line, "Line")
+                    .on_hover_text(
+                        "Drag on the image to measure the temperature profile along a line",
+                    );
+                ui.selectable_value(&mut self.gizmo_tool, GizmoTool::Rect, "Rect")
+                    .on_hover_text(
+                        "Drag on the image to measure the min, max and average in a rectangle",
+                    );
+                if ui
+                    .toggle_value(&mut self.lock_all_gizmos, "Lock all")
+                    .on_hover_text("Prevent moving any measurement on the image")
+                    .changed()
+                {
+                    // Restored on the next launch
+                    if let Some(prefs) = global_state.prefs.as_mut() {
+                        prefs.lock_all_gizmos = self.lock_all_gizmos;
+                        global_state.save_prefs();
                     }
                 }
+                ui.toggle_value(&mut self.cursor_readout, "Cursor readout")
+                    .on_hover_text("Show the temperature under the cursor");
+                ui.toggle_value(&mut self.show_grid, "Grid")
+                    .on_hover_text("Show a grid with the pixel coordinates of the image");
+
+                ui.add_space(8.0);
+
+                ui.toggle_value(&mut global_state.frozen, "⏸ Hold")
+                    .on_hover_text("Hold the current frame and its readings on screen");
+                let frozen = global_state.frozen;
+                let (frame_pos, frame_count) = global_state.frozen_frame_position();
+                if ui
+                    .add_enabled(frozen && frame_pos > 0, Button::new("⏴"))
+                    .on_hover_text("Previous frame")
+                    .clicked()
+                {
+                    global_state.step_frozen_frame(-1);
+                }
+                if ui
+                    .add_enabled(frozen && frame_pos + 1 < frame_count, Button::new("⏵"))
+                    .on_hover_text("Next frame")
+                    .clicked()
+                {
+                    global_state.step_frozen_frame(1);
+                }
+                if frozen && frame_count > 0 {
+                    ui.weak(format!("{}/{}", frame_pos + 1, frame_count));
+                }
+
+                if global_state
+                    .last_thermal_capturer_result
+                    .as_ref()
+                    .is_some_and(|r| r.raw_counts)
+                {
+                    ui.add_space(8.0);
+                    ui.colored_label(ui.visuals().warn_fg_color, "DEBUG: raw sensor counts")
+                        .on_hover_text("Readouts and colors use the unprocessed sensor values");
+                }
+
+                let mut display_processing = global_state
+                    .thermal_capturer_settings
+                    .display_only_processing();
+                if global_state
+                    .prefs
+                    .as_ref()
+                    .is_some_and(|p| p.smooth_thermal_display)
+                {
+                    display_processing.insert(0, "smoothing");
+                }
+                if !display_processing.is_empty() {
+                    ui.add_space(8.0);
+                    ui.weak("Display enhanced").on_hover_text(format!(
+                        "The image is enhanced for display only ({}). \
+                        Measurements do not use these steps.",
+                        display_processing.join(", ")
+                    ));
+                }
 
                 ui.with_layout(
                     Layout::right_to_left(egui::Align::Min).with_main_align(egui::Align::Max),
@@ -152,18 +301,55 @@ impl Pane for ThermalDisplayPane {
         let global_state_clone = self.global_state.clone();
         let mut global_state = global_state_clone.as_ref().borrow_mut();
 
+        // Keep the alarm highlight pulsing
+        if global_state.alarm_monitor.any_active() {
+            ui.ctx().request_repaint();
+        }
+
+        let smooth_display = global_state
+            .prefs
+            .as_ref()
+            .map(|p| p.smooth_thermal_display)
+            .unwrap_or(false);
+
+        if global_state.disconnected_camera.is_some()
+            && global_state.thermal_capturer_inst.is_none()
+        {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Camera disconnected — waiting to reconnect",
+            );
+        }
+
         ui.centered_and_justified(|ui| {
             if let Some(res) = global_state.last_thermal_capturer_result.as_ref() {
                 self.camera_texture = Some(ui.ctx().load_texture(
                     "cam_ctx",
                     res.image.clone(),
                     TextureOptions {
-                        magnification: egui::TextureFilter::Nearest,
+                        // Smoothing only affects the displayed texture, measurements use the raw data
+                        magnification: if smooth_display {
+                            egui::TextureFilter::Linear
+                        } else {
+                            egui::TextureFilter::Nearest
+                        },
                         ..Default::default()
                     },
                 ));
-                self.camera_image_size = Some((res.image.width(), res.image.height()));
+                // The image may be upscaled, the plot coordinates are the pixels of the data
+                self.camera_image_size = Some((res.thermal_data.width, res.thermal_data.height));
             }
+            self.secondary_texture =
+                global_state
+                    .last_secondary_capturer_result
+                    .as_ref()
+                    .map(|res| {
+                        ui.ctx().load_texture(
+                            "secondary_cam_ctx",
+                            res.image.clone(),
+                            Default::default(),
+                        )
+                    });
 
             let gizmo_results = global_state
                 .last_thermal_capturer_result
@@ -178,9 +364,18 @@ impl Pane for ThermalDisplayPane {
 
                     const POINT_GIZMO_SIZE: f32 = 12.0;
 
+                    // Temperature under the cursor, shown next to it after the plot
+                    let mut cursor_readout = None;
+                    // Plot positions and anchors of the lock icons, painted over the plot
+                    let mut lock_markers = vec![];
+                    let image_height = img_size.1 as f64;
                     let plot_response = Plot::new("thermal_display_plot")
-                        .show_grid(false)
-                        .show_axes(false)
+                        .show_grid(self.show_grid)
+                        .show_axes(self.show_grid)
+                        // The plot y axis points up, label it with the image row instead
+                        .y_axis_formatter(move |mark, _range| {
+                            format!("{}", image_height - mark.value)
+                        })
                         .show_y(false)
                         .show_x(false)
                         .allow_boxed_zoom(false)
@@ -229,7 +424,8 @@ impl Pane for ThermalDisplayPane {
                                 Vec2::new(img_size.0 as f32, img_size.1 as f32),
                             ));
 
-                            let temp_unit = global_state.preferred_temperature_unit();
+                            let (temp_unit, unit_suffix) = global_state.readout_unit();
+                            let decimals = global_state.temperature_decimals();
 
                             let mut get_gizmo_under_screen_pos = |screen_pos_to_check: Pos2| {
                                 global_state
@@ -238,15 +434,40 @@ impl Pane for ThermalDisplayPane {
                                     .children_mut()
                                     .unwrap()
                                     .iter()
+                                    .filter(|gizmo| gizmo.enabled)
                                     .find(|gizmo| match gizmo.kind {
-                                        GizmoKind::TempAt { pos } => {
+                                        GizmoKind::TempAt { pos, .. } => {
                                             let gizmo_screen_pos = plot_ui.screen_from_plot(
-                                                [pos.x as f64, img_size.1 as f64 - pos.y as f64]
-                                                    .into(),
+                                                data_pos_to_plot(pos, img_size).into(),
                                             );
                                             screen_pos_to_check.distance(gizmo_screen_pos)
                                                 < POINT_GIZMO_SIZE
                                         }
+                                        GizmoKind::IntegratedAboveAmbient { region } => region
+                                            .contains(plot_to_data_pos(
+                                                plot_ui.plot_from_screen(screen_pos_to_check),
+                                                img_size,
+                                            )),
+                                        GizmoKind::Rect {
+                                            top_left,
+                                            bottom_right,
+                                            ..
+                                        } => ThermalDataRect::from_corners(top_left, bottom_right)
+                                            .contains(plot_to_data_pos(
+                                                plot_ui.plot_from_screen(screen_pos_to_check),
+                                                img_size,
+                                            )),
+                                        GizmoKind::Line { start, end } => {
+                                            distance_to_segment(
+                                                screen_pos_to_check,
+                                                plot_ui.screen_from_plot(
+                                                    data_pos_to_plot(start, img_size).into(),
+                                                ),
+                                                plot_ui.screen_from_plot(
+                                                    data_pos_to_plot(end, img_size).into(),
+                                                ),
+                                            ) < POINT_GIZMO_SIZE / 2.0
+                                        }
                                         _ => false,
                                     })
                                     .map(|gizmo| gizmo.uuid)
@@ -272,23 +493,202 @@ impl Pane for ThermalDisplayPane {
                                 interact_gizmo = get_gizmo_under_screen_pos(pointer_pos);
                             }
 
+                            let alarm_color =
+                                alarm_pulse_color(plot_ui.ctx().input(|inp| inp.time));
                             global_state
                                 .thermal_capturer_settings
                                 .gizmo
-                                .children_mut()
+                                .children()
                                 .unwrap()
                                 .iter()
+                                .filter(|c| c.enabled)
                                 .for_each(|c| {
                                     let result =
                                         gizmo_results.as_ref().and_then(|r| r.get(&c.uuid));
+                                    // Gizmos with an active alarm pulse red
+                                    let color = if global_state.alarm_monitor.is_active(c.uuid) {
+                                        alarm_color
+                                    } else {
+                                        c.color
+                                    };
                                     if let Some(result) = result {
-                                        let _color = c.color;
+                                        // Deltas connect the two referenced gizmos
+                                        if let GizmoKind::Delta { a, b } = c.kind {
+                                            let positions = gizmo_results.as_ref().and_then(|r| {
+                                                Some([r.get(&a)?.pos, r.get(&b)?.pos])
+                                            });
+                                            if let Some(positions) = positions {
+                                                plot_ui.line(
+                                                    Line::new(
+                                                        positions
+                                                            .map(|pos| {
+                                                                data_pos_to_plot(pos, img_size)
+                                                            })
+                                                            .to_vec(),
+                                                    )
+                                                    .color(color)
+                                                    .style(LineStyle::dashed_loose()),
+                                                );
+                                            }
+                                            if c.show_temperature_label {
+                                                plot_ui.text(temperature_label(
+                                                    data_pos_to_plot(result.pos, img_size),
+                                                    format!(
+                                                        "Δ {} {}",
+                                                        result
+                                                            .delta
+                                                            .unwrap_or_default()
+                                                            .format_diff(temp_unit, decimals),
+                                                        unit_suffix
+                                                    ),
+                                                ));
+                                            }
+                                            return;
+                                        }
+                                        if let GizmoKind::IntegratedAboveAmbient { region } = c.kind
+                                        {
+                                            plot_ui.line(
+                                                Line::new(region_outline(region, img_size.1))
+                                                    .color(color)
+                                                    .width(if Some(c.uuid) == hovered_gizmo {
+                                                        3.0
+                                                    } else {
+                                                        2.0
+                                                    }),
+                                            );
+                                            if c.show_temperature_label {
+                                                plot_ui.text(
+                                                    Text::new(
+                                                        PlotPoint::new(
+                                                            region.min.x as f64,
+                                                            img_size.1 as f64 - region.min.y as f64,
+                                                        ),
+                                                        RichText::new(format!(
+                                                            "{:.0} K·px",
+                                                            result
+                                                                .integrated_above_ambient
+                                                                .unwrap_or_default()
+                                                        ))
+                                                        .size(14.0)
+                                                        .background_color(
+                                                            Color32::BLACK.gamma_multiply(0.5),
+                                                        )
+                                                        .color(Color32::WHITE),
+                                                    )
+                                                    .anchor(Align2::LEFT_BOTTOM),
+                                                );
+                                            }
+                                            if c.locked {
+                                                lock_markers.push((
+                                                    [
+                                                        region.max.x as f64 + 1.0,
+                                                        img_size.1 as f64 - region.min.y as f64,
+                                                    ],
+                                                    Align2::RIGHT_BOTTOM,
+                                                ));
+                                            }
+                                            return;
+                                        }
+
+                                        if let GizmoKind::Rect {
+                                            top_left,
+                                            bottom_right,
+                                            ..
+                                        } = c.kind
+                                        {
+                                            let outline = region_outline(
+                                                ThermalDataRect::from_corners(
+                                                    top_left,
+                                                    bottom_right,
+                                                ),
+                                                img_size.1,
+                                            );
+                                            plot_ui.line(
+                                                Line::new(outline.clone()).color(color).width(
+                                                    if Some(c.uuid) == hovered_gizmo {
+                                                        3.0
+                                                    } else {
+                                                        2.0
+                                                    },
+                                                ),
+                                            );
+                                            // Corner handles, for resizing
+                                            plot_ui.points(
+                                                Points::new(outline[..4].to_vec())
+                                                    .shape(MarkerShape::Square)
+                                                    .radius(3.0)
+                                                    .filled(true)
+                                                    .color(color),
+                                            );
+                                            if c.show_temperature_label {
+                                                plot_ui.text(
+                                                    Text::new(
+                                                        PlotPoint::new(
+                                                            outline[0][0],
+                                                            outline[0][1],
+                                                        ),
+                                                        RichText::new(format!(
+                                                            "min {} / avg {} / max {} {}",
+                                                            result.min.format(temp_unit, decimals),
+                                                            result.avg.format(temp_unit, decimals),
+                                                            result.max.format(temp_unit, decimals),
+                                                            unit_suffix
+                                                        ))
+                                                        .size(14.0)
+                                                        .background_color(
+                                                            Color32::BLACK.gamma_multiply(0.5),
+                                                        )
+                                                        .color(Color32::WHITE),
+                                                    )
+                                                    .anchor(Align2::LEFT_BOTTOM),
+                                                );
+                                            }
+                                            if c.locked {
+                                                lock_markers
+                                                    .push((outline[1], Align2::RIGHT_BOTTOM));
+                                            }
+                                            return;
+                                        }
 
-                                        let x = result.pos.x as f64;
+                                        // The line itself, the marker below shows its hottest point
+                                        if let GizmoKind::Line { start, end } = c.kind {
+                                            let endpoints = vec![
+                                                data_pos_to_plot(start, img_size),
+                                                data_pos_to_plot(end, img_size),
+                                            ];
+                                            plot_ui.line(
+                                                Line::new(endpoints.clone()).color(color).width(
+                                                    if Some(c.uuid) == hovered_gizmo {
+                                                        3.0
+                                                    } else {
+                                                        2.0
+                                                    },
+                                                ),
+                                            );
+                                            plot_ui.points(
+                                                Points::new(endpoints)
+                                                    .shape(MarkerShape::Circle)
+                                                    .radius(4.0)
+                                                    .filled(true)
+                                                    .color(color),
+                                            );
+                                        }
 
-                                        let y = img_size.1 as f64 - result.pos.y as f64;
+                                        let [x, y] = data_pos_to_plot(result.pos, img_size);
 
-                                        let _size = 10.0;
+                                        // Area the spot temperature is averaged over
+                                        if let GizmoKind::TempAt { radius, .. } = c.kind {
+                                            if radius > 0 {
+                                                plot_ui.line(
+                                                    Line::new(circle_outline(
+                                                        [x, y],
+                                                        radius as f64 + 0.5,
+                                                    ))
+                                                    .color(color)
+                                                    .width(1.5),
+                                                );
+                                            }
+                                        }
 
                                         let background_opacity = if Some(c.uuid) == hovered_gizmo {
                                             0.5
@@ -296,61 +696,214 @@ impl Pane for ThermalDisplayPane {
                                             0.3
                                         };
 
-                                        plot_ui.points(
-                                            Points::new(vec![[x, y]])
-                                                .shape(MarkerShape::Circle)
-                                                .radius(POINT_GIZMO_SIZE)
-                                                .filled(true)
-                                                .color(
-                                                    Color32::BLACK
-                                                        .gamma_multiply(background_opacity),
-                                                ),
-                                        );
-                                        plot_ui.points(
-                                            Points::new(vec![[x, y]])
-                                                .shape(MarkerShape::Circle)
-                                                .radius(POINT_GIZMO_SIZE * 0.66)
-                                                .filled(false)
-                                                .color(Color32::WHITE),
-                                        );
-                                        plot_ui.points(
-                                            Points::new(vec![[x, y]])
-                                                .shape(MarkerShape::Plus)
-                                                .radius(POINT_GIZMO_SIZE)
-                                                .color(c.color),
+                                        point_marker(
+                                            plot_ui,
+                                            [x, y],
+                                            POINT_GIZMO_SIZE,
+                                            color,
+                                            background_opacity,
                                         );
 
+                                        if c.locked {
+                                            lock_markers.push(([x - 4.0, y], Align2::RIGHT_CENTER));
+                                        }
+
                                         if c.show_temperature_label {
-                                            plot_ui.text(
-                                                Text::new(
-                                                    PlotPoint::new(x + 4.0, y),
-                                                    RichText::new(format!(
-                                                        "{:.1} {}",
-                                                        result.temperature.to_unit(temp_unit),
-                                                        temp_unit.suffix()
-                                                    ))
-                                                    .size(16.0)
-                                                    .background_color(
-                                                        Color32::BLACK.gamma_multiply(0.5),
-                                                    )
-                                                    .color(Color32::WHITE),
-                                                )
-                                                .anchor(Align2::LEFT_CENTER),
-                                            );
+                                            plot_ui.text(temperature_label(
+                                                [x, y],
+                                                format!(
+                                                    "{} {}",
+                                                    result.temperature.format(temp_unit, decimals),
+                                                    unit_suffix
+                                                ),
+                                            ));
                                         }
                                     }
                                 });
 
+                            // Adding regions, rects and lines by dragging
+                            if self.gizmo_tool == GizmoTool::AreaAboveAmbient
+                                || self.gizmo_tool == GizmoTool::Line
+                                || self.gizmo_tool == GizmoTool::Rect
+                            {
+                                let pointer_coordinate = plot_ui.pointer_coordinate();
+                                if plot_ui
+                                    .response()
+                                    .drag_started_by(egui::PointerButton::Primary)
+                                {
+                                    self.drag_start =
+                                        pointer_coordinate.and_then(|p| image_pos_at(p, img_size));
+                                }
+                                let drag_stopped = plot_ui
+                                    .response()
+                                    .drag_stopped_by(egui::PointerButton::Primary);
+                                if let Some(start) = self.drag_start {
+                                    let end = pointer_coordinate
+                                        .map(|p| plot_to_data_pos(p, img_size))
+                                        .unwrap_or(start);
+                                    let region = ThermalDataRect::from_corners(start, end);
+                                    let (kind, name, preview) = match self.gizmo_tool {
+                                        GizmoTool::Line => (
+                                            GizmoKind::Line { start, end },
+                                            "Line",
+                                            vec![
+                                                data_pos_to_plot(start, img_size),
+                                                data_pos_to_plot(end, img_size),
+                                            ],
+                                        ),
+                                        GizmoTool::Rect => (
+                                            GizmoKind::Rect {
+                                                top_left: region.min,
+                                                bottom_right: region.max,
+                                                emissivity: None,
+                                            },
+                                            "Rect",
+                                            region_outline(region, img_size.1),
+                                        ),
+                                        _ => (
+                                            GizmoKind::IntegratedAboveAmbient { region },
+                                            "Area",
+                                            region_outline(region, img_size.1),
+                                        ),
+                                    };
+                                    if drag_stopped {
+                                        global_state
+                                            .thermal_capturer_settings
+                                            .gizmo
+                                            .push_child(kind, name.to_string());
+                                        let settings_clone =
+                                            global_state.thermal_capturer_settings.clone();
+                                        if let Some(thermal_capturer) =
+                                            global_state.thermal_capturer_inst.as_mut()
+                                        {
+                                            thermal_capturer.set_settings(settings_clone);
+                                        }
+                                    } else {
+                                        plot_ui.line(
+                                            Line::new(preview)
+                                                .color(Color32::WHITE)
+                                                .style(egui_plot::LineStyle::dashed_dense()),
+                                        );
+                                    }
+                                }
+                                if drag_stopped {
+                                    self.drag_start = None;
+                                }
+                            }
+
+                            // Moving point gizmos and rect corners by dragging. Positions are snapped
+                            // to whole pixels and the settings are only pushed when they change,
+                            // so that the readout does not flicker and the capturer is not flooded
+                            // with updates.
+                            if self.gizmo_tool == GizmoTool::Point {
+                                if plot_ui
+                                    .response()
+                                    .drag_started_by(egui::PointerButton::Primary)
+                                    && !self.lock_all_gizmos
+                                {
+                                    let unlocked_gizmos = global_state
+                                        .thermal_capturer_settings
+                                        .gizmo
+                                        .children()
+                                        .unwrap()
+                                        .iter()
+                                        .filter(|gizmo| gizmo.enabled && !gizmo.locked);
+                                    // Grabbing a corner of a rect resizes it
+                                    let press_origin =
+                                        plot_ui.ctx().input(|inp| inp.pointer.press_origin());
+                                    let grabbed_corner = press_origin.and_then(|origin| {
+                                        unlocked_gizmos.clone().find_map(|gizmo| match gizmo.kind {
+                                            GizmoKind::Rect {
+                                                top_left,
+                                                bottom_right,
+                                                ..
+                                            } => rect_corner_anchor(
+                                                plot_ui,
+                                                ThermalDataRect::from_corners(
+                                                    top_left,
+                                                    bottom_right,
+                                                ),
+                                                img_size,
+                                                origin,
+                                            )
+                                            .map(|anchor| (gizmo.uuid, anchor)),
+                                            _ => None,
+                                        })
+                                    });
+                                    if let Some((uuid, anchor)) = grabbed_corner {
+                                        self.dragged_gizmo_uuid = Some(uuid);
+                                        self.rect_drag_anchor = Some(anchor);
+                                    } else {
+                                        self.dragged_gizmo_uuid = hovered_gizmo.filter(|uuid| {
+                                            unlocked_gizmos.clone().any(|gizmo| gizmo.uuid == *uuid)
+                                        });
+                                        self.rect_drag_anchor = None;
+                                    }
+                                }
+                                let drag_stopped = plot_ui
+                                    .response()
+                                    .drag_stopped_by(egui::PointerButton::Primary);
+                                if let Some(dragged_uuid) = self.dragged_gizmo_uuid {
+                                    let new_pos = plot_ui
+                                        .pointer_coordinate()
+                                        .map(|p| plot_to_data_pos(p, img_size));
+                                    let moved = global_state
+                                        .thermal_capturer_settings
+                                        .gizmo
+                                        .children_mut()
+                                        .unwrap()
+                                        .iter_mut()
+                                        .find(|gizmo| gizmo.uuid == dragged_uuid)
+                                        .and_then(|gizmo| match &mut gizmo.kind {
+                                            GizmoKind::TempAt { pos, .. } => new_pos
+                                                .filter(|new_pos| new_pos != pos)
+                                                .map(|new_pos| *pos = new_pos),
+                                            GizmoKind::Rect {
+                                                top_left,
+                                                bottom_right,
+                                                ..
+                                            } => {
+                                                let region = ThermalDataRect::from_corners(
+                                                    self.rect_drag_anchor?,
+                                                    new_pos?,
+                                                );
+                                                (region.min != *top_left
+                                                    || region.max != *bottom_right)
+                                                    .then(|| {
+                                                        *top_left = region.min;
+                                                        *bottom_right = region.max;
+                                                    })
+                                            }
+                                            _ => None,
+                                        })
+                                        .is_some();
+                                    if moved {
+                                        let settings_clone =
+                                            global_state.thermal_capturer_settings.clone();
+                                        if let Some(thermal_capturer) =
+                                            global_state.thermal_capturer_inst.as_mut()
+                                        {
+                                            thermal_capturer.set_settings(settings_clone);
+                                        }
+                                    }
+                                }
+                                if drag_stopped {
+                                    self.dragged_gizmo_uuid = None;
+                                    self.rect_drag_anchor = None;
+                                }
+                            }
+
                             // Adding gizmos by clicking, if the plot is clicked and no gizmo is hovered
-                            if plot_ui.response().clicked() && hovered_gizmo.is_none() {
-                                let pos = plot_ui.pointer_coordinate().unwrap();
-                                let x = pos.x as usize;
-                                let y = pos.y as usize;
-                                if x > 0 && y > 0 && x < img_size.0 && y < img_size.1 {
+                            if self.gizmo_tool == GizmoTool::Point
+                                && plot_ui.response().clicked()
+                                && hovered_gizmo.is_none()
+                            {
+                                if let Some(pos) = plot_ui
+                                    .pointer_coordinate()
+                                    .and_then(|p| image_pos_at(p, img_size))
+                                {
                                     global_state.thermal_capturer_settings.gizmo.push_child(
-                                        GizmoKind::TempAt {
-                                            pos: ThermalDataPos::new(x, img_size.1 - y),
-                                        },
+                                        GizmoKind::TempAt { pos, radius: 0 },
                                         "Custom".to_string(),
                                     );
 
@@ -364,6 +917,81 @@ impl Pane for ThermalDisplayPane {
                                 }
                             }
 
+                            // Transient probe following the cursor
+                            if self.gizmo_tool == GizmoTool::Probe && hovered_gizmo.is_none() {
+                                let probe_pos = plot_ui
+                                    .pointer_coordinate()
+                                    .filter(|_| plot_ui.response().hovered())
+                                    .and_then(|p| image_pos_at(p, img_size));
+                                let probe_temperature = probe_pos.and_then(|pos| {
+                                    global_state
+                                        .last_thermal_capturer_result
+                                        .as_ref()
+                                        .map(|r| r.measured_temperature_at(pos.x, pos.y))
+                                });
+                                if let (Some(pos), Some(temperature)) =
+                                    (probe_pos, probe_temperature)
+                                {
+                                    let [x, y] = data_pos_to_plot(pos, img_size);
+                                    point_marker(
+                                        plot_ui,
+                                        [x, y],
+                                        POINT_GIZMO_SIZE,
+                                        Color32::WHITE,
+                                        0.5,
+                                    );
+                                    plot_ui.text(temperature_label(
+                                        [x, y],
+                                        format!(
+                                            "{} {}",
+                                            temperature.format(temp_unit, decimals),
+                                            unit_suffix
+                                        ),
+                                    ));
+
+                                    if plot_ui.response().clicked() {
+                                        global_state.thermal_capturer_settings.gizmo.push_child(
+                                            GizmoKind::TempAt { pos, radius: 0 },
+                                            "Probe".to_string(),
+                                        );
+
+                                        let settings_clone =
+                                            global_state.thermal_capturer_settings.clone();
+                                        if let Some(thermal_capturer) =
+                                            global_state.thermal_capturer_inst.as_mut()
+                                        {
+                                            thermal_capturer.set_settings(settings_clone);
+                                        }
+                                    }
+                                }
+                            }
+
+                            // The probe tool already shows the temperature under the cursor
+                            if self.cursor_readout
+                                && self.gizmo_tool != GizmoTool::Probe
+                                && hovered_gizmo.is_none()
+                                && self.drag_start.is_none()
+                                && self.dragged_gizmo_uuid.is_none()
+                            {
+                                cursor_readout = plot_ui
+                                    .pointer_coordinate()
+                                    .filter(|_| plot_ui.response().hovered())
+                                    .and_then(|p| image_pos_at(p, img_size))
+                                    .and_then(|pos| {
+                                        global_state
+                                            .last_thermal_capturer_result
+                                            .as_ref()
+                                            .map(|r| r.measured_temperature_at(pos.x, pos.y))
+                                    })
+                                    .map(|temperature| {
+                                        format!(
+                                            "{} {}",
+                                            temperature.format(temp_unit, decimals),
+                                            unit_suffix
+                                        )
+                                    });
+                            }
+
                             // handle right click
                             if plot_ui
                                 .response()
@@ -414,8 +1042,37 @@ impl Pane for ThermalDisplayPane {
                                 );
                                 plot_ui.translate_bounds(plot_transform * delta);
                             }
+
+                            // Draw the secondary camera in the top right corner of the view
+                            if let Some(secondary_texture) = self.secondary_texture.as_ref() {
+                                let bounds = plot_ui.plot_bounds();
+                                let [tex_width, tex_height] = secondary_texture.size();
+                                let inset_width = bounds.width() * SECONDARY_INSET_FRACTION;
+                                let inset_height =
+                                    inset_width * tex_height as f64 / tex_width.max(1) as f64;
+                                let margin = bounds.width() * 0.02;
+                                plot_ui.image(PlotImage::new(
+                                    secondary_texture,
+                                    PlotPoint::new(
+                                        bounds.max()[0] - margin - inset_width / 2.0,
+                                        bounds.max()[1] - margin - inset_height / 2.0,
+                                    ),
+                                    Vec2::new(inset_width as f32, inset_height as f32),
+                                ));
+                            }
                         });
 
+                    for (pos, anchor) in lock_markers {
+                        paint_lock_icon(ui, &plot_response.transform, pos, anchor);
+                    }
+
+                    if let Some(readout) = cursor_readout {
+                        plot_response
+                            .response
+                            .clone()
+                            .on_hover_text_at_pointer(readout);
+                    }
+
                     // update external_zoom_factor so that the slider is in sync with the plot zoom
                     self.external_zoom_factor = (img_size.0 as f64
                         / plot_response.transform.bounds().width())
@@ -465,6 +1122,7 @@ impl Pane for ThermalDisplayPane {
                                         &mut gizmo.show_temperature_label,
                                         "Show temperature",
                                     );
+                                    ui.checkbox(&mut gizmo.locked, "Lock position");
                                 });
                             }
                             None => {
@@ -480,6 +1138,10 @@ impl Pane for ThermalDisplayPane {
     fn is_maximized(&self) -> bool {
         self.maximized
     }
+
+    fn set_maximized(&mut self, maximized: bool) {
+        self.maximized = maximized;
+    }
 }
 
 pub fn zoom_edit_field(ui: &mut Ui, zoom_value: &mut f64) -> Response {
@@ -494,3 +1156,161 @@ pub fn zoom_edit_field(ui: &mut Ui, zoom_value: &mut f64) -> Response {
     *zoom_value = tmp_value / 100.0;
     res
 }
+
+//
+// The image is drawn at its native resolution with the origin at the bottom left of the plot,
+// so thermal data pixel (x, y) covers plot x in [x, x + 1] and plot y in [h - y - 1, h - y].
+// All measurement placement goes through these helpers, independently of how the image is displayed.
+//
+
+// Center of a thermal data pixel in plot coordinates
+fn data_pos_to_plot(pos: ThermalDataPos, img_size: (usize, usize)) -> [f64; 2] {
+    [pos.x as f64 + 0.5, img_size.1 as f64 - pos.y as f64 - 0.5]
+}
+
+// Thermal data pixel under a plot coordinate, None if outside of the image
+fn image_pos_at(point: PlotPoint, img_size: (usize, usize)) -> Option<ThermalDataPos> {
+    if point.x < 0.0
+        || point.y < 0.0
+        || point.x >= img_size.0 as f64
+        || point.y >= img_size.1 as f64
+    {
+        return None;
+    }
+    Some(plot_to_data_pos(point, img_size))
+}
+
+// Converts a plot coordinate to the thermal data pixel under it, clamped to the image bounds
+fn plot_to_data_pos(point: PlotPoint, img_size: (usize, usize)) -> ThermalDataPos {
+    let x = point.x.floor().clamp(0.0, img_size.0 as f64 - 1.0);
+    let y = (img_size.1 as f64 - point.y)
+        .floor()
+        .clamp(0.0, img_size.1 as f64 - 1.0);
+    ThermalDataPos::new(x as usize, y as usize)
+}
+
+// Closed outline around the pixels of a region, in plot coordinates
+fn region_outline(region: ThermalDataRect, img_height: usize) -> Vec<[f64; 2]> {
+    let left = region.min.x as f64;
+    let right = region.max.x as f64 + 1.0;
+    let top = img_height as f64 - region.min.y as f64;
+    let bottom = img_height as f64 - region.max.y as f64 - 1.0;
+    vec![
+        [left, top],
+        [right, top],
+        [right, bottom],
+        [left, bottom],
+        [left, top],
+    ]
+}
+
+// Closed circle around `center`, in plot coordinates
+fn circle_outline(center: [f64; 2], radius: f64) -> Vec<[f64; 2]> {
+    const SEGMENTS: usize = 32;
+    (0..=SEGMENTS)
+        .map(|i| {
+            let angle = i as f64 / SEGMENTS as f64 * std::f64::consts::TAU;
+            [
+                center[0] + radius * angle.cos(),
+                center[1] + radius * angle.sin(),
+            ]
+        })
+        .collect()
+}
+
+//
+// Returns the corner opposite to the one of `region` at `screen_pos`, if any.
+// The opposite corner stays in place while the grabbed one is dragged.
+//
+fn rect_corner_anchor(
+    plot_ui: &PlotUi,
+    region: ThermalDataRect,
+    img_size: (usize, usize),
+    screen_pos: Pos2,
+) -> Option<ThermalDataPos> {
+    // In the order of the corners of the outline
+    let anchors = [
+        ThermalDataPos::new(region.max.x, region.max.y),
+        ThermalDataPos::new(region.min.x, region.max.y),
+        ThermalDataPos::new(region.min.x, region.min.y),
+        ThermalDataPos::new(region.max.x, region.min.y),
+    ];
+    region_outline(region, img_size.1)
+        .into_iter()
+        .zip(anchors)
+        .find(|(corner, _)| {
+            screen_pos.distance(plot_ui.screen_from_plot(PlotPoint::new(corner[0], corner[1])))
+                < POINT_GIZMO_SIZE
+        })
+        .map(|(_, anchor)| anchor)
+}
+
+// Crosshair marker used for point measurements
+fn point_marker(
+    plot_ui: &mut PlotUi,
+    pos: [f64; 2],
+    size: f32,
+    color: Color32,
+    background_opacity: f32,
+) {
+    plot_ui.points(
+        Points::new(vec![pos])
+            .shape(MarkerShape::Circle)
+            .radius(size)
+            .filled(true)
+            .color(Color32::BLACK.gamma_multiply(background_opacity)),
+    );
+    plot_ui.points(
+        Points::new(vec![pos])
+            .shape(MarkerShape::Circle)
+            .radius(size * 0.66)
+            .filled(false)
+            .color(Color32::WHITE),
+    );
+    plot_ui.points(
+        Points::new(vec![pos])
+            .shape(MarkerShape::Plus)
+            .radius(size)
+            .color(color),
+    );
+}
+
+fn temperature_label(pos: [f64; 2], text: String) -> Text {
+    Text::new(
+        PlotPoint::new(pos[0] + 4.0, pos[1]),
+        RichText::new(text)
+            .size(16.0)
+            .background_color(Color32::BLACK.gamma_multiply(0.5))
+            .color(Color32::WHITE),
+    )
+    .anchor(Align2::LEFT_CENTER)
+}
+
+// Marks a gizmo whose position is locked, with the icon of the lock button of the measurements pane
+fn paint_lock_icon(ui: &Ui, transform: &PlotTransform, pos: [f64; 2], anchor: Align2) {
+    const ICON_SIZE: f32 = 12.0;
+    const PADDING: f32 = 2.0;
+
+    let screen_pos = transform.position_from_point(&PlotPoint::new(pos[0], pos[1]));
+    let rect = anchor.anchor_size(screen_pos, Vec2::splat(ICON_SIZE + PADDING * 2.0));
+    // Not drawn over the rest of the pane when the gizmo is scrolled out of view
+    if !transform.frame().contains_rect(rect) {
+        return;
+    }
+    ui.painter()
+        .rect_filled(rect, PADDING, Color32::BLACK.gamma_multiply(0.5));
+    Image::new(egui::include_image!("../icons/lock.svg"))
+        .tint(Color32::WHITE)
+        .paint_at(ui, rect.shrink(PADDING));
+}
+
+// Distance from `p` to the segment between `a` and `b`
+fn distance_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0.0 {
+        ((p - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    p.distance(a + ab * t)
+}
@@ -1,12 +1,14 @@
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    pane_dispatcher::Pane, temperature::TemperatureUnit, user_preferences::UserPreferences,
+    pane_dispatcher::Pane,
+    recorders::filename_template::FILENAME_TEMPLATE_TOKENS,
+    temperature::TemperatureUnit,
+    thermal_gradient::{all_gradients, ThermalGradient},
+    user_preferences::UserPreferences,
     AppGlobalState,
 };
-use anyhow::Context;
-use eframe::egui::{self, Grid};
-use log::error;
+use eframe::egui::{self, DragValue, Grid};
 use strum::IntoEnumIterator;
 
 pub struct UserPreferencesPane {
@@ -58,6 +60,18 @@ impl Pane for UserPreferencesPane {
                         });
                     ui.end_row();
 
+                    ui.label("Decimal places");
+                    ui.add(egui::DragValue::new(&mut edited_prefs.decimal_places).range(0..=3))
+                        .on_hover_text("Decimal places of the displayed temperatures");
+                    ui.end_row();
+
+                    ui.label("Allow expressions in temperature fields");
+                    ui.checkbox(&mut edited_prefs.temperature_expressions, "")
+                        .on_hover_text(
+                            "Accept input like \"25+5\" or \"300K\" in temperature fields.",
+                        );
+                    ui.end_row();
+
                     ui.label("Auto Open Camera");
                     ui.checkbox(&mut edited_prefs.auto_open_camera, "");
                     ui.end_row();
@@ -66,9 +80,160 @@ impl Pane for UserPreferencesPane {
                     ui.checkbox(&mut edited_prefs.show_unsupported_cameras, "");
                     ui.end_row();
 
+                    ui.label("Show help when no supported camera is found");
+                    ui.checkbox(&mut edited_prefs.show_camera_onboarding, "");
+                    ui.end_row();
+
                     ui.label("Captures directory");
                     ui.text_edit_singleline(&mut edited_prefs.captures_directory);
                     ui.end_row();
+
+                    ui.label("Gallery size");
+                    ui.add(DragValue::new(&mut edited_prefs.gallery_max_items).range(1..=500))
+                        .on_hover_text("Maximum number of files shown in the gallery");
+                    ui.end_row();
+
+                    ui.label("Snapshot file name");
+                    ui.text_edit_singleline(&mut edited_prefs.snapshot_filename_template)
+                        .on_hover_ui(filename_template_tokens_ui);
+                    ui.end_row();
+
+                    ui.label("Video file name");
+                    ui.text_edit_singleline(&mut edited_prefs.video_filename_template)
+                        .on_hover_ui(filename_template_tokens_ui);
+                    ui.end_row();
+
+                    ui.label("Show stream info");
+                    ui.checkbox(&mut edited_prefs.show_stream_info, "");
+                    ui.end_row();
+
+                    ui.label("Smooth thermal image");
+                    ui.checkbox(&mut edited_prefs.smooth_thermal_display, "")
+                        .on_hover_text("Only affects the display, not the measurements.");
+                    ui.end_row();
+
+                    ui.label("Remember window size and position");
+                    ui.checkbox(&mut edited_prefs.remember_window_geometry, "")
+                        .on_hover_text("Applied after restarting the application.");
+                    ui.end_row();
+
+                    ui.label("Exit maximized view with Esc");
+                    ui.checkbox(&mut edited_prefs.exit_maximized_on_escape, "");
+                    ui.end_row();
+
+                    ui.label("Center spot measurement");
+                    ui.checkbox(&mut edited_prefs.show_center_spot, "")
+                        .on_hover_text("Measure the temperature at the center of the image");
+                    ui.end_row();
+
+                    ui.label("Keep measurements in place when rotating");
+                    ui.checkbox(&mut edited_prefs.rotate_measurements_with_image, "")
+                        .on_hover_text(
+                            "When disabled, measurement points stay fixed to sensor pixels.",
+                        );
+                    ui.end_row();
+
+                    ui.label("Measurement averaging window");
+                    ui.add(
+                        DragValue::new(&mut edited_prefs.readout_averaging_window_ms)
+                            .speed(10.0)
+                            .range(0..=10_000)
+                            .suffix(" ms"),
+                    )
+                    .on_hover_text("Set to 0 to show instantaneous readouts.");
+                    ui.end_row();
+
+                    ui.label("Keep measurement history for");
+                    ui.add(
+                        DragValue::new(&mut edited_prefs.history_retention_minutes)
+                            .range(15..=24 * 60)
+                            .suffix(" min"),
+                    )
+                    .on_hover_text("Older points are dropped from the chart and CSV exports.");
+                    ui.end_row();
+
+                    ui.label("Temperature unit in measurements header");
+                    ui.checkbox(&mut edited_prefs.measurement_units_in_header, "");
+                    ui.end_row();
+
+                    ui.label("Automatic palette");
+                    ui.checkbox(&mut edited_prefs.auto_gradient, "")
+                        .on_hover_text(
+                            "Pick the palette based on the temperature span of the scene.",
+                        );
+                    ui.end_row();
+
+                    ui.add_enabled_ui(edited_prefs.auto_gradient, |ui| {
+                        ui.label("Palette for narrow spans");
+                    });
+                    ui.add_enabled_ui(edited_prefs.auto_gradient, |ui| {
+                        gradient_combo_box(
+                            ui,
+                            "auto_gradient_narrow",
+                            &mut edited_prefs.auto_gradient_narrow,
+                            &edited_prefs.custom_gradients,
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.add_enabled_ui(edited_prefs.auto_gradient, |ui| {
+                        ui.label("Palette for wide spans");
+                    });
+                    ui.add_enabled_ui(edited_prefs.auto_gradient, |ui| {
+                        gradient_combo_box(
+                            ui,
+                            "auto_gradient_wide",
+                            &mut edited_prefs.auto_gradient_wide,
+                            &edited_prefs.custom_gradients,
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.add_enabled_ui(edited_prefs.auto_gradient, |ui| {
+                        ui.label("Wide span threshold");
+                    });
+                    ui.add_enabled(
+                        edited_prefs.auto_gradient,
+                        DragValue::new(&mut edited_prefs.auto_gradient_span_threshold)
+                            .speed(0.5)
+                            .range(1.0..=200.0)
+                            .suffix(" K"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Parallel video thumbnails");
+                    ui.add(DragValue::new(&mut edited_prefs.video_thumbnail_workers).range(1..=16))
+                        .on_hover_text("Applied after restarting the application.");
+                    ui.end_row();
+
+                    ui.label("Maximum GIF duration");
+                    ui.add(
+                        DragValue::new(&mut edited_prefs.gif_max_duration_secs)
+                            .range(1..=60)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+
+                    ui.label("GIF frame skip");
+                    ui.add(DragValue::new(&mut edited_prefs.gif_frame_skip).range(1..=10))
+                        .on_hover_text("Keeps only every n-th frame, to make long GIFs smaller.");
+                    ui.end_row();
+
+                    #[cfg(feature = "osc")]
+                    {
+                        ui.label("Send measurements over OSC");
+                        ui.checkbox(&mut edited_prefs.osc_enabled, "")
+                            .on_hover_text("Values are sent in the preferred temperature unit.");
+                        ui.end_row();
+
+                        ui.label("OSC target (host:port)");
+                        ui.text_edit_singleline(&mut edited_prefs.osc_target);
+                        ui.end_row();
+
+                        ui.label("OSC address prefix");
+                        ui.text_edit_singleline(&mut edited_prefs.osc_address_prefix);
+                        ui.end_row();
+                    }
                 });
 
             ui.add_space(10.0);
@@ -79,12 +244,7 @@ impl Pane for UserPreferencesPane {
                     global_state.prefs =
                         Some(self.local_user_preferences.as_ref().unwrap().clone());
                     self.local_user_preferences = None;
-                    let _ = global_state
-                        .prefs
-                        .as_ref()
-                        .context("Failed to get user preferences")
-                        .map(|prefs| prefs.save())
-                        .inspect_err(|err| error!("Failed to save user preferences: {}", err));
+                    global_state.save_prefs();
                 }
                 if ui.button("Cancel").clicked() {
                     self.local_user_preferences = None;
@@ -97,3 +257,37 @@ impl Pane for UserPreferencesPane {
         self.local_user_preferences.is_none()
     }
 }
+
+fn gradient_combo_box(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    selected_uuid: &mut uuid::Uuid,
+    custom_gradients: &[ThermalGradient],
+) {
+    let gradients = all_gradients(custom_gradients);
+    let selected_name = gradients
+        .iter()
+        .find(|g| g.uuid == *selected_uuid)
+        .map(|g| g.name.clone())
+        .unwrap_or_default();
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(selected_name)
+        .show_ui(ui, |ui| {
+            for gradient in gradients.iter() {
+                ui.selectable_value(selected_uuid, gradient.uuid, gradient.name.clone());
+            }
+        });
+}
+
+// Lists the tokens which can be used in the file name templates
+fn filename_template_tokens_ui(ui: &mut egui::Ui) {
+    ui.label("Available tokens:");
+    Grid::new("filename_template_tokens").show(ui, |ui| {
+        for (token, description) in FILENAME_TEMPLATE_TOKENS {
+            ui.monospace(token);
+            ui.label(description);
+            ui.end_row();
+        }
+    });
+    ui.label("Other characters than letters, digits and dashes are replaced with underscores.");
+}
@@ -0,0 +1,142 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    thermal_capturer::ThermalCapturerResult,
+    types::media_formats::{ImageEncodeSettings, ImageFormat},
+    util::unique_path,
+};
+
+use super::{
+    filename_template::FilenameTemplate,
+    image_recorder::{encode_snapshot, snapshot_image, CaptureOverlays},
+    recorder::{Recorder, RecorderState},
+};
+
+//
+// Takes `count` snapshots, at most one every `interval`, numbered in the order they were taken.
+//
+pub struct BurstImageRecorder {
+    // Params
+    destination_folder: PathBuf,
+    image_format: ImageFormat,
+    encode_settings: ImageEncodeSettings,
+    count: usize,
+    interval: Duration,
+    filename_template: FilenameTemplate,
+    overlays: CaptureOverlays,
+
+    // State
+    // Shared by all the files of the burst, set on the first frame
+    base_name: Option<String>,
+    last_capture_time: Option<Instant>,
+
+    // Output info
+    output_files: Vec<PathBuf>,
+    curr_state: RecorderState,
+}
+
+impl BurstImageRecorder {
+    pub fn new(
+        destination_folder: PathBuf,
+        image_format: ImageFormat,
+        encode_settings: ImageEncodeSettings,
+        count: usize,
+        interval: Duration,
+        filename_template: FilenameTemplate,
+        overlays: CaptureOverlays,
+    ) -> BurstImageRecorder {
+        BurstImageRecorder {
+            destination_folder,
+            image_format,
+            encode_settings,
+            count: count.max(1),
+            interval,
+            filename_template,
+            overlays,
+            base_name: None,
+            last_capture_time: None,
+            output_files: vec![],
+            curr_state: RecorderState::Initial,
+        }
+    }
+}
+
+impl Recorder for BurstImageRecorder {
+    fn start(
+        &mut self,
+        _params: super::recorder::RecorderStreamParams,
+    ) -> Result<(), anyhow::Error> {
+        self.curr_state = RecorderState::Recording;
+        Ok(())
+    }
+
+    fn process_result(&mut self, result: &ThermalCapturerResult) -> Result<(), anyhow::Error> {
+        if self
+            .last_capture_time
+            .is_some_and(|last| result.capture_time.saturating_duration_since(last) < self.interval)
+        {
+            return Ok(());
+        }
+        self.last_capture_time = Some(result.capture_time);
+
+        let img = snapshot_image(result, &self.overlays)?;
+
+        std::fs::create_dir_all(self.destination_folder.clone())?;
+        let current_local: DateTime<Local> = Local::now();
+        let base_name = self
+            .base_name
+            .get_or_insert_with(|| {
+                format!(
+                    "{}_burst",
+                    self.filename_template.expand(
+                        &result.camera_short_name,
+                        current_local,
+                        Some(result.captured_range),
+                        "snapshot",
+                    )
+                )
+            })
+            .clone();
+        let save_path = unique_path(
+            &self.destination_folder,
+            &format!("{}_{:03}", base_name, self.output_files.len() + 1),
+            self.image_format.extension(),
+        );
+
+        let encoded = encode_snapshot(
+            &img,
+            result,
+            self.image_format,
+            self.encode_settings,
+            current_local,
+        )?;
+        std::fs::write(&save_path, encoded)?;
+        self.output_files.push(save_path);
+        if self.output_files.len() >= self.count {
+            self.curr_state = RecorderState::Done;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> RecorderState {
+        self.curr_state
+    }
+
+    fn files_created(&self) -> Vec<PathBuf> {
+        self.output_files.clone()
+    }
+
+    fn stop(&mut self) -> Result<(), anyhow::Error> {
+        self.curr_state = RecorderState::Done;
+        Ok(())
+    }
+
+    fn is_continuous(&self) -> bool {
+        true
+    }
+}
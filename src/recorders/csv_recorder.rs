@@ -0,0 +1,122 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    temperature::TemperatureUnit,
+    thermal_capturer::ThermalCapturerResult,
+    types::media_formats::CsvDelimiter,
+    util::{pathify_string, unique_path},
+};
+
+use super::recorder::{Recorder, RecorderState};
+
+//
+// Saves the temperature of every pixel of a single frame as a CSV file.
+// The first row describes the data (width, height and unit), followed by one row per image row.
+//
+pub struct CsvRecorder {
+    // Params
+    destination_folder: PathBuf,
+    unit: TemperatureUnit,
+    delimiter: CsvDelimiter,
+
+    // Output info
+    output_file: Option<PathBuf>,
+    curr_state: RecorderState,
+}
+
+impl CsvRecorder {
+    pub fn new(
+        destination_folder: PathBuf,
+        unit: TemperatureUnit,
+        delimiter: CsvDelimiter,
+    ) -> CsvRecorder {
+        CsvRecorder {
+            destination_folder,
+            unit,
+            delimiter,
+            output_file: None,
+            curr_state: RecorderState::Initial,
+        }
+    }
+}
+
+impl Recorder for CsvRecorder {
+    fn start(
+        &mut self,
+        _params: super::recorder::RecorderStreamParams,
+    ) -> Result<(), anyhow::Error> {
+        self.curr_state = RecorderState::Recording;
+        // Ignore params, we only capture a single frame.
+        Ok(())
+    }
+
+    fn process_result(&mut self, result: &ThermalCapturerResult) -> Result<(), anyhow::Error> {
+        // In the raw counts debug mode the data holds sensor counts, stored as Kelvin
+        let (unit, unit_suffix) = if result.raw_counts {
+            (TemperatureUnit::Kelvin, "counts".to_string())
+        } else {
+            (self.unit, self.unit.suffix_ascii())
+        };
+        let data = &result.thermal_data;
+        let delimiter = self.delimiter.character();
+
+        std::fs::create_dir_all(self.destination_folder.clone())?;
+        let current_local: DateTime<Local> = Local::now();
+        let save_path = unique_path(
+            &self.destination_folder,
+            &format!(
+                "{}_{}",
+                pathify_string(result.camera_short_name.clone()),
+                current_local.format("%Y-%m-%d_%H-%M-%S"),
+            ),
+            "csv",
+        );
+
+        let mut writer = BufWriter::new(File::create(&save_path)?);
+        writeln!(
+            writer,
+            "width{d}{}{d}height{d}{}{d}unit{d}{}",
+            data.width,
+            data.height,
+            unit_suffix,
+            d = delimiter
+        )?;
+        for y in 0..data.height {
+            let row: Vec<String> = (0..data.width)
+                .map(|x| format!("{:.2}", data.temperature_at(x, y).to_unit(unit)))
+                .collect();
+            writeln!(writer, "{}", row.join(&delimiter.to_string()))?;
+        }
+        writer.flush()?;
+
+        self.output_file = Some(save_path);
+        self.curr_state = RecorderState::Done;
+        Ok(())
+    }
+
+    fn state(&self) -> RecorderState {
+        self.curr_state
+    }
+
+    fn files_created(&self) -> Vec<PathBuf> {
+        match &self.output_file {
+            Some(file) => vec![file.clone()],
+            None => vec![],
+        }
+    }
+
+    fn stop(&mut self) -> Result<(), anyhow::Error> {
+        self.curr_state = RecorderState::Done;
+        Ok(())
+    }
+
+    fn is_continuous(&self) -> bool {
+        false
+    }
+}
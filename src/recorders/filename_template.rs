@@ -0,0 +1,77 @@
+use chrono::{DateTime, Local};
+
+use crate::{
+    temperature::{Temp, TempRange, TemperatureUnit},
+    util::pathify_string,
+};
+
+pub const DEFAULT_SNAPSHOT_FILENAME_TEMPLATE: &str = "{camera}_{date}_{time}";
+pub const DEFAULT_VIDEO_FILENAME_TEMPLATE: &str = "video_{date}_{time}";
+
+// Tokens substituted in the filename templates, with their description shown in the preferences
+pub const FILENAME_TEMPLATE_TOKENS: [(&str, &str); 6] = [
+    ("{date}", "Capture date (YYYY-MM-DD)"),
+    ("{time}", "Capture time (HH-MM-SS)"),
+    ("{camera}", "Short name of the camera"),
+    ("{max_temp}", "Maximum temperature of the frame"),
+    ("{min_temp}", "Minimum temperature of the frame"),
+    ("{seq}", "Number of the capture since the start of the app"),
+];
+
+//
+// Name of a captured file, with the tokens from FILENAME_TEMPLATE_TOKENS replaced on capture.
+//
+#[derive(Debug, Clone)]
+pub struct FilenameTemplate {
+    pub template: String,
+    pub sequence: usize,
+    // Unit and decimal places of the {max_temp} and {min_temp} tokens
+    pub unit: TemperatureUnit,
+    pub decimals: usize,
+}
+
+impl FilenameTemplate {
+    //
+    // Returns the file name without the extension, sanitized with pathify_string.
+    // The temperature tokens are left empty when the range is not known yet (e.g. when a video starts),
+    // `fallback` is used if nothing is left of the template.
+    //
+    pub fn expand(
+        &self,
+        camera: &str,
+        local_time: DateTime<Local>,
+        range: Option<TempRange>,
+        fallback: &str,
+    ) -> String {
+        let format_temp = |temp: Option<Temp>| {
+            temp.map(|temp| {
+                format!(
+                    "{}{}",
+                    temp.format(self.unit, self.decimals),
+                    self.unit.suffix_ascii()
+                )
+            })
+            .unwrap_or_default()
+        };
+        let expanded = self
+            .template
+            .replace("{date}", &local_time.format("%Y-%m-%d").to_string())
+            .replace("{time}", &local_time.format("%H-%M-%S").to_string())
+            .replace("{camera}", camera)
+            .replace("{max_temp}", &format_temp(range.map(|r| r.max)))
+            .replace("{min_temp}", &format_temp(range.map(|r| r.min)))
+            .replace("{seq}", &format!("{:04}", self.sequence));
+
+        // Dashes are kept so the date and time stay readable
+        let name = expanded
+            .split('-')
+            .map(|part| pathify_string(part.to_string()))
+            .collect::<Vec<_>>()
+            .join("-");
+        if name.chars().all(|c| c == '_' || c == '-') {
+            pathify_string(fallback.to_string())
+        } else {
+            name
+        }
+    }
+}
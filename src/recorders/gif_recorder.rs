@@ -0,0 +1,202 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    Delay, Frame, RgbaImage,
+};
+
+use crate::{
+    thermal_capturer::ThermalCapturerResult,
+    types::media_formats::VideoFormat,
+    util::{pathify_string, unique_path},
+};
+
+use super::recorder::{Recorder, RecorderState, RecorderStreamParams};
+
+// GIFs are meant for quick sharing, so keep them small
+const GIF_MAX_FRAMERATE: usize = 10;
+const GIF_MAX_WIDTH: u32 = 320;
+
+///
+/// Records a short animated GIF of the colorized thermal image.
+/// Frames are accumulated in memory and encoded on a separate thread once the recording stops
+/// or the maximum duration is reached, the recorder is done when the file is written.
+///
+pub struct GifRecorder {
+    // Params
+    destination_folder: PathBuf,
+    max_duration: Duration,
+    // Additional skip chosen by the user, applied on top of the framerate limit
+    user_frame_skip: usize,
+    // Chosen when the recorder is created, so that conflicts can be detected early
+    planned_path: PathBuf,
+
+    // State
+    frames: Vec<Frame>,
+    frames_seen: usize,
+    frame_skip: usize,
+    frame_delay: Delay,
+    max_frames: usize,
+
+    // Encodes the frames after the recording stopped
+    encoder_thread: Option<JoinHandle<()>>,
+
+    // Output info, set by the encoder thread once the file is written
+    output_file: Arc<Mutex<Option<PathBuf>>>,
+    curr_state: RecorderState,
+}
+
+impl GifRecorder {
+    pub fn new(
+        destination_folder: PathBuf,
+        name_prefix: String,
+        max_duration: Duration,
+        user_frame_skip: usize,
+    ) -> Self {
+        let current_local: DateTime<Local> = Local::now();
+        let planned_path = unique_path(
+            &destination_folder,
+            &format!(
+                "{}_{}",
+                pathify_string(name_prefix),
+                current_local.format("%Y-%m-%d_%H-%M-%S"),
+            ),
+            VideoFormat::GIF.extension(),
+        );
+        GifRecorder {
+            destination_folder,
+            max_duration,
+            user_frame_skip: user_frame_skip.max(1),
+            planned_path,
+            frames: vec![],
+            frames_seen: 0,
+            frame_skip: 1,
+            frame_delay: Delay::from_numer_denom_ms(1000, GIF_MAX_FRAMERATE as u32),
+            max_frames: 0,
+            encoder_thread: None,
+            output_file: Arc::new(Mutex::new(None)),
+            curr_state: RecorderState::Initial,
+        }
+    }
+
+    fn write_gif(
+        destination_folder: &Path,
+        full_path: &Path,
+        frames: Vec<Frame>,
+    ) -> Result<(), anyhow::Error> {
+        std::fs::create_dir_all(destination_folder)?;
+
+        let writer = BufWriter::new(File::create(full_path)?);
+        let mut encoder = GifEncoder::new_with_speed(writer, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(frames)?;
+        Ok(())
+    }
+}
+
+impl Recorder for GifRecorder {
+    fn start(&mut self, params: RecorderStreamParams) -> Result<(), anyhow::Error> {
+        let framerate = params.framerate.max(1);
+
+        // Only keep every n-th frame so that the GIF does not exceed GIF_MAX_FRAMERATE
+        self.frame_skip = framerate.div_ceil(GIF_MAX_FRAMERATE) * self.user_frame_skip;
+        self.frame_delay =
+            Delay::from_numer_denom_ms((1000 * self.frame_skip) as u32, framerate as u32);
+        self.max_frames = ((self.max_duration.as_secs_f32() * framerate as f32) as usize
+            / self.frame_skip)
+            .max(1);
+
+        self.curr_state = RecorderState::Recording;
+        Ok(())
+    }
+
+    fn process_result(&mut self, result: &ThermalCapturerResult) -> Result<(), anyhow::Error> {
+        self.frames_seen += 1;
+        if (self.frames_seen - 1) % self.frame_skip != 0 {
+            return Ok(());
+        }
+
+        let image = &result.image;
+        let mut rgba_img = RgbaImage::from_raw(
+            image.width() as u32,
+            image.height() as u32,
+            image.as_raw().into(),
+        )
+        .ok_or(anyhow!("Failed to create image when recording GIF"))?;
+
+        if rgba_img.width() > GIF_MAX_WIDTH {
+            let height = rgba_img.height() * GIF_MAX_WIDTH / rgba_img.width();
+            rgba_img = imageops::resize(&rgba_img, GIF_MAX_WIDTH, height, FilterType::Nearest);
+        }
+
+        self.frames
+            .push(Frame::from_parts(rgba_img, 0, 0, self.frame_delay));
+
+        if self.frames.len() >= self.max_frames {
+            self.stop()?;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> RecorderState {
+        // Still encoding
+        if self
+            .encoder_thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+        {
+            return RecorderState::Recording;
+        }
+        self.curr_state
+    }
+
+    fn planned_output_path(&self) -> Option<PathBuf> {
+        Some(self.planned_path.clone())
+    }
+
+    fn files_created(&self) -> Vec<PathBuf> {
+        self.output_file
+            .lock()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect()
+    }
+
+    fn stop(&mut self) -> Result<(), anyhow::Error> {
+        if self.curr_state == RecorderState::Done {
+            return Ok(());
+        }
+        self.curr_state = RecorderState::Done;
+        if self.frames.is_empty() {
+            return Ok(());
+        }
+
+        // Encoding takes a while, do not hold up the capture thread
+        let destination_folder = self.destination_folder.clone();
+        let full_path = self.planned_path.clone();
+        let frames = std::mem::take(&mut self.frames);
+        let output_file = self.output_file.clone();
+        self.encoder_thread = Some(thread::spawn(move || {
+            match Self::write_gif(&destination_folder, &full_path, frames) {
+                Ok(()) => *output_file.lock().unwrap() = Some(full_path),
+                Err(err) => log::error!("Failed to write GIF {}: {}", full_path.display(), err),
+            }
+        }));
+        Ok(())
+    }
+
+    fn is_continuous(&self) -> bool {
+        true
+    }
+}
@@ -1,20 +1,38 @@
-use std::path::PathBuf;
+use std::{io::Cursor, path::PathBuf};
 
 use anyhow::anyhow;
 use chrono::{DateTime, Local};
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    RgbImage,
+};
 
 use crate::{
+    gizmos::Gizmo,
+    image_metadata::{embed_jpeg_metadata, embed_png_metadata, ImageMetadata},
+    temperature::{Temp, TemperatureUnit},
     thermal_capturer::ThermalCapturerResult,
-    types::media_formats::ImageFormat,
-    util::{pathify_string, rgba8_to_rgb8},
+    types::{
+        media_formats::{ImageEncodeSettings, ImageFormat},
+        watermark::WatermarkSettings,
+    },
+    util::{
+        draw_corner_label, draw_gizmo_overlay, overlay_color_scale, rgba8_to_rgb8, unique_path,
+    },
 };
 
-use super::recorder::{Recorder, RecorderState};
+use super::{
+    filename_template::FilenameTemplate,
+    recorder::{Recorder, RecorderState},
+};
 
 pub struct ImageRecorder {
     // Params
     destination_folder: PathBuf,
     image_format: ImageFormat,
+    encode_settings: ImageEncodeSettings,
+    filename_template: FilenameTemplate,
+    overlays: CaptureOverlays,
 
     // Output info
     output_file: Option<PathBuf>,
@@ -22,10 +40,19 @@ pub struct ImageRecorder {
 }
 
 impl ImageRecorder {
-    pub fn new(destination_folder: PathBuf, image_format: ImageFormat) -> ImageRecorder {
+    pub fn new(
+        destination_folder: PathBuf,
+        image_format: ImageFormat,
+        encode_settings: ImageEncodeSettings,
+        filename_template: FilenameTemplate,
+        overlays: CaptureOverlays,
+    ) -> ImageRecorder {
         ImageRecorder {
             destination_folder,
             image_format,
+            encode_settings,
+            filename_template,
+            overlays,
             output_file: None,
             curr_state: RecorderState::Initial,
         }
@@ -43,29 +70,30 @@ impl Recorder for ImageRecorder {
     }
 
     fn process_result(&mut self, result: &ThermalCapturerResult) -> Result<(), anyhow::Error> {
-        let image = &result.image;
-        let rgba_img = image::RgbaImage::from_raw(
-            image.width() as u32,
-            image.height() as u32,
-            image.as_raw().into(),
-        )
-        .ok_or(anyhow!("Failed to create image when saving snapshot"))?;
-
-        // Convert to Rgb8, we don't need the alpha channel
-        let img = rgba8_to_rgb8(rgba_img);
+        let img = snapshot_image(result, &self.overlays)?;
 
         std::fs::create_dir_all(self.destination_folder.clone())?;
         let current_local: DateTime<Local> = Local::now();
 
-        let filename = format!(
-            "{}_{}.{}",
-            pathify_string(result.camera_short_name.clone()),
-            current_local.format("%Y-%m-%d_%H-%M-%S"),
-            self.image_format.extension()
+        let save_path = unique_path(
+            &self.destination_folder,
+            &self.filename_template.expand(
+                &result.camera_short_name,
+                current_local,
+                Some(result.captured_range),
+                "snapshot",
+            ),
+            self.image_format.extension(),
         );
 
-        let save_path = self.destination_folder.join(PathBuf::from(filename));
-        img.save(save_path.clone())?;
+        let encoded = encode_snapshot(
+            &img,
+            result,
+            self.image_format,
+            self.encode_settings,
+            current_local,
+        )?;
+        std::fs::write(&save_path, encoded)?;
         self.output_file = Some(save_path);
         self.curr_state = RecorderState::Done;
         Ok(())
@@ -91,3 +119,159 @@ impl Recorder for ImageRecorder {
         false
     }
 }
+
+//
+// Encodes a snapshot of the result in the given format, with the capture metadata embedded.
+//
+pub fn encode_snapshot(
+    img: &RgbImage,
+    result: &ThermalCapturerResult,
+    image_format: ImageFormat,
+    encode_settings: ImageEncodeSettings,
+    capture_local: DateTime<Local>,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let mut encoded = vec![];
+    let writer = Cursor::new(&mut encoded);
+    match image_format {
+        ImageFormat::Jpeg => img.write_with_encoder(JpegEncoder::new_with_quality(
+            writer,
+            encode_settings.jpeg_quality.clamp(1, 100),
+        ))?,
+        ImageFormat::Png => img.write_with_encoder(PngEncoder::new_with_quality(
+            writer,
+            encode_settings.png_compression.compression_type(),
+            image::codecs::png::FilterType::Adaptive,
+        ))?,
+    }
+    let metadata = ImageMetadata {
+        camera: result.camera_short_name.clone(),
+        date_time: capture_local.format("%Y:%m:%d %H:%M:%S").to_string(),
+        comment: metadata_comment(result),
+    };
+    match image_format {
+        ImageFormat::Jpeg => embed_jpeg_metadata(&encoded, &metadata),
+        ImageFormat::Png => embed_png_metadata(&encoded, &metadata),
+    }
+}
+
+//
+// Measurements drawn on saved images and video frames, labeled in `unit`.
+// Taken when the recorder is created, so a recording keeps the measurements it started with.
+//
+#[derive(Clone)]
+pub struct GizmoOverlay {
+    pub gizmos: Vec<Gizmo>,
+    pub unit: TemperatureUnit,
+    pub decimals: usize,
+}
+
+impl GizmoOverlay {
+    pub fn draw(&self, img: &mut RgbImage, result: &ThermalCapturerResult) {
+        let (unit, unit_suffix) = label_unit(result, self.unit);
+        draw_gizmo_overlay(
+            img,
+            &self.gizmos,
+            &result.gizmo_results,
+            result.thermal_data.width,
+            unit,
+            self.decimals,
+            &unit_suffix,
+        );
+    }
+}
+
+//
+// Everything drawn over the frames of saved images and videos.
+//
+#[derive(Clone, Default)]
+pub struct CaptureOverlays {
+    // Unit of the color scale added to the right of snapshots, not drawn on videos
+    pub color_scale_unit: Option<TemperatureUnit>,
+    pub color_scale_decimals: usize,
+    pub gizmos: Option<GizmoOverlay>,
+    pub watermark: Option<WatermarkSettings>,
+}
+
+impl CaptureOverlays {
+    // Draws the overlays covering the frame itself, i.e. all but the color scale
+    pub fn draw_on_frame(&self, img: &mut RgbImage, result: &ThermalCapturerResult) {
+        if let Some(gizmos) = &self.gizmos {
+            gizmos.draw(img, result);
+        }
+        if let Some(watermark) = &self.watermark {
+            draw_corner_label(
+                img,
+                &watermark.text(&result.camera_short_name, result.capture_time),
+                watermark.corner,
+                watermark.font_size,
+                watermark.opacity,
+            );
+        }
+    }
+}
+
+// In the raw counts debug mode the data holds sensor counts, stored as Kelvin
+fn label_unit(result: &ThermalCapturerResult, unit: TemperatureUnit) -> (TemperatureUnit, String) {
+    if result.raw_counts {
+        (TemperatureUnit::Kelvin, "counts".to_string())
+    } else {
+        (unit, unit.suffix())
+    }
+}
+
+//
+// Renders the colorized frame of a result with the requested overlays.
+//
+pub fn snapshot_image(
+    result: &ThermalCapturerResult,
+    overlays: &CaptureOverlays,
+) -> Result<RgbImage, anyhow::Error> {
+    let image = &result.image;
+    let rgba_img = image::RgbaImage::from_raw(
+        image.width() as u32,
+        image.height() as u32,
+        image.as_raw().into(),
+    )
+    .ok_or(anyhow!("Failed to create image when saving snapshot"))?;
+
+    // Convert to Rgb8, we don't need the alpha channel
+    let mut img = rgba8_to_rgb8(rgba_img);
+
+    overlays.draw_on_frame(&mut img, result);
+
+    if let Some(unit) = overlays.color_scale_unit {
+        let (unit, unit_suffix) = label_unit(result, unit);
+        img = overlay_color_scale(
+            &img,
+            &result.scale_colors,
+            result.image_range,
+            unit,
+            overlays.color_scale_decimals,
+            &unit_suffix,
+        );
+    }
+    Ok(img)
+}
+
+//
+// Describes the capture conditions, embedded in the saved image.
+// Temperatures are in Celsius, Min and Max are corrected for the scene emissivity like the measurements.
+//
+fn metadata_comment(result: &ThermalCapturerResult) -> String {
+    let format_temp = |temp: Temp| {
+        let temp = result.correction.apply(temp).0;
+        if result.raw_counts {
+            format!("{:.0} counts", temp.to_unit(TemperatureUnit::Kelvin))
+        } else {
+            format!("{:.1} C", temp.to_unit(TemperatureUnit::Celsius))
+        }
+    };
+    format!(
+        "Camera: {}\nEmissivity: {:.2}\nAmbient: {:.1} C\nMin: {}\nMax: {}",
+        result.camera_short_name,
+        result.correction.emissivity,
+        result.ambient.to_unit(TemperatureUnit::Celsius),
+        format_temp(result.captured_range.min),
+        format_temp(result.captured_range.max),
+    )
+}
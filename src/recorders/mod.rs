@@ -1,3 +1,8 @@
+pub mod burst_image_recorder;
+pub mod csv_recorder;
+pub mod filename_template;
+pub mod gif_recorder;
 pub mod image_recorder;
+pub mod raw_recorder;
 pub mod recorder;
 pub mod video_recorder;
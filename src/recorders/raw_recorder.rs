@@ -0,0 +1,137 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    temperature::TemperatureUnit,
+    thermal_capturer::ThermalCapturerResult,
+    util::{pathify_string, unique_path},
+};
+
+use super::recorder::{Recorder, RecorderState};
+
+pub const RAW_FILE_MAGIC: &[u8; 6] = b"TCRAW1";
+
+// Flags stored in the header
+pub const RAW_FLAG_HAS_COUNTS: u16 = 1 << 0;
+pub const RAW_FLAG_DATA_IS_COUNTS: u16 = 1 << 1;
+pub const RAW_FLAG_HAS_FRAMERATE: u16 = 1 << 2;
+
+//
+// Saves the thermal data of a single frame as a binary .bin file, for offline analysis.
+//
+// Layout, all values little endian, pixels in row-major order (after the image rotation):
+//
+//   offset  size        content
+//   0       6           magic "TCRAW1"
+//   6       2           u16 flags: bit 0 - the sensor counts block is present,
+//                       bit 1 - the temperatures are sensor counts (raw counts debug mode),
+//                       bit 2 - the frame rate field is present
+//   8       4           u32 width
+//   12      4           u32 height
+//   16      4           u32 frame rate of the camera, only if bit 2 of the flags is set
+//   ...     w * h * 4   f32 temperatures in Kelvin
+//   ...     w * h * 2   u16 sensor counts, only if bit 0 of the flags is set
+//
+// A file may hold several such frames one after another, they are replayed in order.
+//
+pub struct RawRecorder {
+    // Params
+    destination_folder: PathBuf,
+    framerate: usize,
+
+    // Output info
+    output_file: Option<PathBuf>,
+    curr_state: RecorderState,
+}
+
+impl RawRecorder {
+    pub fn new(destination_folder: PathBuf) -> RawRecorder {
+        RawRecorder {
+            destination_folder,
+            framerate: 0,
+            output_file: None,
+            curr_state: RecorderState::Initial,
+        }
+    }
+}
+
+impl Recorder for RawRecorder {
+    fn start(
+        &mut self,
+        params: super::recorder::RecorderStreamParams,
+    ) -> Result<(), anyhow::Error> {
+        self.curr_state = RecorderState::Recording;
+        // Only the frame rate is stored, we capture a single frame.
+        self.framerate = params.framerate;
+        Ok(())
+    }
+
+    fn process_result(&mut self, result: &ThermalCapturerResult) -> Result<(), anyhow::Error> {
+        let data = &result.thermal_data;
+
+        std::fs::create_dir_all(self.destination_folder.clone())?;
+        let current_local: DateTime<Local> = Local::now();
+        let save_path = unique_path(
+            &self.destination_folder,
+            &format!(
+                "{}_{}",
+                pathify_string(result.camera_short_name.clone()),
+                current_local.format("%Y-%m-%d_%H-%M-%S"),
+            ),
+            "bin",
+        );
+
+        let mut flags = RAW_FLAG_HAS_FRAMERATE;
+        if data.raw_counts.is_some() {
+            flags |= RAW_FLAG_HAS_COUNTS;
+        }
+        if result.raw_counts {
+            flags |= RAW_FLAG_DATA_IS_COUNTS;
+        }
+
+        let mut writer = BufWriter::new(File::create(&save_path)?);
+        writer.write_all(RAW_FILE_MAGIC)?;
+        writer.write_all(&flags.to_le_bytes())?;
+        writer.write_all(&(data.width as u32).to_le_bytes())?;
+        writer.write_all(&(data.height as u32).to_le_bytes())?;
+        writer.write_all(&(self.framerate as u32).to_le_bytes())?;
+        for temp in data.data.iter() {
+            writer.write_all(&temp.to_unit(TemperatureUnit::Kelvin).to_le_bytes())?;
+        }
+        if let Some(raw_counts) = data.raw_counts.as_ref() {
+            for count in raw_counts.iter() {
+                writer.write_all(&count.to_le_bytes())?;
+            }
+        }
+        writer.flush()?;
+
+        self.output_file = Some(save_path);
+        self.curr_state = RecorderState::Done;
+        Ok(())
+    }
+
+    fn state(&self) -> RecorderState {
+        self.curr_state
+    }
+
+    fn files_created(&self) -> Vec<PathBuf> {
+        match &self.output_file {
+            Some(file) => vec![file.clone()],
+            None => vec![],
+        }
+    }
+
+    fn stop(&mut self) -> Result<(), anyhow::Error> {
+        self.curr_state = RecorderState::Done;
+        Ok(())
+    }
+
+    fn is_continuous(&self) -> bool {
+        false
+    }
+}
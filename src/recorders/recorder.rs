@@ -53,4 +53,12 @@ pub trait Recorder: Send + Sync {
     /// change the image format.
     ///
     fn is_continuous(&self) -> bool;
+
+    ///
+    /// The file this recorder is going to write, if it is known before the recording starts.
+    /// Used to reject recorders which would write to the same file as an active one.
+    ///
+    fn planned_output_path(&self) -> Option<PathBuf> {
+        None
+    }
 }
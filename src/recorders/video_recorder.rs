@@ -1,12 +1,15 @@
 extern crate ffmpeg_next as ffmpeg;
 use std::sync::mpsc::Sender;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{path::PathBuf, sync::mpsc::channel, thread};
 
 use crate::util::rgba8_to_rgb8;
-use crate::{types::media_formats::VideoFormat, util::pathify_string};
+use crate::{
+    types::media_formats::{VideoEncodeSettings, VideoFormat},
+    util::unique_path,
+};
 use anyhow::anyhow;
-use chrono::{DateTime, Local};
 use ffmpeg::ffi::av_log_set_level;
 use ffmpeg::format::{output_as, Pixel};
 use ffmpeg::rescale::TIME_BASE;
@@ -15,16 +18,34 @@ use ffmpeg::{codec, encoder, format, frame, picture, Dictionary, Packet, Rationa
 use image::RgbImage;
 use std::borrow::BorrowMut;
 
-use super::recorder::{Recorder, RecorderState};
+use super::{
+    image_recorder::CaptureOverlays,
+    recorder::{Recorder, RecorderState},
+};
+
+//
+// Records one frame every interval, played back at the output framerate.
+//
+#[derive(Debug, Clone, Copy)]
+pub struct Timelapse {
+    pub interval: Duration,
+    pub output_framerate: usize,
+}
 
 pub struct VideoRecorder {
     // Params
     destination_folder: PathBuf,
-    name_prefix: String,
     video_format: VideoFormat,
+    encode_settings: VideoEncodeSettings,
+    timelapse: Option<Timelapse>,
+    overlays: CaptureOverlays,
+    // Chosen when the recorder is created, so that conflicts can be detected early
+    planned_path: PathBuf,
 
     // State
     tx_frames: Option<Sender<RgbImage>>,
+    // Capture time of the last frame sent to the encoder in timelapse mode
+    last_timelapse_frame: Option<Instant>,
 
     // Output info
     output_file: Option<PathBuf>,
@@ -32,16 +53,28 @@ pub struct VideoRecorder {
 }
 
 impl VideoRecorder {
+    //
+    // `file_name` is without the extension, a number is appended if the file already exists.
+    // The color scale of the overlays is ignored.
+    //
     pub fn new(
         destination_folder: PathBuf,
-        name_prefix: String,
+        file_name: &str,
         video_format: VideoFormat,
+        encode_settings: VideoEncodeSettings,
+        timelapse: Option<Timelapse>,
+        overlays: CaptureOverlays,
     ) -> VideoRecorder {
+        let planned_path = unique_path(&destination_folder, file_name, video_format.extension());
         VideoRecorder {
             destination_folder,
-            name_prefix,
             video_format,
+            encode_settings,
+            timelapse,
+            overlays,
+            planned_path,
             tx_frames: None,
+            last_timelapse_frame: None,
             output_file: None,
             curr_state: RecorderState::Initial,
         }
@@ -57,21 +90,30 @@ impl Recorder for VideoRecorder {
             av_log_set_level(ffmpeg::ffi::AV_LOG_VERBOSE);
         }
 
-        std::fs::create_dir_all(self.destination_folder.clone())?;
-        let current_local: DateTime<Local> = Local::now();
+        if self.encode_settings.bitrate_kbps == 0 {
+            return Err(anyhow!("The video bitrate must be positive"));
+        }
 
-        let filename = format!(
-            "{}_{}.{}",
-            pathify_string(self.name_prefix.clone()),
-            current_local.format("%Y-%m-%d_%H-%M-%S"),
-            self.video_format.extension()
-        );
+        // In timelapse mode the video plays back at its own rate, independent of the camera
+        let framerate = match self.timelapse {
+            Some(timelapse) => timelapse.output_framerate,
+            None => params.framerate,
+        }
+        .max(1);
 
-        let (tx_frames, rx_frames) = channel();
-        self.tx_frames = Some(tx_frames);
-        let full_path = self.destination_folder.join(filename.clone());
-        self.output_file = Some(full_path.clone());
-        let mut octx = output_as(&full_path, "mp4")
+        std::fs::create_dir_all(self.destination_folder.clone())?;
+        let full_path = self.planned_path.clone();
+
+        let (container, codec_id) = container_and_codec(self.video_format)?;
+        let the_codec = encoder::find(codec_id).ok_or_else(|| {
+            anyhow!(
+                "The ffmpeg build does not include a {:?} encoder, which is needed for {}",
+                codec_id,
+                self.video_format.name()
+            )
+        })?;
+
+        let mut octx = output_as(&full_path, container)
             .map_err(|err| anyhow::anyhow!("failed to create output: {}", err))?;
 
         let global_header = octx
@@ -79,7 +121,6 @@ impl Recorder for VideoRecorder {
             .flags()
             .contains(format::flag::Flags::GLOBAL_HEADER);
 
-        let the_codec = encoder::find(codec::Id::H264);
         let mut ost = octx.add_stream(the_codec)?;
         ost.set_time_base(TIME_BASE);
         let ost_index = ost.index(); // output stream
@@ -89,30 +130,53 @@ impl Recorder for VideoRecorder {
 
         encoder.set_height(params.height as u32);
         encoder.set_width(params.width as u32);
-        encoder.set_frame_rate(Some(Rational::new(params.framerate as i32, 1)));
+        encoder.set_frame_rate(Some(Rational::new(framerate as i32, 1)));
         encoder.set_format(Pixel::YUV420P);
 
         encoder.set_qmin(10);
         encoder.set_qmax(51);
-        encoder.set_bit_rate(1_000_000);
-        encoder.set_me_range(16);
-        encoder.set_i_quant_factor(0.71);
+        if self.encode_settings.constant_quality.is_some() {
+            // libvpx only targets a constant quality when no bitrate is set
+            encoder.set_bit_rate(0);
+        } else {
+            encoder.set_bit_rate(self.encode_settings.bitrate_kbps as usize * 1000);
+        }
+        if codec_id == codec::Id::H264 {
+            encoder.set_me_range(16);
+            encoder.set_i_quant_factor(0.71);
+        }
 
-        encoder.set_time_base(Rational::new(1, params.framerate as i32)); // todo change this?
+        encoder.set_time_base(Rational::new(1, framerate as i32)); // todo change this?
         if global_header {
             encoder.set_flags(ffmpeg::codec::flag::Flags::GLOBAL_HEADER);
         }
-        let mut x264_opts = Dictionary::new();
-
-        x264_opts.set("preset", "medium");
+        let mut encoder_opts = Dictionary::new();
+        match codec_id {
+            codec::Id::VP9 => {
+                // libvpx is very slow with its default settings, trade some quality for speed
+                encoder_opts.set("deadline", "realtime");
+                encoder_opts.set("cpu-used", "8");
+                encoder_opts.set("row-mt", "1");
+            }
+            _ => {
+                encoder_opts.set("preset", self.encode_settings.preset.x264_name());
+            }
+        }
+        if let Some(quality) = self.encode_settings.constant_quality {
+            encoder_opts.set("crf", &quality.to_string());
+        }
 
         let mut encoder = encoder
-            .open_as_with(the_codec, x264_opts)
-            .expect("error opening libx264 encoder with supplied settings");
+            .open_as_with(the_codec, encoder_opts)
+            .map_err(|err| anyhow!("failed to open the {:?} encoder: {}", codec_id, err))?;
 
         ost.set_parameters(&encoder);
 
         octx.write_header()?;
+
+        let (tx_frames, rx_frames) = channel();
+        self.tx_frames = Some(tx_frames);
+        self.output_file = Some(full_path.clone());
         let mutexed_octx = Mutex::new(octx);
 
         thread::spawn(move || {
@@ -135,7 +199,7 @@ impl Recorder for VideoRecorder {
 
                 video_frame.set_kind(picture::Type::None);
                 scaler.run(&video_frame, &mut yuv_frame).unwrap();
-                yuv_frame.set_pts(Some((i as i64) * (1_000_000 / params.framerate as i64)));
+                yuv_frame.set_pts(Some((i as i64) * (1_000_000 / framerate as i64)));
                 match encoder.send_frame(&yuv_frame) {
                     Ok(_) => {}
                     Err(err) => {
@@ -171,7 +235,16 @@ impl Recorder for VideoRecorder {
         result: &crate::thermal_capturer::ThermalCapturerResult,
     ) -> Result<(), anyhow::Error> {
         if let Some(tx_frames) = &self.tx_frames {
-            let rgb_img = rgba8_to_rgb8(
+            if let Some(timelapse) = self.timelapse {
+                if self.last_timelapse_frame.is_some_and(|last| {
+                    result.capture_time.duration_since(last) < timelapse.interval
+                }) {
+                    return Ok(());
+                }
+                self.last_timelapse_frame = Some(result.capture_time);
+            }
+
+            let mut rgb_img = rgba8_to_rgb8(
                 image::RgbaImage::from_raw(
                     result.image.width() as u32,
                     result.image.height() as u32,
@@ -179,6 +252,7 @@ impl Recorder for VideoRecorder {
                 )
                 .ok_or(anyhow!("Failed to create image when copying frame"))?,
             );
+            self.overlays.draw_on_frame(&mut rgb_img, result);
 
             tx_frames.send(rgb_img)?;
         }
@@ -189,6 +263,10 @@ impl Recorder for VideoRecorder {
         self.curr_state
     }
 
+    fn planned_output_path(&self) -> Option<PathBuf> {
+        Some(self.planned_path.clone())
+    }
+
     fn files_created(&self) -> Vec<PathBuf> {
         self.output_file.clone().into_iter().collect()
     }
@@ -204,6 +282,18 @@ impl Recorder for VideoRecorder {
     }
 }
 
+//
+// ffmpeg muxer name and video codec used for a format.
+//
+fn container_and_codec(format: VideoFormat) -> Result<(&'static str, codec::Id), anyhow::Error> {
+    match format {
+        VideoFormat::MP4_H264 => Ok(("mp4", codec::Id::H264)),
+        VideoFormat::WEBM_VP9 => Ok(("webm", codec::Id::VP9)),
+        VideoFormat::MKV_VP9 => Ok(("matroska", codec::Id::VP9)),
+        VideoFormat::GIF => Err(anyhow!("GIF recordings are not written with ffmpeg")),
+    }
+}
+
 pub fn convert_rgb_image_to_video_frame(img: RgbImage) -> frame::Video {
     let frame_width = img.width();
     let frame_height = img.height();
@@ -0,0 +1,384 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use image::{Rgb, RgbImage, RgbaImage};
+use imageproc::{drawing::draw_filled_rect_mut, rect::Rect};
+use printpdf::{
+    BuiltinFont, ColorBits, ColorSpace, Image, ImageTransform, ImageXObject, IndirectFontRef, Mm,
+    PdfDocument, PdfDocumentReference, PdfLayerReference, Px,
+};
+
+use crate::{
+    gizmos::{Gizmo, GizmoKind},
+    temperature::{Temp, TemperatureUnit},
+    thermal_capturer::{ThermalCapturerResult, ThermalCapturerSettings},
+    util::{draw_gizmo_markers, rgba8_to_rgb8, unique_path},
+};
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const LINE_HEIGHT: f32 = 5.5;
+// Rough number of characters fitting on a line of body text
+const WRAP_COLUMNS: usize = 95;
+
+//
+// Writes a PDF report of the given frame: the thermal image with the measurements drawn on it,
+// a color legend, a table of the measurements, the histogram and some metadata.
+//
+pub fn export_pdf_report(
+    destination_folder: &Path,
+    result: &ThermalCapturerResult,
+    settings: &ThermalCapturerSettings,
+    unit: TemperatureUnit,
+    decimals: usize,
+    description: &str,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(destination_folder)?;
+    let current_local: DateTime<Local> = Local::now();
+    let path = unique_path(
+        destination_folder,
+        &format!("report_{}", current_local.format("%Y-%m-%d_%H-%M-%S")),
+        "pdf",
+    );
+
+    let format_temp = |temp: Temp| format!("{} {}", temp.format(unit, decimals), unit.suffix());
+
+    let mut report = ReportWriter::new("Thermal Cat report")?;
+    let gizmos: Vec<Gizmo> = settings
+        .gizmo
+        .children()
+        .map(|children| children.iter().filter(|g| g.enabled).cloned().collect())
+        .unwrap_or_default();
+
+    report.text("Thermal Cat report", 18.0, true);
+    report.space(2.0);
+    report.text(
+        &format!("Date: {}", current_local.format("%Y-%m-%d %H:%M:%S")),
+        10.0,
+        false,
+    );
+    report.text(
+        &format!("Camera: {}", result.camera_short_name),
+        10.0,
+        false,
+    );
+    report.text(
+        &format!(
+            "Scene range: {} to {}",
+            format_temp(result.captured_range.min),
+            format_temp(result.captured_range.max)
+        ),
+        10.0,
+        false,
+    );
+    report.text(
+        &format!(
+            "Display range: {} to {} ({})",
+            format_temp(result.image_range.min),
+            format_temp(result.image_range.max),
+            if settings.auto_range {
+                "automatic"
+            } else {
+                "manual"
+            }
+        ),
+        10.0,
+        false,
+    );
+    report.text(
+        &format!("Ambient: {}", format_temp(result.ambient)),
+        10.0,
+        false,
+    );
+    report.text(
+        &format!("Emissivity: {:.2}", result.correction.emissivity),
+        10.0,
+        false,
+    );
+    report.text(&format!("Palette: {}", settings.gradient.name), 10.0, false);
+
+    if !description.trim().is_empty() {
+        report.space(4.0);
+        report.text("Description", 12.0, true);
+        for line in description.lines() {
+            for wrapped in wrap_line(line, WRAP_COLUMNS) {
+                report.text(&wrapped, 10.0, false);
+            }
+        }
+    }
+
+    // Thermal image with a legend on its right side
+    report.space(4.0);
+    let image = annotated_image(result, &gizmos)?;
+    let image_width = 130.0;
+    let image_height = image_width * image.height() as f32 / image.width() as f32;
+    report.ensure_space(image_height);
+    let image_top = report.y;
+    report.image(&image, MARGIN, image_top, image_width);
+
+    let legend_width = 6.0;
+    // Same pixel density as the thermal image, so that both have the same height on the page
+    let legend = legend_image(
+        settings,
+        result,
+        (image.width() as f32 * legend_width / image_width).ceil() as u32,
+        image.height(),
+    );
+    let legend_x = MARGIN + image_width + 5.0;
+    report.image(&legend, legend_x, image_top, legend_width);
+    report.text_at(
+        &format_temp(result.image_range.max),
+        9.0,
+        legend_x + 8.0,
+        image_top - 3.0,
+    );
+    report.text_at(
+        &format_temp(result.image_range.min),
+        9.0,
+        legend_x + 8.0,
+        image_top - image_height,
+    );
+    report.y -= image_height;
+
+    report.space(6.0);
+    report.text("Measurements", 12.0, true);
+    for gizmo in &gizmos {
+        let Some(gizmo_result) = result.gizmo_results.get(&gizmo.uuid) else {
+            continue;
+        };
+        let value = match (&gizmo.kind, gizmo_result.integrated_above_ambient) {
+            (_, Some(integrated)) => format!("{:.0} K·px above ambient", integrated),
+            (GizmoKind::Delta { .. }, None) => format!(
+                "Delta {} {}",
+                gizmo_result
+                    .delta
+                    .unwrap_or_default()
+                    .format_diff(unit, decimals),
+                unit.suffix()
+            ),
+            (GizmoKind::Rect { .. }, None) => format!(
+                "min {}, avg {}, max {}",
+                format_temp(gizmo_result.min),
+                format_temp(gizmo_result.avg),
+                format_temp(gizmo_result.max)
+            ),
+            _ => format_temp(gizmo_result.temperature),
+        };
+        let clamped = if gizmo_result.clamped {
+            " (clamped to camera range)"
+        } else {
+            ""
+        };
+        report.text(
+            &format!(
+                "{}  ({}, {}): {}{}",
+                gizmo.name, gizmo_result.pos.x, gizmo_result.pos.y, value, clamped
+            ),
+            10.0,
+            false,
+        );
+    }
+
+    report.space(6.0);
+    report.text("Histogram", 12.0, true);
+    let histogram = histogram_image(settings, result);
+    let histogram_width = PAGE_WIDTH - 2.0 * MARGIN;
+    let histogram_height = histogram_width * histogram.height() as f32 / histogram.width() as f32;
+    report.ensure_space(histogram_height + LINE_HEIGHT);
+    let histogram_top = report.y;
+    report.image(&histogram, MARGIN, histogram_top, histogram_width);
+    report.y -= histogram_height;
+    if let (Some(first), Some(last)) = (
+        result.histogram.points.first(),
+        result.histogram.points.last(),
+    ) {
+        report.text_at(&format_temp(first.temperature), 9.0, MARGIN, report.y - 4.0);
+        report.text_at(
+            &format_temp(last.temperature),
+            9.0,
+            PAGE_WIDTH - MARGIN - 20.0,
+            report.y - 4.0,
+        );
+    }
+
+    report.save(&path)?;
+    Ok(path)
+}
+
+// Naive word wrapping, good enough for short descriptions
+fn wrap_line(line: &str, columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + word.chars().count() + 1 > columns {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines
+}
+
+// The captured image with the point and region measurements drawn on it
+fn annotated_image(result: &ThermalCapturerResult, gizmos: &[Gizmo]) -> Result<RgbImage> {
+    let rgba_img = RgbaImage::from_raw(
+        result.image.width() as u32,
+        result.image.height() as u32,
+        result.image.as_raw().into(),
+    )
+    .ok_or(anyhow!("Failed to create image for the report"))?;
+    let mut img = rgba8_to_rgb8(rgba_img);
+    draw_gizmo_markers(
+        &mut img,
+        gizmos,
+        &result.gizmo_results,
+        result.thermal_data.width,
+    );
+    Ok(img)
+}
+
+// Vertical color bar from the maximum (top) to the minimum (bottom) of the display range
+fn legend_image(
+    settings: &ThermalCapturerSettings,
+    result: &ThermalCapturerResult,
+    width: u32,
+    height: u32,
+) -> RgbImage {
+    let height = height.max(2);
+    RgbImage::from_fn(width.max(1), height, |_, y| {
+        let factor = 1.0 - y as f32 / (height - 1) as f32;
+        let color = settings.temp_to_color(
+            result.image_range.factor_to_temp(factor),
+            Some(result.image_range),
+        );
+        Rgb([color.r(), color.g(), color.b()])
+    })
+}
+
+fn histogram_image(settings: &ThermalCapturerSettings, result: &ThermalCapturerResult) -> RgbImage {
+    const BAR_WIDTH: u32 = 4;
+    const HEIGHT: u32 = 100;
+    let points = &result.histogram.points;
+    let mut img = RgbImage::from_pixel(
+        (points.len() as u32 * BAR_WIDTH).max(1),
+        HEIGHT,
+        Rgb([255, 255, 255]),
+    );
+    let max_factor = points
+        .iter()
+        .map(|p| p.factor)
+        .fold(0.0_f32, f32::max)
+        .max(f32::EPSILON);
+    for (i, point) in points.iter().enumerate() {
+        let bar_height = ((point.factor / max_factor) * HEIGHT as f32).round() as u32;
+        if bar_height == 0 {
+            continue;
+        }
+        let color = settings.temp_to_color(point.temperature, Some(result.image_range));
+        draw_filled_rect_mut(
+            &mut img,
+            Rect::at((i as u32 * BAR_WIDTH) as i32, (HEIGHT - bar_height) as i32)
+                .of_size(BAR_WIDTH, bar_height),
+            Rgb([color.r(), color.g(), color.b()]),
+        );
+    }
+    img
+}
+
+//
+// Lays out text and images top to bottom, starting new pages as needed.
+//
+struct ReportWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: IndirectFontRef,
+    bold_font: IndirectFontRef,
+    // Current vertical position (from the bottom of the page, like in PDF)
+    y: f32,
+}
+
+impl ReportWriter {
+    fn new(title: &str) -> Result<Self> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold_font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self {
+            doc,
+            layer,
+            font,
+            bold_font,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height >= MARGIN {
+            return;
+        }
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn text(&mut self, text: &str, size: f32, bold: bool) {
+        let line_height = LINE_HEIGHT.max(size * 0.45);
+        self.ensure_space(line_height);
+        self.y -= line_height;
+        let font = if bold { &self.bold_font } else { &self.font };
+        self.layer
+            .use_text(text, size, Mm(MARGIN), Mm(self.y), font);
+    }
+
+    fn text_at(&self, text: &str, size: f32, x: f32, y: f32) {
+        self.layer.use_text(text, size, Mm(x), Mm(y), &self.font);
+    }
+
+    // Places the image with its top left corner at (x, top), scaled to the given width
+    fn image(&self, img: &RgbImage, x: f32, top: f32, width: f32) {
+        let height = width * img.height() as f32 / img.width() as f32;
+        // printpdf sizes images by their DPI
+        let dpi = img.width() as f32 * 25.4 / width;
+        Image::from(ImageXObject {
+            width: Px(img.width() as usize),
+            height: Px(img.height() as usize),
+            color_space: ColorSpace::Rgb,
+            bits_per_component: ColorBits::Bit8,
+            interpolate: false,
+            image_data: img.as_raw().clone(),
+            image_filter: None,
+            smask: None,
+            clipping_bbox: None,
+        })
+        .add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(x)),
+                translate_y: Some(Mm(top - height)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn save(self, path: &Path) -> Result<()> {
+        self.doc.save(&mut BufWriter::new(File::create(path)?))?;
+        Ok(())
+    }
+}
@@ -36,6 +36,7 @@ impl Temp {
                 TemperatureUnit::Kelvin => value,
                 TemperatureUnit::Celsius => value + 273.15,
                 TemperatureUnit::Fahrenheit => (value - 32.0) / 1.8 + 273.15,
+                TemperatureUnit::Rankine => value / 1.8,
             },
         }
     }
@@ -44,8 +45,58 @@ impl Temp {
             TemperatureUnit::Kelvin => self.value_kelvin,
             TemperatureUnit::Celsius => self.value_kelvin - 273.15,
             TemperatureUnit::Fahrenheit => (self.value_kelvin - 273.15) * 1.8 + 32.0,
+            TemperatureUnit::Rankine => self.value_kelvin * 1.8,
         }
     }
+
+    // Converts a temperature difference, which is not offset by the zero of the unit
+    pub fn diff_to_unit(self, unit: TemperatureUnit) -> f32 {
+        match unit {
+            TemperatureUnit::Kelvin | TemperatureUnit::Celsius => self.value_kelvin,
+            TemperatureUnit::Fahrenheit | TemperatureUnit::Rankine => self.value_kelvin * 1.8,
+        }
+    }
+
+    // Inverse of diff_to_unit
+    pub fn from_diff_unit(unit: TemperatureUnit, value: f32) -> Self {
+        Self {
+            value_kelvin: match unit {
+                TemperatureUnit::Kelvin | TemperatureUnit::Celsius => value,
+                TemperatureUnit::Fahrenheit | TemperatureUnit::Rankine => value / 1.8,
+            },
+        }
+    }
+
+    // Formats the temperature in the given unit (without the suffix) for display
+    pub fn format(self, unit: TemperatureUnit, decimals: usize) -> String {
+        format_temperature_value(self.to_unit(unit) as f64, decimals)
+    }
+
+    // Formats a temperature difference, always with a sign
+    pub fn format_diff(self, unit: TemperatureUnit, decimals: usize) -> String {
+        format!("{:+.*}", decimals, self.diff_to_unit(unit))
+    }
+
+    //
+    // Treats this temperature as a reading taken with an emissivity of 1 and returns
+    // the temperature of a surface with the given emissivity, which also reflects
+    // its surroundings at `reflected`. Follows the Stefan-Boltzmann law.
+    //
+    pub fn with_emissivity(self, emissivity: f32, reflected: Temp) -> Temp {
+        let emissivity = emissivity.clamp(0.01, 1.0) as f64;
+        let apparent = (self.value_kelvin as f64).powi(4);
+        let reflected = (reflected.value_kelvin as f64).powi(4);
+        let object = ((apparent - (1.0 - emissivity) * reflected) / emissivity).max(0.0);
+        Temp::new(object.powf(0.25) as f32)
+    }
+}
+
+//
+// Formats a value already converted to a temperature unit, e.g. a plot coordinate.
+// All temperatures shown in the UI go through here, so that their format is consistent.
+//
+pub fn format_temperature_value(value: f64, decimals: usize) -> String {
+    format!("{:.*}", decimals, value)
 }
 
 impl Default for Temp {
@@ -143,6 +194,13 @@ impl TempRange {
         temp.value_kelvin >= self.min.value_kelvin && temp.value_kelvin <= self.max.value_kelvin
     }
 
+    pub fn clamp(&self, temp: Temp) -> Temp {
+        Temp::new(
+            temp.value_kelvin
+                .clamp(self.min.value_kelvin, self.max.value_kelvin),
+        )
+    }
+
     pub fn contains_range(&self, range: TempRange) -> bool {
         self.contains(range.min) && self.contains(range.max)
     }
@@ -190,6 +248,7 @@ pub enum TemperatureUnit {
     Kelvin,
     Celsius,
     Fahrenheit,
+    Rankine,
 }
 
 impl TemperatureUnit {
@@ -198,6 +257,75 @@ impl TemperatureUnit {
             TemperatureUnit::Kelvin => "K".to_string(),
             TemperatureUnit::Celsius => "°C".to_string(),
             TemperatureUnit::Fahrenheit => "°F".to_string(),
+            TemperatureUnit::Rankine => "°R".to_string(),
+        }
+    }
+
+    // Suffix without the degree symbol, for exported files read by other tools
+    pub fn suffix_ascii(&self) -> String {
+        match self {
+            TemperatureUnit::Kelvin => "K".to_string(),
+            TemperatureUnit::Celsius => "C".to_string(),
+            TemperatureUnit::Fahrenheit => "F".to_string(),
+            TemperatureUnit::Rankine => "R".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn converts_known_points() {
+        // Freezing and boiling point of water, absolute zero
+        for (kelvin, celsius, fahrenheit, rankine) in [
+            (273.15, 0.0, 32.0, 491.67),
+            (373.15, 100.0, 212.0, 671.67),
+            (0.0, -273.15, -459.67, 0.0),
+        ] {
+            let temp = Temp::new(kelvin);
+            assert_close(temp.to_unit(TemperatureUnit::Kelvin), kelvin);
+            assert_close(temp.to_unit(TemperatureUnit::Celsius), celsius);
+            assert_close(temp.to_unit(TemperatureUnit::Fahrenheit), fahrenheit);
+            assert_close(temp.to_unit(TemperatureUnit::Rankine), rankine);
         }
     }
+
+    #[test]
+    fn unit_round_trips() {
+        for unit in TemperatureUnit::iter() {
+            for value in [-40.0, 0.0, 21.5, 300.0, 1000.0] {
+                assert_close(Temp::from_unit(unit, value).to_unit(unit), value);
+                assert_close(Temp::from_diff_unit(unit, value).diff_to_unit(unit), value);
+            }
+            for kelvin in [0.0, 255.5, 310.15] {
+                let temp = Temp::new(kelvin);
+                assert_close(
+                    Temp::from_unit(unit, temp.to_unit(unit)).to_unit(TemperatureUnit::Kelvin),
+                    kelvin,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn differences_are_not_offset() {
+        let diff = Temp::from_celsius(30.0) - Temp::from_celsius(20.0);
+        assert_close(diff.diff_to_unit(TemperatureUnit::Celsius), 10.0);
+        assert_close(diff.diff_to_unit(TemperatureUnit::Kelvin), 10.0);
+        assert_close(diff.diff_to_unit(TemperatureUnit::Fahrenheit), 18.0);
+        assert_close(diff.diff_to_unit(TemperatureUnit::Rankine), 18.0);
+    }
 }
@@ -2,14 +2,21 @@ use eframe::egui::{self, DragValue, Response, Ui};
 
 use crate::temperature::{Temp, TempRange, TemperatureUnit};
 
-pub fn temperature_edit_field(ui: &mut Ui, unit: TemperatureUnit, value: &mut Temp) -> Response {
+pub fn temperature_edit_field(
+    ui: &mut Ui,
+    unit: TemperatureUnit,
+    allow_expressions: bool,
+    value: &mut Temp,
+) -> Response {
     let mut tmp_value = value.to_unit(unit);
-    let res = ui.add(
-        DragValue::new(&mut tmp_value)
-            .speed(0.5)
-            .max_decimals(1)
-            .suffix(unit.suffix()),
-    );
+    let mut drag_value = DragValue::new(&mut tmp_value)
+        .speed(0.5)
+        .max_decimals(1)
+        .suffix(unit.suffix());
+    if allow_expressions {
+        drag_value = drag_value.custom_parser(move |text| parse_temperature_expression(text, unit));
+    }
+    let res = ui.add(drag_value);
     *value = Temp::from_unit(unit, tmp_value);
     res
 }
@@ -19,6 +26,7 @@ pub fn temperature_range_edit_field(
     id_source: impl std::hash::Hash,
     enabled: bool,
     unit: TemperatureUnit,
+    allow_expressions: bool,
     value: &mut TempRange,
 ) -> Response {
     let mut did_change = false;
@@ -30,13 +38,13 @@ pub fn temperature_range_edit_field(
             ui.label("Min");
             ui.label("Max");
             ui.end_row();
-            if temperature_edit_field(ui, unit, &mut value.min).changed() {
+            if temperature_edit_field(ui, unit, allow_expressions, &mut value.min).changed() {
                 did_change = true;
                 if value.min > value.max {
                     value.max = value.min;
                 }
             }
-            if temperature_edit_field(ui, unit, &mut value.max).changed() {
+            if temperature_edit_field(ui, unit, allow_expressions, &mut value.max).changed() {
                 did_change = true;
                 if value.max < value.min {
                     value.min = value.max;
@@ -51,3 +59,186 @@ pub fn temperature_range_edit_field(
     }
     resp
 }
+
+//
+// Parses simple arithmetic expressions typed into temperature fields, such as "25+5" or "(20+30)/2".
+// Numbers may be suffixed with a unit (K, C, °C, F, °F, R, °R), in which case they are converted
+// from that unit to the unit of the field, so "300K" typed into a °C field gives 26.85.
+//
+// Returns None if the text is not a valid expression.
+//
+pub fn parse_temperature_expression(text: &str, unit: TemperatureUnit) -> Option<f64> {
+    let chars = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '−' { '-' } else { c })
+        .collect();
+    let mut parser = ExpressionParser {
+        chars,
+        pos: 0,
+        unit,
+    };
+    let value = parser.expression()?;
+    if parser.pos != parser.chars.len() {
+        return None;
+    }
+    value.is_finite().then_some(value)
+}
+
+struct ExpressionParser {
+    chars: Vec<char>,
+    pos: usize,
+    // Unit the result is expressed in
+    unit: TemperatureUnit,
+}
+
+impl ExpressionParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(op) = self.peek().filter(|c| *c == '+' || *c == '-') {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    // term := factor (('*' | '/') factor)*
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        while let Some(op) = self.peek().filter(|c| *c == '*' || *c == '/') {
+            self.pos += 1;
+            let rhs = self.factor()?;
+            value = if op == '*' { value * rhs } else { value / rhs };
+        }
+        Some(value)
+    }
+
+    // factor := '-' factor | '(' expression ')' | number
+    fn factor(&mut self) -> Option<f64> {
+        match self.peek()? {
+            '-' => {
+                self.pos += 1;
+                if self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    // negative literals keep their sign before unit conversion, so "-10C" is -10 °C
+                    self.number(true)
+                } else {
+                    Some(-self.factor()?)
+                }
+            }
+            '(' => {
+                self.pos += 1;
+                let value = self.expression()?;
+                if self.peek()? != ')' {
+                    return None;
+                }
+                self.pos += 1;
+                Some(value)
+            }
+            _ => self.number(false),
+        }
+    }
+
+    // number := digits ['.' digits] [unit]
+    fn number(&mut self, negative: bool) -> Option<f64> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        {
+            self.pos += 1;
+        }
+        let literal: String = self.chars[start..self.pos]
+            .iter()
+            .map(|c| if *c == ',' { '.' } else { *c })
+            .collect();
+        let mut value: f64 = literal.parse().ok()?;
+        if negative {
+            value = -value;
+        }
+        match self.unit_suffix() {
+            Some(unit) => Some(Temp::from_unit(unit, value as f32).to_unit(self.unit) as f64),
+            None => Some(value),
+        }
+    }
+
+    fn unit_suffix(&mut self) -> Option<TemperatureUnit> {
+        let mut pos = self.pos;
+        if self.chars.get(pos) == Some(&'°') {
+            pos += 1;
+        }
+        let unit = match self.chars.get(pos)?.to_ascii_uppercase() {
+            'K' => TemperatureUnit::Kelvin,
+            'C' => TemperatureUnit::Celsius,
+            'F' => TemperatureUnit::Fahrenheit,
+            'R' => TemperatureUnit::Rankine,
+            _ => return None,
+        };
+        self.pos = pos + 1;
+        Some(unit)
+    }
+}
+
+pub fn emissivity_edit_field(ui: &mut Ui, value: &mut f32) -> Response {
+    ui.add(
+        DragValue::new(value)
+            .range(0.01..=1.0)
+            .speed(0.01)
+            .max_decimals(2)
+            .prefix("ε "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_parses(text: &str, unit: TemperatureUnit, expected: f64) {
+        let value = parse_temperature_expression(text, unit)
+            .unwrap_or_else(|| panic!("{:?} did not parse", text));
+        assert!(
+            (value - expected).abs() < 1e-3,
+            "{:?} parsed as {}, expected {}",
+            text,
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn parses_expressions() {
+        assert_parses("25+5", TemperatureUnit::Celsius, 30.0);
+        assert_parses("(20 + 30) / 2", TemperatureUnit::Celsius, 25.0);
+        assert_parses("-10*2", TemperatureUnit::Celsius, -20.0);
+        assert_parses("1,5", TemperatureUnit::Celsius, 1.5);
+        assert_eq!(
+            parse_temperature_expression("25+", TemperatureUnit::Celsius),
+            None
+        );
+        assert_eq!(
+            parse_temperature_expression("(25", TemperatureUnit::Celsius),
+            None
+        );
+        assert_eq!(
+            parse_temperature_expression("1/0", TemperatureUnit::Celsius),
+            None
+        );
+    }
+
+    #[test]
+    fn converts_unit_suffixes() {
+        assert_parses("300K", TemperatureUnit::Celsius, 26.85);
+        assert_parses("-10C", TemperatureUnit::Kelvin, 263.15);
+        assert_parses("212°F", TemperatureUnit::Celsius, 100.0);
+        assert_parses("32f", TemperatureUnit::Kelvin, 273.15);
+        assert_parses("491.67R", TemperatureUnit::Celsius, 0.0);
+        assert_parses("671.67°R", TemperatureUnit::Fahrenheit, 212.0);
+        assert_parses("0r", TemperatureUnit::Kelvin, 0.0);
+        assert_parses("20C", TemperatureUnit::Rankine, 527.67);
+    }
+}
@@ -6,57 +6,211 @@ use std::{
 
 use anyhow::{anyhow, Error};
 use eframe::epaint::{Color32, ColorImage};
-use nokhwa::Camera;
+use nokhwa::utils::CameraFormat;
 use uuid::Uuid;
 
 use crate::{
-    auto_display_range_controller::AutoDisplayRangeController,
-    camera_adapter::CameraAdapter,
+    auto_display_range_controller::{AutoDisplayRangeController, AutoRangeSettings},
     dynamic_range_curve::DynamicRangeCurve,
     gizmos::{Gizmo, GizmoKind, GizmoResult},
     recorders::recorder::{Recorder, RecorderState, RecorderStreamParams},
-    temperature::{Temp, TempRange},
-    thermal_data::ThermalDataHistogram,
+    temperature::{Temp, TempRange, TemperatureUnit},
+    thermal_data::{ThermalData, ThermalDataHistogram, ThermalDataPos, ThermalDataRect},
+    thermal_data_source::ThermalDataSource,
     thermal_gradient::ThermalGradient,
     types::image_rotation::ImageRotation,
+    util::{blend_edge_map, sobel_edge_map},
 };
 
+#[derive(Clone)]
 pub struct ThermalCapturerResult {
     pub image: ColorImage,
     pub image_range: TempRange,
+    // Actual range of temperatures in the captured frame
+    pub captured_range: TempRange,
+    // Mean temperature of the captured frame
+    pub captured_avg: Temp,
     pub real_fps: f32,
     pub reported_fps: f32,
     pub histogram: ThermalDataHistogram,
     pub gizmo_results: HashMap<Uuid, GizmoResult>,
     pub capture_time: std::time::Instant,
     pub camera_short_name: String,
+    // Rotated temperature data of the frame, used for probing temperatures in the UI
+    pub thermal_data: ThermalData,
+    // The "temperatures" in this result are raw sensor counts (debug mode)
+    pub raw_counts: bool,
+    // Colors along image_range, from the minimum to the maximum, for drawing color scales
+    pub scale_colors: Vec<Color32>,
+    // Set on the frame a flat field reference has been captured from
+    pub flat_field_offsets: Option<Vec<Temp>>,
+    // Ambient temperature the frame was measured with
+    pub ambient: Temp,
+    // Focus assist score of the frame, see ThermalData::sharpness
+    pub sharpness: f32,
+    // Corrections applied to the readings of the gizmos
+    pub correction: ReadingCorrection,
+}
+
+impl ThermalCapturerResult {
+    // Corrected temperature of a pixel, the same value a spot gizmo on it shows
+    pub fn measured_temperature_at(&self, x: usize, y: usize) -> Temp {
+        self.correction
+            .apply(self.thermal_data.temperature_at(x, y))
+            .0
+    }
+}
+
+//
+// Corrections turning the temperatures of a frame into the displayed readings.
+//
+#[derive(Clone, Copy)]
+pub struct ReadingCorrection {
+    // Emissivity of the scene, 1 leaves the readings as reported by the camera
+    pub emissivity: f32,
+    // Reflected apparent temperature used by the emissivity correction
+    pub reflected: Temp,
+    // Readings outside of this range are clamped to it
+    pub clamp_range: Option<TempRange>,
+}
+
+impl ReadingCorrection {
+    // Returns the corrected temperature and whether it has been clamped
+    pub fn apply(&self, temp: Temp) -> (Temp, bool) {
+        let temp = if self.emissivity < 1.0 {
+            temp.with_emissivity(self.emissivity, self.reflected)
+        } else {
+            temp
+        };
+        match self.clamp_range {
+            Some(range) if !range.contains(temp) => (range.clamp(temp), true),
+            _ => (temp, false),
+        }
+    }
+}
+
+//
+// Edges blended over the colorized image to help with orientation.
+// The edge map is computed from the thermal image itself for now,
+// a camera with a visible-light stream would provide it instead.
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeOverlaySettings {
+    // 0 hides the edges, 1 draws the strongest ones in solid white
+    pub opacity: f32,
+}
+
+impl Default for EdgeOverlaySettings {
+    fn default() -> Self {
+        Self { opacity: 0.5 }
+    }
+}
+
+// Where the ambient temperature of the measurements comes from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AmbientSource {
+    // The `ambient` of the settings
+    Manual,
+    // Average of a rectangle measurement viewing a surface at ambient temperature (e.g. a wall),
+    // falls back to the manual value if the measurement is missing
+    FromGizmo(Uuid),
 }
 
 #[derive(Clone)]
 pub struct ThermalCapturerSettings {
     pub auto_range: bool,
+    // Auto range over the warmer part of the scene, ignoring a cold background
+    pub auto_range_ignore_background: bool,
+    pub auto_range_settings: AutoRangeSettings,
     pub manual_range: TempRange,
     pub gradient: ThermalGradient,
+    // Map cold temperatures to the warm end of the gradient and vice versa
+    pub invert_gradient: bool,
     pub rotation: ImageRotation,
+    // Mirror the image, applied before the rotation (to correct the mounting of the camera)
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
     pub gizmo: Gizmo,
+    // Manual ambient temperature, also the fallback of ambient_source
+    pub ambient: Temp,
+    pub ambient_source: AmbientSource,
+    // Emissivity of the scene, applied to all measurements. Rect measurements may override it.
+    pub emissivity: f32,
+    // Reflected apparent temperature for the emissivity correction, None uses the ambient
+    pub reflected_temp: Option<Temp>,
+    // Clamp measurements and the auto range to the valid range of the camera
+    pub clamp_to_camera_range: bool,
+    // Debug mode: measure and colorize raw sensor counts instead of temperatures
+    pub show_raw_counts: bool,
     pub dynamic_range_curve: DynamicRangeCurve,
+    // Non-uniformity correction: per-pixel offsets subtracted from every frame, before rotation
+    pub nuc_offsets: Option<Vec<Temp>>,
+    // Capture the next frame as the flat field reference for nuc_offsets
+    pub capture_flat_field: bool,
+    // Temperatures inside the band are drawn with a solid color instead of the gradient
+    pub isotherm: Option<(TempRange, Color32)>,
+    // Emit results no faster than this, the remaining frames of the source are dropped
+    pub target_fps: Option<f32>,
+    // Keep the Max and Min markers in place until another pixel is hotter (colder)
+    // than the marked one by more than the margin
+    pub stabilize_extremes: bool,
+    pub stabilize_extremes_margin: Temp,
+    // The colorized image is rendered from the data upscaled by this factor, 1 disables it.
+    // Measurements and gizmo positions keep using the original resolution.
+    pub upscale_factor: usize,
+    // Weight of the previous frames in the temporal denoise (exponential moving average), 0 is off
+    pub denoise_strength: f32,
+    // Spatial box blur, 0 or 1 disables it
+    pub blur_kernel_size: usize,
+    // Only blur the displayed image, measurements use the unblurred data
+    pub blur_display_only: bool,
+    // Number of buckets of the histogram in the results
+    pub histogram_buckets: usize,
+    // Outline the edges of the scene over the colorized image (MSX-like), None disables it
+    pub edge_overlay: Option<EdgeOverlaySettings>,
     pub recorders: Vec<Arc<Mutex<dyn Recorder>>>,
 }
 
 impl ThermalCapturerSettings {
     //
     // Returns the color corresponding to the given temperature,
-    // applying all necessary transformations (dynamic range curve, gradient)
+    // applying all necessary transformations (isotherm, dynamic range curve, gradient)
     //
     // override_range should be the actual range of the image. If not available, pass None.
     //
     pub fn temp_to_color(&self, temp: Temp, override_range: Option<TempRange>) -> Color32 {
+        if let Some((band, color)) = self.isotherm {
+            if band.contains(temp) {
+                return color;
+            }
+        }
         let mut fac = override_range.unwrap_or(self.manual_range).factor(temp);
         fac = self.dynamic_range_curve.get_value(fac);
+        if self.invert_gradient {
+            fac = 1.0 - fac;
+        }
         self.gradient.get_color(fac)
     }
+
+    // Names of the enabled processing steps that only change the displayed image, not the measurements
+    pub fn display_only_processing(&self) -> Vec<&'static str> {
+        let mut steps = vec![];
+        if self.blur_kernel_size > 1 && self.blur_display_only {
+            steps.push("blur");
+        }
+        if self.upscale_factor > 1 {
+            steps.push("upscaling");
+        }
+        if self.edge_overlay.is_some() {
+            steps.push("edge overlay");
+        }
+        steps
+    }
 }
 
+// Number of colors sampled for the color scale of each result
+const SCALE_COLORS: usize = 256;
+
 pub type ThermalCapturerCallback = Arc<dyn Fn() + Send + Sync>;
 
 enum ThermalCapturerCmd {
@@ -65,98 +219,303 @@ enum ThermalCapturerCmd {
 }
 
 struct ThermalCapturerCtx {
-    camera: Camera,
+    source: Box<dyn ThermalDataSource>,
     callback: ThermalCapturerCallback,
     cmd_receiver: mpsc::Receiver<ThermalCapturerCmd>,
     result_sender: mpsc::Sender<Result<Box<ThermalCapturerResult>, Error>>,
-    adapter: Arc<dyn CameraAdapter>,
     settings: ThermalCapturerSettings,
     auto_range_controller: AutoDisplayRangeController,
-    last_frame_time: std::time::Instant,
+    // Time the last result has been produced at, used for the decimation and real_fps
+    last_result_time: std::time::Instant,
+    // Positions of the Min and Max markers in the last result, when stabilize_extremes is on
+    stable_extremes: Option<(ThermalDataPos, ThermalDataPos)>,
+    // Denoised previous frame and whether it holds raw counts
+    denoise_accumulator: Option<(ThermalData, bool)>,
 }
 
 pub struct ThermalCapturer {
     ctx: Option<ThermalCapturerCtx>,
     cmd_sender: mpsc::Sender<ThermalCapturerCmd>,
 
+    // Format negotiated with the camera when it was opened and the one the camera adapter
+    // expects the camera to deliver, None when not capturing from a camera
+    pub stream_formats: Option<(CameraFormat, CameraFormat)>,
+
     pub result_receiver: mpsc::Receiver<Result<Box<ThermalCapturerResult>, Error>>,
 }
 
 ///
-/// ThermalCapturer runs in a background thread continuously capturing images from the camera,
+/// ThermalCapturer runs in a background thread continuously capturing images from the source,
 /// And calling the callback function with the captured image.
 impl ThermalCapturer {
     pub fn new(
-        camera: Camera,
-        adapter: Arc<dyn CameraAdapter>,
+        source: Box<dyn ThermalDataSource>,
         default_settings: ThermalCapturerSettings,
         callback: ThermalCapturerCallback,
     ) -> Self {
         let (cmd_sender, cmd_receiver) = mpsc::channel();
         let (result_sender, result_receiver) = mpsc::channel();
+        let stream_formats = source.stream_formats();
+        let auto_range_settings = default_settings.auto_range_settings;
         Self {
             ctx: Some(ThermalCapturerCtx {
-                camera,
-                adapter,
+                source,
                 callback,
                 cmd_receiver,
                 result_sender,
                 settings: default_settings,
-                auto_range_controller: AutoDisplayRangeController::new(),
-                last_frame_time: std::time::Instant::now(),
+                auto_range_controller: AutoDisplayRangeController::new(auto_range_settings),
+                last_result_time: std::time::Instant::now(),
+                stable_extremes: None,
+                denoise_accumulator: None,
             }),
             cmd_sender,
+            stream_formats,
             result_receiver,
         }
     }
 
     //
     pub fn start(&mut self) {
-        // move the camera out of self so we can use it into the thread
+        // move the source out of self so we can use it into the thread
         let mut ctx = self.ctx.take().unwrap();
         thread::spawn(move || {
-            ctx.camera.open_stream().unwrap();
+            if let Err(err) = ctx.source.open() {
+                let _ = ctx.result_sender.send(Err(err));
+                (ctx.callback)();
+                return;
+            }
 
+            //
+            // Captures and processes the next frame.
+            // Returns None for frames dropped to keep up with the target frame rate.
+            //
             fn produce_result(
                 ctx: &mut ThermalCapturerCtx,
-            ) -> Result<Box<ThermalCapturerResult>, Error> {
-                ctx.last_frame_time = std::time::Instant::now();
+            ) -> Result<Option<Box<ThermalCapturerResult>>, Error> {
+                // The source is read at its own rate either way, so that frames do not pile up
+                let mut thermal_data = ctx.source.capture()?;
+                let target_fps = ctx.settings.target_fps.filter(|fps| *fps > 0.0);
+                if let Some(target_fps) = target_fps {
+                    if ctx.last_result_time.elapsed().as_secs_f32() < 1.0 / target_fps {
+                        return Ok(None);
+                    }
+                }
+                let real_fps = 1.0 / ctx.last_result_time.elapsed().as_secs_f32();
+                ctx.last_result_time = std::time::Instant::now();
 
-                let thermal_data = ctx
-                    .adapter
-                    .capture_thermal_data(&mut ctx.camera)?
+                let mut raw_counts = false;
+                if ctx.settings.show_raw_counts {
+                    if let Some(raw_data) = thermal_data.raw_counts_as_data() {
+                        thermal_data = raw_data;
+                        raw_counts = true;
+                    }
+                }
+                // Raw counts are left uncorrected
+                let mut flat_field_offsets = None;
+                if !raw_counts {
+                    if ctx.settings.capture_flat_field {
+                        let offsets = thermal_data.flat_field_offsets();
+                        ctx.settings.capture_flat_field = false;
+                        ctx.settings.nuc_offsets = Some(offsets.clone());
+                        flat_field_offsets = Some(offsets);
+                    }
+                    if let Some(corrected) = ctx
+                        .settings
+                        .nuc_offsets
+                        .as_ref()
+                        .and_then(|offsets| thermal_data.apply_nuc(offsets))
+                    {
+                        thermal_data = corrected;
+                    }
+                }
+                // Denoised before the rotation, so that rotating keeps the accumulator valid.
+                // It is restarted when the resolution or the kind of data changes.
+                let denoise_strength = ctx.settings.denoise_strength.clamp(0.0, 0.95);
+                if denoise_strength > 0.0 {
+                    if let Some(blended) = ctx
+                        .denoise_accumulator
+                        .as_ref()
+                        .filter(|(_, acc_raw_counts)| *acc_raw_counts == raw_counts)
+                        .and_then(|(previous, _)| {
+                            thermal_data.blended_with(previous, denoise_strength)
+                        })
+                    {
+                        thermal_data = blended;
+                    }
+                    ctx.denoise_accumulator = Some((thermal_data.clone(), raw_counts));
+                } else {
+                    ctx.denoise_accumulator = None;
+                }
+                if ctx.settings.blur_kernel_size > 1 && !ctx.settings.blur_display_only {
+                    thermal_data = thermal_data.blurred(ctx.settings.blur_kernel_size);
+                }
+                let thermal_data = thermal_data
+                    .flipped(ctx.settings.flip_horizontal, ctx.settings.flip_vertical)
                     .rotated(ctx.settings.rotation);
                 let capture_time = std::time::Instant::now();
 
-                let (mintemp_pos, maxtemp_pos) = thermal_data.get_min_max_pos();
+                let (mintemp_pos, maxtemp_pos, captured_avg) =
+                    thermal_data.get_min_max_pos_and_mean();
 
-                let captured_range = TempRange::new(
-                    thermal_data.temperature_at(mintemp_pos.x, mintemp_pos.y),
-                    thermal_data.temperature_at(maxtemp_pos.x, maxtemp_pos.y),
-                );
+                let (camera_min, camera_max) = ctx.source.temperature_range();
+                let camera_range = TempRange::new(Temp::new(camera_min), Temp::new(camera_max));
+                // Counts are not comparable with the camera range
+                let clamp_to_camera_range = ctx.settings.clamp_to_camera_range && !raw_counts;
+                // Returns the temperature to display and whether it has been clamped
+                let clamp = |temp: Temp| {
+                    if clamp_to_camera_range && !camera_range.contains(temp) {
+                        (camera_range.clamp(temp), true)
+                    } else {
+                        (temp, false)
+                    }
+                };
+
+                let (min_temp, _) =
+                    clamp(thermal_data.temperature_at(mintemp_pos.x, mintemp_pos.y));
+                let (max_temp, _) =
+                    clamp(thermal_data.temperature_at(maxtemp_pos.x, maxtemp_pos.y));
+                let captured_range = TempRange::new(min_temp, max_temp);
 
-                let mut mapping_range = ctx.auto_range_controller.compute(captured_range);
+                // The markers stay on the previous extremes unless the new ones beat them
+                // by the margin, the captured range still holds the actual extremes
+                let (marker_min_pos, marker_max_pos) = if ctx.settings.stabilize_extremes {
+                    let margin = ctx.settings.stabilize_extremes_margin;
+                    let in_bounds = |pos: &ThermalDataPos| {
+                        pos.x < thermal_data.width && pos.y < thermal_data.height
+                    };
+                    let stable = match ctx.stable_extremes {
+                        Some((prev_min, prev_max))
+                            if in_bounds(&prev_min) && in_bounds(&prev_max) =>
+                        {
+                            let min_pos = if thermal_data.temperature_at(prev_min.x, prev_min.y)
+                                - margin
+                                > thermal_data.temperature_at(mintemp_pos.x, mintemp_pos.y)
+                            {
+                                mintemp_pos
+                            } else {
+                                prev_min
+                            };
+                            let max_pos = if thermal_data.temperature_at(prev_max.x, prev_max.y)
+                                + margin
+                                < thermal_data.temperature_at(maxtemp_pos.x, maxtemp_pos.y)
+                            {
+                                maxtemp_pos
+                            } else {
+                                prev_max
+                            };
+                            (min_pos, max_pos)
+                        }
+                        _ => (mintemp_pos, maxtemp_pos),
+                    };
+                    ctx.stable_extremes = Some(stable);
+                    stable
+                } else {
+                    ctx.stable_extremes = None;
+                    (mintemp_pos, maxtemp_pos)
+                };
+                let auto_range_input = if ctx.settings.auto_range_ignore_background {
+                    ThermalDataHistogram::from_thermal_data(&thermal_data, captured_range, 100)
+                        .subject_range()
+                        .unwrap_or(captured_range)
+                } else {
+                    captured_range
+                };
+                let mut mapping_range = ctx.auto_range_controller.compute(auto_range_input);
 
                 if !ctx.settings.auto_range {
                     mapping_range = ctx.settings.manual_range;
                 }
 
-                let image = thermal_data
-                    .map_to_image(|t| ctx.settings.temp_to_color(t, Some(mapping_range)));
+                let to_color = |t| ctx.settings.temp_to_color(t, Some(mapping_range));
+                let display_blur =
+                    ctx.settings.blur_kernel_size > 1 && ctx.settings.blur_display_only;
+                let mut image = match (display_blur, ctx.settings.upscale_factor > 1) {
+                    (false, false) => thermal_data.map_to_image(to_color),
+                    (false, true) => thermal_data
+                        .upscaled(ctx.settings.upscale_factor)
+                        .map_to_image(to_color),
+                    (true, false) => thermal_data
+                        .blurred(ctx.settings.blur_kernel_size)
+                        .map_to_image(to_color),
+                    (true, true) => thermal_data
+                        .blurred(ctx.settings.blur_kernel_size)
+                        .upscaled(ctx.settings.upscale_factor)
+                        .map_to_image(to_color),
+                };
+                if let Some(edge_overlay) = ctx.settings.edge_overlay {
+                    let edges = sobel_edge_map(&image);
+                    blend_edge_map(&mut image, &edges, edge_overlay.opacity);
+                }
+                let scale_colors = (0..SCALE_COLORS)
+                    .map(|i| {
+                        let factor = i as f32 / (SCALE_COLORS - 1) as f32;
+                        ctx.settings.temp_to_color(
+                            mapping_range.factor_to_temp(factor),
+                            Some(mapping_range),
+                        )
+                    })
+                    .collect();
 
                 let mut gizmo_results = HashMap::default();
+                let ambient = match ctx.settings.ambient_source {
+                    // Counts can not be used as the ambient of temperatures
+                    AmbientSource::FromGizmo(uuid) if !raw_counts => ctx
+                        .settings
+                        .gizmo
+                        .children()
+                        .and_then(|children| children.iter().find(|g| g.uuid == uuid && g.enabled))
+                        .and_then(|g| match g.kind {
+                            GizmoKind::Rect {
+                                top_left,
+                                bottom_right,
+                                ..
+                            } => {
+                                let mut sum = Temp::new(0.0);
+                                let mut count = 0;
+                                thermal_data.for_each_in_rect(
+                                    ThermalDataRect::from_corners(top_left, bottom_right),
+                                    |temp| {
+                                        sum = sum + clamp(temp).0;
+                                        count += 1;
+                                    },
+                                );
+                                (count > 0).then(|| sum / count as f32)
+                            }
+                            _ => None,
+                        })
+                        .unwrap_or(ctx.settings.ambient),
+                    _ => ctx.settings.ambient,
+                };
+                let correction = ReadingCorrection {
+                    // Raw counts can not be corrected
+                    emissivity: if raw_counts {
+                        1.0
+                    } else {
+                        ctx.settings.emissivity.clamp(0.01, 1.0)
+                    },
+                    reflected: ctx.settings.reflected_temp.unwrap_or(ambient),
+                    clamp_range: clamp_to_camera_range.then_some(camera_range),
+                };
+                let (marker_min_temp, marker_min_clamped) = correction
+                    .apply(thermal_data.temperature_at(marker_min_pos.x, marker_min_pos.y));
+                let (marker_max_temp, marker_max_clamped) = correction
+                    .apply(thermal_data.temperature_at(marker_max_pos.x, marker_max_pos.y));
                 ctx.settings
                     .gizmo
                     .children_mut()
                     .ok_or(anyhow!("Root gizmo has no children"))?
                     .iter()
+                    .filter(|g| g.enabled)
                     .for_each(|g| match g.kind {
                         GizmoKind::MaxTemp => {
                             gizmo_results.insert(
                                 g.uuid,
                                 GizmoResult {
-                                    temperature: captured_range.max,
-                                    pos: maxtemp_pos,
+                                    temperature: marker_max_temp,
+                                    pos: marker_max_pos,
+                                    clamped: marker_max_clamped,
+                                    ..Default::default()
                                 },
                             );
                         }
@@ -164,45 +523,219 @@ impl ThermalCapturer {
                             gizmo_results.insert(
                                 g.uuid,
                                 GizmoResult {
-                                    temperature: captured_range.min,
-                                    pos: mintemp_pos,
+                                    temperature: marker_min_temp,
+                                    pos: marker_min_pos,
+                                    clamped: marker_min_clamped,
+                                    ..Default::default()
                                 },
                             );
                         }
-                        GizmoKind::TempAt { pos } => {
+                        GizmoKind::TempAt { pos, radius } => {
+                            // Average over the circular neighborhood, cut at the image edges
+                            let mut sum = Temp::new(0.0);
+                            let mut count = 0;
+                            let mut clamped = false;
+                            let max_x = (pos.x + radius).min(thermal_data.width - 1);
+                            let max_y = (pos.y + radius).min(thermal_data.height - 1);
+                            for y in pos.y.saturating_sub(radius)..=max_y {
+                                for x in pos.x.saturating_sub(radius)..=max_x {
+                                    let (dx, dy) = (x.abs_diff(pos.x), y.abs_diff(pos.y));
+                                    if dx * dx + dy * dy > radius * radius {
+                                        continue;
+                                    }
+                                    let (temp, temp_clamped) =
+                                        correction.apply(thermal_data.temperature_at(x, y));
+                                    clamped |= temp_clamped;
+                                    sum = sum + temp;
+                                    count += 1;
+                                }
+                            }
+                            if count == 0 {
+                                return;
+                            }
+                            let temperature = sum / count as f32;
                             gizmo_results.insert(
                                 g.uuid,
                                 GizmoResult {
-                                    temperature: thermal_data.temperature_at(pos.x, pos.y),
+                                    temperature,
                                     pos,
+                                    clamped,
+                                    ..Default::default()
                                 },
                             );
                         }
+                        GizmoKind::IntegratedAboveAmbient { region } => {
+                            let mut sum = Temp::new(0.0);
+                            let mut above_ambient = 0.0;
+                            let mut count = 0;
+                            let mut clamped = false;
+                            thermal_data.for_each_in_rect(region, |temp| {
+                                let (temp, temp_clamped) = correction.apply(temp);
+                                clamped |= temp_clamped;
+                                sum = sum + temp;
+                                above_ambient +=
+                                    (temp - ambient).to_unit(TemperatureUnit::Kelvin).max(0.0);
+                                count += 1;
+                            });
+                            if count > 0 {
+                                gizmo_results.insert(
+                                    g.uuid,
+                                    GizmoResult {
+                                        temperature: sum / count as f32,
+                                        pos: region.center(),
+                                        integrated_above_ambient: Some(above_ambient),
+                                        clamped,
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+                        }
+                        GizmoKind::Line { start, end } => {
+                            let positions = thermal_data.line_positions(start, end);
+                            let mut clamped = false;
+                            let line_samples: Vec<Temp> = positions
+                                .iter()
+                                .map(|pos| {
+                                    let (temp, temp_clamped) =
+                                        correction.apply(thermal_data.temperature_at(pos.x, pos.y));
+                                    clamped |= temp_clamped;
+                                    temp
+                                })
+                                .collect();
+                            // The hottest point along the line is used as the readout
+                            let hottest = line_samples.iter().enumerate().max_by(|a, b| {
+                                a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal)
+                            });
+                            if let Some((i, &temperature)) = hottest {
+                                gizmo_results.insert(
+                                    g.uuid,
+                                    GizmoResult {
+                                        temperature,
+                                        pos: positions[i],
+                                        clamped,
+                                        line_samples,
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+                        }
+                        GizmoKind::Rect {
+                            top_left,
+                            bottom_right,
+                            emissivity,
+                        } => {
+                            let rect = ThermalDataRect::from_corners(top_left, bottom_right);
+                            let mut min = Temp::MAX;
+                            let mut max = Temp::MIN;
+                            let mut sum = Temp::new(0.0);
+                            let mut count = 0;
+                            let mut clamped = false;
+                            // Raw counts can not be corrected
+                            let correction = match emissivity.filter(|_| !raw_counts) {
+                                Some(emissivity) => ReadingCorrection {
+                                    emissivity,
+                                    ..correction
+                                },
+                                None => correction,
+                            };
+                            thermal_data.for_each_in_rect(rect, |temp| {
+                                let (temp, temp_clamped) = correction.apply(temp);
+                                clamped |= temp_clamped;
+                                if temp < min {
+                                    min = temp;
+                                }
+                                if temp > max {
+                                    max = temp;
+                                }
+                                sum = sum + temp;
+                                count += 1;
+                            });
+                            // The rect may lie outside of the image after a resolution change
+                            if count > 0 {
+                                let avg = sum / count as f32;
+                                gizmo_results.insert(
+                                    g.uuid,
+                                    GizmoResult {
+                                        temperature: avg,
+                                        pos: rect.center(),
+                                        clamped,
+                                        min,
+                                        max,
+                                        avg,
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+                        }
+                        // Resolved below, once the referenced results are known
+                        GizmoKind::Delta { .. } => {}
                         _ => panic!("Unimplemented gizmo kind"),
                     });
 
+                for g in ctx.settings.gizmo.children().unwrap().iter() {
+                    let GizmoKind::Delta { a, b } = g.kind else {
+                        continue;
+                    };
+                    if !g.enabled {
+                        continue;
+                    }
+                    // Deltas of deltas are not supported
+                    let (Some(result_a), Some(result_b)) = (
+                        gizmo_results.get(&a).filter(|r| r.delta.is_none()),
+                        gizmo_results.get(&b).filter(|r| r.delta.is_none()),
+                    ) else {
+                        continue;
+                    };
+                    let result = GizmoResult {
+                        pos: ThermalDataPos::new(
+                            (result_a.pos.x + result_b.pos.x) / 2,
+                            (result_a.pos.y + result_b.pos.y) / 2,
+                        ),
+                        clamped: result_a.clamped || result_b.clamped,
+                        delta: Some(result_a.temperature - result_b.temperature),
+                        ..Default::default()
+                    };
+                    gizmo_results.insert(g.uuid, result);
+                }
+
+                let sharpness = thermal_data.sharpness();
                 let result = Box::new(ThermalCapturerResult {
                     image,
-                    real_fps: 1.0 / ctx.last_frame_time.elapsed().as_secs_f32(),
-                    reported_fps: ctx.camera.frame_rate() as f32,
+                    real_fps,
+                    reported_fps: ctx.source.frame_rate() as f32,
                     image_range: mapping_range,
+                    captured_range,
+                    captured_avg,
                     histogram: ThermalDataHistogram::from_thermal_data(
                         &thermal_data,
                         captured_range.join(mapping_range),
-                        100,
+                        // Zero buckets would produce an empty histogram
+                        ctx.settings.histogram_buckets.max(1),
                     ),
                     gizmo_results,
                     capture_time,
-                    camera_short_name: ctx.adapter.short_name(),
+                    camera_short_name: ctx.source.short_name(),
+                    thermal_data,
+                    raw_counts,
+                    scale_colors,
+                    flat_field_offsets,
+                    ambient,
+                    sharpness,
+                    correction,
                 });
 
+                // Recordings play back at the rate the results are produced at
+                let framerate = match target_fps {
+                    Some(target_fps) => target_fps.min(ctx.source.frame_rate() as f32),
+                    None => ctx.source.frame_rate() as f32,
+                };
                 for recorder in ctx.settings.recorders.iter() {
                     let recorder = &mut recorder.lock().unwrap();
                     if recorder.state() == RecorderState::Initial {
                         recorder.start(RecorderStreamParams {
                             width: result.image.size[0],
                             height: result.image.size[1],
-                            framerate: ctx.camera.frame_rate() as usize,
+                            framerate: (framerate.round() as usize).max(1),
                         })?;
                     }
                     if recorder.state() != RecorderState::Done {
@@ -210,26 +743,40 @@ impl ThermalCapturer {
                     }
                 }
 
-                Ok(result)
+                Ok(Some(result))
             }
-            loop {
-                let result = produce_result(&mut ctx);
-                if let Err(err) = ctx.result_sender.send(result) {
-                    log::error!("Error sending result: {}", err);
-                    break;
-                }
+            'capture: loop {
+                // Dropped frames are not sent, but the commands are still handled after them
+                if let Some(result) = produce_result(&mut ctx).transpose() {
+                    // The capturer is dropped on errors (e.g. an unplugged camera), stop reading
+                    let is_error = result.is_err();
+                    if let Err(err) = ctx.result_sender.send(result) {
+                        log::error!("Error sending result: {}", err);
+                        break;
+                    }
 
-                (ctx.callback)();
+                    (ctx.callback)();
+                    if is_error {
+                        if let Err(err) = ctx.source.close() {
+                            log::error!("Error closing the source: {}", err);
+                        }
+                        break;
+                    }
+                }
 
                 // drain the command queue
 
                 while let Ok(cmd) = ctx.cmd_receiver.try_recv() {
                     match cmd {
                         ThermalCapturerCmd::Stop => {
-                            ctx.camera.stop_stream().unwrap();
-                            break;
+                            if let Err(err) = ctx.source.close() {
+                                log::error!("Error closing the source: {}", err);
+                            }
+                            break 'capture;
                         }
                         ThermalCapturerCmd::SetSettings(range_settings) => {
+                            ctx.auto_range_controller
+                                .set_settings(range_settings.auto_range_settings);
                             ctx.settings = range_settings;
                         }
                     }
@@ -238,14 +785,16 @@ impl ThermalCapturer {
         });
     }
     pub fn set_settings(&mut self, settings: ThermalCapturerSettings) {
-        self.cmd_sender
-            .send(ThermalCapturerCmd::SetSettings(settings))
-            .unwrap();
+        // Fails only if the capture thread has exited after an error, which is reported as a result
+        let _ = self
+            .cmd_sender
+            .send(ThermalCapturerCmd::SetSettings(settings));
     }
 }
 
 impl Drop for ThermalCapturer {
     fn drop(&mut self) {
-        self.cmd_sender.send(ThermalCapturerCmd::Stop).unwrap();
+        // The capture thread has already exited after an error
+        let _ = self.cmd_sender.send(ThermalCapturerCmd::Stop);
     }
 }
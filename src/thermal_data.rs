@@ -1,10 +1,19 @@
+use std::io::{self, Write};
+
 use eframe::epaint::{Color32, ColorImage};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    temperature::{Temp, TempRange},
+    temperature::{Temp, TempRange, TemperatureUnit},
     types::image_rotation::ImageRotation,
 };
 
+// Frames with at least this many pixels are color mapped in parallel (with the `parallel` feature).
+// Below that the overhead of distributing the work outweighs the gain.
+// Low enough to include 256x192 sensors, where the dynamic range curve makes each pixel costly.
+#[cfg(feature = "parallel")]
+const PARALLEL_MAP_MIN_PIXELS: usize = 32 * 1024;
+
 #[derive(Clone)]
 pub struct ThermalData {
     // Width in pixels
@@ -15,9 +24,12 @@ pub struct ThermalData {
 
     // Temperature data in degrees Kelvin
     pub data: Vec<Temp>,
+
+    // Unprocessed sensor values, if provided by the camera adapter (for adapter development)
+    pub raw_counts: Option<Vec<u16>>,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct ThermalDataPos {
     pub x: usize,
     pub y: usize,
@@ -27,6 +39,69 @@ impl ThermalDataPos {
     pub fn new(x: usize, y: usize) -> Self {
         Self { x, y }
     }
+
+    // Returns the position this point ends up at after rotating an image of the given size
+    #[inline(always)]
+    pub fn rotated(self, rotation: ImageRotation, width: usize, height: usize) -> Self {
+        let (x, y) = match rotation {
+            ImageRotation::None => (self.x, self.y),
+            ImageRotation::Clockwise90 => (self.y, width - self.x - 1),
+            ImageRotation::Clockwise180 => (width - self.x - 1, height - self.y - 1),
+            ImageRotation::Clockwise270 => (height - self.y - 1, self.x),
+        };
+        Self { x, y }
+    }
+
+    // Returns the position this point ends up at after mirroring an image of the given size
+    pub fn flipped(self, horizontal: bool, vertical: bool, width: usize, height: usize) -> Self {
+        Self {
+            x: if horizontal {
+                width - self.x - 1
+            } else {
+                self.x
+            },
+            y: if vertical {
+                height - self.y - 1
+            } else {
+                self.y
+            },
+        }
+    }
+}
+
+//
+// Rectangular region of the thermal data, both corners are inclusive.
+//
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct ThermalDataRect {
+    pub min: ThermalDataPos,
+    pub max: ThermalDataPos,
+}
+
+impl ThermalDataRect {
+    // Creates a rect spanning the two given corners, in any order
+    pub fn from_corners(a: ThermalDataPos, b: ThermalDataPos) -> Self {
+        Self {
+            min: ThermalDataPos::new(a.x.min(b.x), a.y.min(b.y)),
+            max: ThermalDataPos::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    pub fn center(&self) -> ThermalDataPos {
+        ThermalDataPos::new((self.min.x + self.max.x) / 2, (self.min.y + self.max.y) / 2)
+    }
+
+    pub fn contains(&self, pos: ThermalDataPos) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    // Returns the region this rect covers after rotating an image of the given size
+    pub fn rotated(self, rotation: ImageRotation, width: usize, height: usize) -> Self {
+        Self::from_corners(
+            self.min.rotated(rotation, width, height),
+            self.max.rotated(rotation, width, height),
+        )
+    }
 }
 
 impl ThermalData {
@@ -35,35 +110,193 @@ impl ThermalData {
             width,
             height,
             data,
+            raw_counts: None,
         }
     }
 
+    pub fn with_raw_counts(self, raw_counts: Vec<u16>) -> Self {
+        Self {
+            raw_counts: Some(raw_counts),
+            ..self
+        }
+    }
+
+    //
+    // Returns data holding the raw sensor counts in place of the temperatures
+    // (a count of N is stored as N Kelvin), or None if the adapter does not provide them.
+    //
+    pub fn raw_counts_as_data(&self) -> Option<Self> {
+        self.raw_counts.as_ref().map(|raw_counts| {
+            Self::new(
+                self.width,
+                self.height,
+                raw_counts.iter().map(|&c| Temp::new(c as f32)).collect(),
+            )
+        })
+    }
+
+    //
+    // Returns the data with the per-pixel offsets of a flat field reference subtracted,
+    // or None if the offsets were captured at a different resolution.
+    //
+    pub fn apply_nuc(&self, offsets: &[Temp]) -> Option<Self> {
+        if offsets.len() != self.data.len() {
+            return None;
+        }
+        Some(Self {
+            width: self.width,
+            height: self.height,
+            data: self
+                .data
+                .iter()
+                .zip(offsets)
+                .map(|(&temp, &offset)| temp - offset)
+                .collect(),
+            raw_counts: self.raw_counts.clone(),
+        })
+    }
+
+    //
+    // Temporal denoise: returns the data blended with the previous (already denoised) frame,
+    // `strength` being the weight of the previous frame. Returns None if the resolutions differ.
+    //
+    pub fn blended_with(&self, previous: &ThermalData, strength: f32) -> Option<Self> {
+        if previous.width != self.width || previous.height != self.height {
+            return None;
+        }
+        Some(Self {
+            width: self.width,
+            height: self.height,
+            data: self
+                .data
+                .iter()
+                .zip(&previous.data)
+                .map(|(&temp, &prev)| prev * strength + temp * (1.0 - strength))
+                .collect(),
+            raw_counts: self.raw_counts.clone(),
+        })
+    }
+
+    // Deviation of each pixel from the frame mean, to be captured while imaging a uniform surface
+    pub fn flat_field_offsets(&self) -> Vec<Temp> {
+        if self.data.is_empty() {
+            return vec![];
+        }
+        // Summed as f64, f32 loses too much precision over a whole frame
+        let sum: f64 = self
+            .data
+            .iter()
+            .map(|t| t.to_unit(TemperatureUnit::Kelvin) as f64)
+            .sum();
+        let mean = Temp::new((sum / self.data.len() as f64) as f32);
+        self.data.iter().map(|&temp| temp - mean).collect()
+    }
+
     #[inline(always)]
     pub fn temperature_at(&self, x: usize, y: usize) -> Temp {
         self.data[y * self.width + x]
     }
 
     #[inline(always)]
-    pub fn map_to_image<F: Fn(Temp) -> Color32>(&self, callback: F) -> ColorImage {
+    pub fn map_to_image<F: Fn(Temp) -> Color32 + Sync>(&self, callback: F) -> ColorImage {
+        // Small frames are faster to map on a single thread
+        #[cfg(feature = "parallel")]
+        if self.width * self.height >= PARALLEL_MAP_MIN_PIXELS {
+            return self.map_to_image_parallel(callback);
+        }
+        self.map_to_image_serial(callback)
+    }
+
+    fn map_to_image_serial<F: Fn(Temp) -> Color32>(&self, callback: F) -> ColorImage {
         let mut img = ColorImage::new([self.width, self.height], Color32::BLACK);
         for (i, pixel) in img.pixels.iter_mut().enumerate() {
             let x = i % self.width;
             let y = i / self.width;
             *pixel = callback(self.temperature_at(x, y));
         }
+        img
+    }
 
+    #[cfg(feature = "parallel")]
+    fn map_to_image_parallel<F: Fn(Temp) -> Color32 + Sync>(&self, callback: F) -> ColorImage {
+        use rayon::prelude::*;
+        let mut img = ColorImage::new([self.width, self.height], Color32::BLACK);
+        // Same indexing as map_to_image_serial, so that both produce identical images
+        img.pixels
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, pixel)| {
+                let x = i % self.width;
+                let y = i / self.width;
+                *pixel = callback(self.temperature_at(x, y));
+            });
         img
     }
 
-    pub fn get_min_max_pos(&self) -> (ThermalDataPos, ThermalDataPos) {
+    // Calls the callback with each temperature inside the rect, clipped to the data bounds
+    pub fn for_each_in_rect<F: FnMut(Temp)>(&self, rect: ThermalDataRect, mut callback: F) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let max_x = rect.max.x.min(self.width - 1);
+        let max_y = rect.max.y.min(self.height - 1);
+        for y in rect.min.y..=max_y {
+            for x in rect.min.x..=max_x {
+                callback(self.temperature_at(x, y));
+            }
+        }
+    }
+
+    //
+    // Returns the pixels along the segment from `start` to `end`,
+    // one per step along the longer axis.
+    // Endpoints outside of the data are clamped to its edges.
+    //
+    pub fn line_positions(
+        &self,
+        start: ThermalDataPos,
+        end: ThermalDataPos,
+    ) -> Vec<ThermalDataPos> {
+        if self.width == 0 || self.height == 0 {
+            return vec![];
+        }
+        let clamp_pos = |pos: ThermalDataPos| {
+            ThermalDataPos::new(pos.x.min(self.width - 1), pos.y.min(self.height - 1))
+        };
+        let (start, end) = (clamp_pos(start), clamp_pos(end));
+        let dx = end.x as f32 - start.x as f32;
+        let dy = end.y as f32 - start.y as f32;
+        let steps = dx.abs().max(dy.abs()) as usize;
+        (0..=steps)
+            .map(|i| {
+                let t = if steps == 0 {
+                    0.0
+                } else {
+                    i as f32 / steps as f32
+                };
+                ThermalDataPos::new(
+                    (start.x as f32 + dx * t).round() as usize,
+                    (start.y as f32 + dy * t).round() as usize,
+                )
+            })
+            .collect()
+    }
+
+    //
+    // Returns the positions of the coldest and the hottest pixel and the mean temperature
+    // of the frame, in a single pass.
+    //
+    pub fn get_min_max_pos_and_mean(&self) -> (ThermalDataPos, ThermalDataPos, Temp) {
         let mut min_pos = ThermalDataPos::default();
         let mut max_pos = ThermalDataPos::default();
         let mut min_temp = Temp::MAX;
         let mut max_temp = Temp::MIN;
+        let mut sum = 0.0f64;
         for (i, pixel) in self.data.iter().enumerate() {
             let x = i % self.width;
             let y = i / self.width;
             let temp = *pixel;
+            sum += temp.to_unit(TemperatureUnit::Kelvin) as f64;
             if temp < min_temp {
                 min_temp = temp;
                 min_pos.x = x;
@@ -75,7 +308,132 @@ impl ThermalData {
                 max_pos.y = y;
             }
         }
-        (min_pos, max_pos)
+        let mean = Temp::new((sum / self.data.len().max(1) as f64) as f32);
+        (min_pos, max_pos, mean)
+    }
+
+    //
+    // Box blur over a kernel_size x kernel_size neighbourhood (rounded up to an odd size),
+    // repeating the edge pixels outside of the image. The raw counts are kept unblurred.
+    //
+    pub fn blurred(&self, kernel_size: usize) -> Self {
+        let radius = kernel_size / 2;
+        if radius == 0 || self.data.is_empty() {
+            return self.clone();
+        }
+        let weight = 1.0 / (2 * radius + 1) as f32;
+        // Separable: blur the rows, then the columns of the result
+        let blur_pass = |data: &[Temp], horizontal: bool| -> Vec<Temp> {
+            (0..data.len())
+                .map(|i| {
+                    let (x, y) = (i % self.width, i / self.width);
+                    let mut sum = Temp::new(0.0);
+                    for offset in 0..=2 * radius {
+                        let (sx, sy) = if horizontal {
+                            ((x + offset).saturating_sub(radius).min(self.width - 1), y)
+                        } else {
+                            (x, (y + offset).saturating_sub(radius).min(self.height - 1))
+                        };
+                        sum = sum + data[sy * self.width + sx];
+                    }
+                    sum * weight
+                })
+                .collect()
+        };
+        let rows = blur_pass(&self.data, true);
+        Self {
+            width: self.width,
+            height: self.height,
+            data: blur_pass(&rows, false),
+            raw_counts: self.raw_counts.clone(),
+        }
+    }
+
+    //
+    // Focus assist metric: variance of the Laplacian over the interior pixels, in K².
+    // Sharper images have stronger edges and score higher, only comparable within one scene.
+    //
+    pub fn sharpness(&self) -> f32 {
+        if self.width < 3 || self.height < 3 || self.data.len() < self.width * self.height {
+            return 0.0;
+        }
+        let kelvin =
+            |x: usize, y: usize| self.data[y * self.width + x].to_unit(TemperatureUnit::Kelvin);
+        let mut sum = 0.0;
+        let mut sum_sq = 0.0;
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let laplacian =
+                    kelvin(x - 1, y) + kelvin(x + 1, y) + kelvin(x, y - 1) + kelvin(x, y + 1)
+                        - 4.0 * kelvin(x, y);
+                sum += laplacian;
+                sum_sq += laplacian * laplacian;
+            }
+        }
+        let count = ((self.width - 2) * (self.height - 2)) as f32;
+        let mean = sum / count;
+        (sum_sq / count - mean * mean).max(0.0)
+    }
+
+    //
+    // Returns the data scaled up by an integer factor with bilinear interpolation,
+    // for display only. The raw counts are dropped.
+    //
+    pub fn upscaled(&self, factor: usize) -> Self {
+        if factor <= 1 || self.width == 0 || self.height == 0 {
+            return self.clone();
+        }
+        let (width, height) = (self.width * factor, self.height * factor);
+        // Position in the source of the center of an output pixel, with its neighbours
+        let sample = |out: usize, size: usize| {
+            let src = ((out as f32 + 0.5) / factor as f32 - 0.5).clamp(0.0, (size - 1) as f32);
+            let low = src.floor() as usize;
+            (low, (low + 1).min(size - 1), src - low as f32)
+        };
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (y0, y1, fy) = sample(y, self.height);
+            for x in 0..width {
+                let (x0, x1, fx) = sample(x, self.width);
+                let top =
+                    self.temperature_at(x0, y0) * (1.0 - fx) + self.temperature_at(x1, y0) * fx;
+                let bottom =
+                    self.temperature_at(x0, y1) * (1.0 - fx) + self.temperature_at(x1, y1) * fx;
+                data.push(top * (1.0 - fy) + bottom * fy);
+            }
+        }
+        Self::new(width, height, data)
+    }
+
+    // Mirrors the data horizontally (left to right) and/or vertically (top to bottom)
+    pub fn flipped(&self, horizontal: bool, vertical: bool) -> Self {
+        if !horizontal && !vertical {
+            return self.clone();
+        }
+        Self {
+            width: self.width,
+            height: self.height,
+            data: self.flip_buffer(&self.data, horizontal, vertical),
+            raw_counts: self
+                .raw_counts
+                .as_ref()
+                .map(|raw_counts| self.flip_buffer(raw_counts, horizontal, vertical)),
+        }
+    }
+
+    // Mirrors a buffer with the layout of the data
+    fn flip_buffer<T: Copy>(&self, buffer: &[T], horizontal: bool, vertical: bool) -> Vec<T> {
+        (0..buffer.len())
+            .map(|i| {
+                let pos = ThermalDataPos::new(i % self.width, i / self.width).flipped(
+                    horizontal,
+                    vertical,
+                    self.width,
+                    self.height,
+                );
+                buffer[pos.y * self.width + pos.x]
+            })
+            .collect()
     }
 
     pub fn rotated(&self, rotation: ImageRotation) -> Self {
@@ -89,25 +447,36 @@ impl ThermalData {
             ImageRotation::Clockwise270 => (self.height, self.width),
         };
 
-        let mut data: Vec<Temp> = vec![Temp::new(0.0); width * height];
-        for (i, pixel) in self.data.iter().enumerate() {
-            let x = i % self.width;
-            let y = i / self.width;
-            let (x, y) = match rotation {
-                ImageRotation::None => (x, y),
-                ImageRotation::Clockwise90 => (y, self.width - x - 1),
-                ImageRotation::Clockwise180 => (self.width - x - 1, self.height - y - 1),
-                ImageRotation::Clockwise270 => (self.height - y - 1, x),
-            };
-            data[y * width + x] = *pixel;
-        }
-
         Self {
             width,
             height,
-            data,
+            data: self.rotate_buffer(&self.data, Temp::new(0.0), rotation, width),
+            raw_counts: self
+                .raw_counts
+                .as_ref()
+                .map(|raw_counts| self.rotate_buffer(raw_counts, 0, rotation, width)),
         }
     }
+
+    // Rotates a buffer with the layout of the data, `new_width` is the width after the rotation
+    fn rotate_buffer<T: Copy>(
+        &self,
+        buffer: &[T],
+        fill: T,
+        rotation: ImageRotation,
+        new_width: usize,
+    ) -> Vec<T> {
+        let mut rotated = vec![fill; buffer.len()];
+        for (i, value) in buffer.iter().enumerate() {
+            let pos = ThermalDataPos::new(i % self.width, i / self.width).rotated(
+                rotation,
+                self.width,
+                self.height,
+            );
+            rotated[pos.y * new_width + pos.x] = *value;
+        }
+        rotated
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -116,6 +485,7 @@ pub struct ThermalDataHistogramPoint {
     pub factor: f32,
 }
 
+#[derive(Clone)]
 pub struct ThermalDataHistogram {
     pub points: Vec<ThermalDataHistogramPoint>,
 }
@@ -147,4 +517,348 @@ impl ThermalDataHistogram {
         }
         Self { points }
     }
+
+    //
+    // Splits the histogram into a cold and a warm cluster (Otsu's method) and returns the range
+    // of the warm cluster, which usually is the subject when a cold background (like the sky)
+    // takes up a large part of the image.
+    // Returns None if the histogram does not have two significant clusters, that is when either
+    // side of the split is too small or there is no valley between the peaks of both sides.
+    //
+    pub fn subject_range(&self) -> Option<TempRange> {
+        // Each cluster must hold at least this fraction of the pixels
+        const MIN_CLUSTER_FRACTION: f32 = 0.05;
+        // The lowest bucket between the peaks of both clusters must be below this fraction
+        // of the smaller peak, a single (even if skewed) peak never has such a valley
+        const MAX_VALLEY_FRACTION: f32 = 0.5;
+
+        if self.points.len() < 2 {
+            return None;
+        }
+        let total: f32 = self.points.iter().map(|p| p.factor).sum();
+        let weighted_total: f32 = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| i as f32 * p.factor)
+            .sum();
+
+        // Find the split maximizing the between-class variance
+        let mut best_split = None;
+        let mut best_variance = 0.0;
+        let mut cold_weight = 0.0;
+        let mut cold_weighted_sum = 0.0;
+        for (i, point) in self.points.iter().enumerate().take(self.points.len() - 1) {
+            cold_weight += point.factor;
+            cold_weighted_sum += i as f32 * point.factor;
+            let warm_weight = total - cold_weight;
+            if cold_weight < MIN_CLUSTER_FRACTION * total
+                || warm_weight < MIN_CLUSTER_FRACTION * total
+            {
+                continue;
+            }
+            let cold_mean = cold_weighted_sum / cold_weight;
+            let warm_mean = (weighted_total - cold_weighted_sum) / warm_weight;
+            let variance = cold_weight * warm_weight * (cold_mean - warm_mean).powi(2);
+            if variance > best_variance {
+                best_variance = variance;
+                best_split = Some(i + 1);
+            }
+        }
+        let split = best_split?;
+
+        let peak_index = |range: std::ops::Range<usize>| {
+            range.max_by(|a, b| self.points[*a].factor.total_cmp(&self.points[*b].factor))
+        };
+        let cold_peak = peak_index(0..split)?;
+        let warm_peak = peak_index(split..self.points.len())?;
+        let valley = self.points[cold_peak..=warm_peak]
+            .iter()
+            .map(|p| p.factor)
+            .fold(f32::INFINITY, f32::min);
+        let smaller_peak = self.points[cold_peak]
+            .factor
+            .min(self.points[warm_peak].factor);
+        if valley > MAX_VALLEY_FRACTION * smaller_peak {
+            return None;
+        }
+
+        // Skip the empty buckets between the clusters
+        let first_warm = split + self.points[split..].iter().position(|p| p.factor > 0.0)?;
+        let last_warm = self.points.iter().rposition(|p| p.factor > 0.0)?;
+        let half_bucket = (self.points[1].temperature - self.points[0].temperature) / 2.0;
+        Some(TempRange::new(
+            self.points[first_warm].temperature - half_bucket,
+            self.points[last_warm].temperature + half_bucket,
+        ))
+    }
+
+    // Writes the bucket center temperatures (in the given unit) and the percentage of pixels in each bucket
+    pub fn write_csv<W: Write>(&self, mut writer: W, unit: TemperatureUnit) -> io::Result<()> {
+        writeln!(writer, "Temperature ({}),Percentage", unit.suffix_ascii())?;
+        for point in &self.points {
+            writeln!(
+                writer,
+                "{:.3},{:.4}",
+                point.temperature.to_unit(unit),
+                point.factor * 100.0
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROTATIONS: [ImageRotation; 4] = [
+        ImageRotation::None,
+        ImageRotation::Clockwise90,
+        ImageRotation::Clockwise180,
+        ImageRotation::Clockwise270,
+    ];
+    const FLIPS: [(bool, bool); 4] = [(false, false), (true, false), (false, true), (true, true)];
+
+    // Non-square frame, so that swapped axes are caught, with a distinct value in every pixel
+    fn test_data() -> ThermalData {
+        ThermalData::new(3, 2, (0..6).map(|i| Temp::new(i as f32)).collect())
+    }
+
+    fn value_at(data: &ThermalData, pos: ThermalDataPos) -> Temp {
+        data.data[pos.y * data.width + pos.x]
+    }
+
+    fn positions(width: usize, height: usize) -> Vec<ThermalDataPos> {
+        (0..width * height)
+            .map(|i| ThermalDataPos::new(i % width, i / width))
+            .collect()
+    }
+
+    fn corners(width: usize, height: usize) -> Vec<ThermalDataPos> {
+        vec![
+            ThermalDataPos::new(0, 0),
+            ThermalDataPos::new(width - 1, 0),
+            ThermalDataPos::new(0, height - 1),
+            ThermalDataPos::new(width - 1, height - 1),
+        ]
+    }
+
+    #[test]
+    fn rotated_positions_follow_rotated_data() {
+        let data = test_data();
+        for rotation in ROTATIONS {
+            let rotated = data.rotated(rotation);
+            if rotation.swaps_axes() {
+                assert_eq!((rotated.width, rotated.height), (2, 3));
+            } else {
+                assert_eq!((rotated.width, rotated.height), (3, 2));
+            }
+            for pos in positions(3, 2) {
+                assert_eq!(
+                    value_at(&data, pos),
+                    value_at(&rotated, pos.rotated(rotation, 3, 2)),
+                    "{:?} {:?}",
+                    rotation,
+                    pos
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_corners_stay_corners() {
+        for rotation in ROTATIONS {
+            let (width, height) = if rotation.swaps_axes() {
+                (2, 3)
+            } else {
+                (3, 2)
+            };
+            let mut rotated_corners: Vec<_> = corners(3, 2)
+                .into_iter()
+                .map(|pos| pos.rotated(rotation, 3, 2))
+                .collect();
+            let mut expected = corners(width, height);
+            rotated_corners.sort_by_key(|pos| (pos.y, pos.x));
+            expected.sort_by_key(|pos| (pos.y, pos.x));
+            assert_eq!(rotated_corners, expected, "{:?}", rotation);
+        }
+    }
+
+    #[test]
+    fn four_quarter_turns_round_trip() {
+        let data = test_data();
+        let mut rotated = data.clone();
+        for _ in 0..4 {
+            rotated = rotated.rotated(ImageRotation::Clockwise90);
+        }
+        assert_eq!((rotated.width, rotated.height), (3, 2));
+        assert_eq!(rotated.data, data.data);
+
+        for pos in positions(3, 2) {
+            let (mut width, mut height) = (3, 2);
+            let mut rotated_pos = pos;
+            for _ in 0..4 {
+                rotated_pos = rotated_pos.rotated(ImageRotation::Clockwise90, width, height);
+                (width, height) = (height, width);
+            }
+            assert_eq!(rotated_pos, pos);
+        }
+    }
+
+    #[test]
+    fn flipped_positions_follow_flipped_data() {
+        let data = test_data();
+        for (horizontal, vertical) in FLIPS {
+            let flipped = data.flipped(horizontal, vertical);
+            assert_eq!((flipped.width, flipped.height), (3, 2));
+            for pos in positions(3, 2) {
+                let flipped_pos = pos.flipped(horizontal, vertical, 3, 2);
+                assert_eq!(value_at(&data, pos), value_at(&flipped, flipped_pos));
+                // Flipping twice gives back the original position
+                assert_eq!(flipped_pos.flipped(horizontal, vertical, 3, 2), pos);
+            }
+            assert_eq!(flipped.flipped(horizontal, vertical).data, data.data);
+        }
+    }
+
+    #[test]
+    fn flipped_corners() {
+        let [top_left, top_right, bottom_left, bottom_right] = corners(3, 2)[..] else {
+            unreachable!()
+        };
+        assert_eq!(top_left.flipped(true, false, 3, 2), top_right);
+        assert_eq!(top_left.flipped(false, true, 3, 2), bottom_left);
+        assert_eq!(top_left.flipped(true, true, 3, 2), bottom_right);
+        assert_eq!(bottom_right.flipped(true, false, 3, 2), bottom_left);
+        assert_eq!(bottom_right.flipped(false, true, 3, 2), top_right);
+        assert_eq!(bottom_right.flipped(true, true, 3, 2), top_left);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_map_matches_serial_map() {
+        // Non-square and large enough to take the parallel path
+        let (width, height) = (320, 240);
+        assert!(width * height >= PARALLEL_MAP_MIN_PIXELS);
+        let data = ThermalData::new(
+            width,
+            height,
+            (0..width * height)
+                .map(|i| Temp::new(250.0 + (i % 997) as f32 * 0.1))
+                .collect(),
+        );
+        let range = TempRange::new(Temp::new(250.0), Temp::new(350.0));
+        let callback = |temp: Temp| {
+            let factor = range.factor(temp);
+            Color32::from_rgb(
+                (factor * 255.0) as u8,
+                (factor * factor * 255.0) as u8,
+                ((1.0 - factor) * 255.0) as u8,
+            )
+        };
+
+        let parallel = data.map_to_image(callback);
+        let serial = data.map_to_image_serial(callback);
+        assert_eq!(parallel.size, [width, height]);
+        assert_eq!(parallel.size, serial.size);
+        assert!(parallel.pixels == serial.pixels);
+    }
+
+    fn mean_kelvin(data: &ThermalData) -> f32 {
+        data.data
+            .iter()
+            .map(|temp| temp.to_unit(TemperatureUnit::Kelvin))
+            .sum::<f32>()
+            / data.data.len() as f32
+    }
+
+    #[test]
+    fn blurring_a_uniform_field_keeps_it_unchanged() {
+        let data = ThermalData::new(7, 5, vec![Temp::new(300.0); 35]);
+        for kernel_size in 0..=9 {
+            let blurred = data.blurred(kernel_size);
+            assert_eq!((blurred.width, blurred.height), (7, 5));
+            for temp in blurred.data {
+                assert!((temp.to_unit(TemperatureUnit::Kelvin) - 300.0).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn blurring_spreads_a_hot_pixel_and_keeps_the_mean() {
+        // Hot pixel far enough from the edges for the 3x3 kernel not to repeat them
+        let mut data = ThermalData::new(7, 5, vec![Temp::new(300.0); 35]);
+        data.data[2 * 7 + 3] = Temp::new(309.0);
+
+        // Even sizes are rounded up, so 2 is a 3x3 kernel
+        for kernel_size in [2, 3] {
+            let blurred = data.blurred(kernel_size);
+            for pos in positions(7, 5) {
+                let expected = if pos.x.abs_diff(3) <= 1 && pos.y.abs_diff(2) <= 1 {
+                    301.0
+                } else {
+                    300.0
+                };
+                let temp = value_at(&blurred, pos).to_unit(TemperatureUnit::Kelvin);
+                assert!((temp - expected).abs() < 1e-3, "{:?} {}", pos, temp);
+            }
+            assert!((mean_kelvin(&blurred) - mean_kelvin(&data)).abs() < 1e-3);
+        }
+    }
+
+    // Triangular distribution of temperatures between min and max (in Kelvin)
+    fn triangular_temps(min: f32, max: f32, steps: usize) -> Vec<Temp> {
+        let step = (max - min) / (2 * steps) as f32;
+        (0..steps)
+            .flat_map(|a| (0..steps).map(move |b| Temp::new(min + (a + b) as f32 * step)))
+            .collect()
+    }
+
+    fn histogram_of(
+        temps: Vec<Temp>,
+        range: TempRange,
+        num_buckets: usize,
+    ) -> ThermalDataHistogram {
+        let data = ThermalData::new(temps.len(), 1, temps);
+        ThermalDataHistogram::from_thermal_data(&data, range, num_buckets)
+    }
+
+    #[test]
+    fn single_peak_has_no_subject_range() {
+        let histogram = histogram_of(
+            triangular_temps(290.0, 310.0, 100),
+            TempRange::new(Temp::new(280.0), Temp::new(320.0)),
+            40,
+        );
+        assert!(histogram.subject_range().is_none());
+    }
+
+    #[test]
+    fn flat_histogram_has_no_subject_range() {
+        let temps = (0..1000)
+            .map(|i| Temp::new(280.0 + i as f32 * 0.04))
+            .collect();
+        let histogram = histogram_of(
+            temps,
+            TempRange::new(Temp::new(280.0), Temp::new(320.0)),
+            40,
+        );
+        assert!(histogram.subject_range().is_none());
+    }
+
+    #[test]
+    fn subject_range_excludes_the_sky() {
+        // 80% cold sky around 250 K, 20% subject around 300 K
+        let mut temps = triangular_temps(240.0, 260.0, 100);
+        temps.extend(triangular_temps(290.0, 310.0, 50));
+        let histogram = histogram_of(
+            temps,
+            TempRange::new(Temp::new(230.0), Temp::new(320.0)),
+            90,
+        );
+        let range = histogram.subject_range().unwrap();
+        assert!((range.min.to_unit(TemperatureUnit::Kelvin) - 290.0).abs() <= 1.0);
+        assert!((range.max.to_unit(TemperatureUnit::Kelvin) - 310.0).abs() <= 1.0);
+    }
 }
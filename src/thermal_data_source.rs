@@ -0,0 +1,255 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use nokhwa::{utils::CameraFormat, Camera};
+
+use crate::{
+    camera_adapter::CameraAdapter,
+    recorders::raw_recorder::{
+        RAW_FILE_MAGIC, RAW_FLAG_DATA_IS_COUNTS, RAW_FLAG_HAS_COUNTS, RAW_FLAG_HAS_FRAMERATE,
+    },
+    temperature::Temp,
+    thermal_data::ThermalData,
+};
+
+// Used when a replayed file does not store the frame rate
+const DEFAULT_REPLAY_FRAMERATE: u32 = 25;
+
+//
+// Provides the frames processed by the ThermalCapturer, either from a camera or from a file.
+//
+pub trait ThermalDataSource: Send {
+    ///
+    /// Start producing frames, called on the capture thread
+    ///
+    fn open(&mut self) -> Result<()>;
+
+    ///
+    /// Get the next frame, blocking until it is available
+    ///
+    fn capture(&mut self) -> Result<ThermalData>;
+
+    ///
+    /// Stop producing frames
+    ///
+    fn close(&mut self) -> Result<()>;
+
+    fn frame_rate(&self) -> u32;
+
+    ///
+    /// Get the valid temperature range of the source
+    /// (min, max) in Kelvin
+    ///
+    fn temperature_range(&self) -> (f32, f32);
+
+    ///
+    /// Shorter name of the source for use in filenames
+    ///
+    fn short_name(&self) -> String;
+
+    ///
+    /// Get the format negotiated with the camera and the one the adapter expects,
+    /// None if the source is not a camera
+    ///
+    fn stream_formats(&self) -> Option<(CameraFormat, CameraFormat)>;
+}
+
+//
+// A camera read through its adapter.
+//
+pub struct CameraSource {
+    camera: Camera,
+    adapter: Arc<dyn CameraAdapter>,
+}
+
+impl CameraSource {
+    pub fn new(camera: Camera, adapter: Arc<dyn CameraAdapter>) -> Self {
+        Self { camera, adapter }
+    }
+}
+
+impl ThermalDataSource for CameraSource {
+    fn open(&mut self) -> Result<()> {
+        Ok(self.camera.open_stream()?)
+    }
+
+    fn capture(&mut self) -> Result<ThermalData> {
+        Ok(self.adapter.capture_thermal_data(&mut self.camera)?)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        Ok(self.camera.stop_stream()?)
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.camera.frame_rate()
+    }
+
+    fn temperature_range(&self) -> (f32, f32) {
+        self.adapter.temperature_range()
+    }
+
+    fn short_name(&self) -> String {
+        self.adapter.short_name()
+    }
+
+    fn stream_formats(&self) -> Option<(CameraFormat, CameraFormat)> {
+        Some((self.camera.camera_format(), self.adapter.expected_format()))
+    }
+}
+
+//
+// Replays the frames of a file saved by the RawRecorder, at the frame rate stored in the file.
+//
+pub struct FileReplaySource {
+    frames: Vec<ThermalData>,
+    frame_rate: u32,
+    // Start over after the last frame, otherwise the replay stops with an error
+    looping: bool,
+    next_frame: usize,
+    next_frame_time: Option<Instant>,
+}
+
+impl FileReplaySource {
+    pub fn new(path: &Path, looping: bool) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?;
+        let mut rest = bytes.as_slice();
+        let mut frames = vec![];
+        let mut frame_rate = None;
+        while !rest.is_empty() {
+            let (frame, frame_frame_rate) = read_raw_frame(&mut rest)
+                .with_context(|| format!("Invalid raw thermal file (frame {})", frames.len()))?;
+            frames.push(frame);
+            frame_rate = frame_rate.or(frame_frame_rate);
+        }
+        if frames.is_empty() {
+            return Err(anyhow!("The raw thermal file is empty"));
+        }
+        Ok(Self {
+            frames,
+            frame_rate: frame_rate
+                .filter(|rate| *rate > 0)
+                .unwrap_or(DEFAULT_REPLAY_FRAMERATE),
+            looping,
+            next_frame: 0,
+            next_frame_time: None,
+        })
+    }
+}
+
+impl ThermalDataSource for FileReplaySource {
+    fn open(&mut self) -> Result<()> {
+        self.next_frame = 0;
+        self.next_frame_time = None;
+        Ok(())
+    }
+
+    fn capture(&mut self) -> Result<ThermalData> {
+        // Wait for the time of the frame, like a camera would
+        let now = Instant::now();
+        if let Some(next_frame_time) = self.next_frame_time {
+            if next_frame_time > now {
+                std::thread::sleep(next_frame_time - now);
+            }
+        }
+        let frame_interval = Duration::from_secs_f32(1.0 / self.frame_rate as f32);
+        self.next_frame_time = Some(self.next_frame_time.unwrap_or(now).max(now) + frame_interval);
+
+        if self.next_frame >= self.frames.len() {
+            if !self.looping {
+                return Err(anyhow!("Reached the end of the replayed file"));
+            }
+            self.next_frame = 0;
+        }
+        let frame = self.frames[self.next_frame].clone();
+        self.next_frame += 1;
+        Ok(frame)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn frame_rate(&self) -> u32 {
+        self.frame_rate
+    }
+
+    fn temperature_range(&self) -> (f32, f32) {
+        // The range of the camera that recorded the file is not known
+        (0.0, f32::MAX)
+    }
+
+    fn short_name(&self) -> String {
+        "replay".to_string()
+    }
+
+    fn stream_formats(&self) -> Option<(CameraFormat, CameraFormat)> {
+        None
+    }
+}
+
+// Splits off the first `len` bytes
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if rest.len() < len {
+        return Err(anyhow!("Unexpected end of file"));
+    }
+    let (taken, remaining) = rest.split_at(len);
+    *rest = remaining;
+    Ok(taken)
+}
+
+fn take_u32(rest: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(rest, 4)?.try_into()?))
+}
+
+//
+// Reads a single frame in the layout described in raw_recorder.rs, advancing `rest` past it.
+// Returns the frame and the frame rate, if stored.
+//
+fn read_raw_frame(rest: &mut &[u8]) -> Result<(ThermalData, Option<u32>)> {
+    if take(rest, RAW_FILE_MAGIC.len())? != RAW_FILE_MAGIC {
+        return Err(anyhow!("Not a raw thermal file"));
+    }
+    let flags = u16::from_le_bytes(take(rest, 2)?.try_into()?);
+    // The temperatures are sensor counts, which can not be converted without the camera
+    if flags & RAW_FLAG_DATA_IS_COUNTS != 0 {
+        return Err(anyhow!(
+            "The file was recorded in the raw sensor counts debug mode and can not be replayed"
+        ));
+    }
+    let width = take_u32(rest)? as usize;
+    let height = take_u32(rest)? as usize;
+    let frame_rate = if flags & RAW_FLAG_HAS_FRAMERATE != 0 {
+        Some(take_u32(rest)?)
+    } else {
+        None
+    };
+
+    let pixels = width
+        .checked_mul(height)
+        .ok_or_else(|| anyhow!("Invalid frame size {}x{}", width, height))?;
+    let data_len = pixels
+        .checked_mul(4)
+        .ok_or_else(|| anyhow!("Invalid frame size {}x{}", width, height))?;
+    let data = take(rest, data_len)?
+        .chunks_exact(4)
+        .map(|bytes| Temp::new(f32::from_le_bytes(bytes.try_into().unwrap())))
+        .collect();
+    let mut frame = ThermalData::new(width, height, data);
+    if flags & RAW_FLAG_HAS_COUNTS != 0 {
+        let counts_len = pixels
+            .checked_mul(2)
+            .ok_or_else(|| anyhow!("Invalid frame size {}x{}", width, height))?;
+        let raw_counts = take(rest, counts_len)?
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        frame = frame.with_raw_counts(raw_counts);
+    }
+    Ok((frame, frame_rate))
+}
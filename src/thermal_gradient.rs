@@ -3,6 +3,7 @@ use std::hash::{Hash, Hasher};
 use eframe::epaint::{Color32, ColorImage};
 
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use uuid::{uuid, Uuid};
 
 pub static THERMAL_GRADIENTS: Lazy<Vec<ThermalGradient>> = Lazy::new(|| {
@@ -41,10 +42,22 @@ pub static THERMAL_GRADIENTS: Lazy<Vec<ThermalGradient>> = Lazy::new(|| {
     ]
 });
 
-#[derive(Clone)]
+//
+// The built-in gradients followed by the user's custom ones.
+//
+pub fn all_gradients(custom_gradients: &[ThermalGradient]) -> Vec<ThermalGradient> {
+    THERMAL_GRADIENTS
+        .iter()
+        .chain(custom_gradients)
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThermalGradientPoint {
-    color: Color32,
-    pos: f32,
+    pub color: Color32,
+    // Position in the range [0, 1]
+    pub pos: f32,
 }
 
 impl ThermalGradientPoint {
@@ -54,6 +67,10 @@ impl ThermalGradientPoint {
             pos,
         }
     }
+
+    pub fn new(color: Color32, pos: f32) -> Self {
+        Self { color, pos }
+    }
 }
 
 impl Hash for ThermalGradientPoint {
@@ -63,10 +80,10 @@ impl Hash for ThermalGradientPoint {
     }
 }
 
-#[derive(Clone, Hash)]
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct ThermalGradient {
     ///
-    /// UUID of the gradient (will be important when custom gradients are supported)
+    /// UUID of the gradient, used to refer to it from the preferences
     ///
     pub uuid: Uuid,
     pub name: String,
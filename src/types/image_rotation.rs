@@ -23,4 +23,23 @@ impl ImageRotation {
             Self::Clockwise270 => Self::Clockwise180,
         }
     }
+
+    // Whether the width and height of the image are swapped by the rotation
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Self::Clockwise90 | Self::Clockwise270)
+    }
+
+    // Number of clockwise quarter turns needed to get from this rotation to `target`
+    pub fn clockwise_steps_to(self, target: Self) -> usize {
+        (target.quarter_turns() + 4 - self.quarter_turns()) % 4
+    }
+
+    fn quarter_turns(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Clockwise90 => 1,
+            Self::Clockwise180 => 2,
+            Self::Clockwise270 => 3,
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use std::hash::Hash;
+use std::{hash::Hash, path::Path};
 
 use eframe::egui::{ComboBox, Ui};
 use serde::{Deserialize, Serialize};
@@ -38,12 +38,69 @@ impl ImageFormat {
     }
 }
 
+// Trade-off between the size of saved PNG files and the time it takes to encode them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum PngCompression {
+    Fast,
+    Default,
+    Best,
+}
+
+impl PngCompression {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PngCompression::Fast => "Fast compression",
+            PngCompression::Default => "Default compression",
+            PngCompression::Best => "Best compression",
+        }
+    }
+
+    pub fn compression_type(&self) -> image::codecs::png::CompressionType {
+        match self {
+            PngCompression::Fast => image::codecs::png::CompressionType::Fast,
+            PngCompression::Default => image::codecs::png::CompressionType::Default,
+            PngCompression::Best => image::codecs::png::CompressionType::Best,
+        }
+    }
+
+    pub fn egui_combo_box(ui: &mut Ui, id_source: impl Hash, value: &mut Self, width: f32) {
+        ComboBox::from_id_source(id_source)
+            .selected_text(value.name())
+            .width(width)
+            .show_ui(ui, |ui| {
+                for compression in Self::iter() {
+                    ui.selectable_value(value, compression, compression.name());
+                }
+            });
+    }
+}
+
+// Encoder options used when saving snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageEncodeSettings {
+    // 1 to 100
+    pub jpeg_quality: u8,
+    pub png_compression: PngCompression,
+}
+
+impl Default for ImageEncodeSettings {
+    fn default() -> Self {
+        Self {
+            jpeg_quality: 90,
+            png_compression: PngCompression::Default,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 #[allow(non_camel_case_types)]
 pub enum VideoFormat {
     MP4_H264,
     WEBM_VP9,
     MKV_VP9,
+    // Written by the GIF recorder instead of ffmpeg
+    GIF,
 }
 
 impl VideoFormat {
@@ -52,6 +109,7 @@ impl VideoFormat {
             VideoFormat::MP4_H264 => "mp4",
             VideoFormat::WEBM_VP9 => "webm",
             VideoFormat::MKV_VP9 => "mkv",
+            VideoFormat::GIF => "gif",
         }
     }
 
@@ -60,6 +118,7 @@ impl VideoFormat {
             VideoFormat::MP4_H264 => "MP4 (H.264)",
             VideoFormat::WEBM_VP9 => "WebM (VP9)",
             VideoFormat::MKV_VP9 => "MKV (VP9)",
+            VideoFormat::GIF => "GIF (animated)",
         }
     }
 
@@ -75,6 +134,111 @@ impl VideoFormat {
     }
 }
 
+// x264 speed preset, slower presets give smaller files at the same quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum VideoPreset {
+    Ultrafast,
+    Veryfast,
+    Fast,
+    Medium,
+    Slow,
+    Veryslow,
+}
+
+impl VideoPreset {
+    pub fn x264_name(&self) -> &'static str {
+        match self {
+            VideoPreset::Ultrafast => "ultrafast",
+            VideoPreset::Veryfast => "veryfast",
+            VideoPreset::Fast => "fast",
+            VideoPreset::Medium => "medium",
+            VideoPreset::Slow => "slow",
+            VideoPreset::Veryslow => "veryslow",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VideoPreset::Ultrafast => "Ultra fast",
+            VideoPreset::Veryfast => "Very fast",
+            VideoPreset::Fast => "Fast",
+            VideoPreset::Medium => "Medium",
+            VideoPreset::Slow => "Slow",
+            VideoPreset::Veryslow => "Very slow",
+        }
+    }
+
+    pub fn egui_combo_box(ui: &mut Ui, id_source: impl Hash, value: &mut Self, width: f32) {
+        ComboBox::from_id_source(id_source)
+            .selected_text(value.name())
+            .width(width)
+            .show_ui(ui, |ui| {
+                for preset in Self::iter() {
+                    ui.selectable_value(value, preset, preset.name());
+                }
+            });
+    }
+}
+
+// Encoder options used for ffmpeg recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoEncodeSettings {
+    pub bitrate_kbps: u32,
+    // Only used by the H.264 encoder
+    pub preset: VideoPreset,
+    // Constant quality (CRF) instead of the bitrate, lower is better
+    pub constant_quality: Option<u8>,
+}
+
+impl Default for VideoEncodeSettings {
+    fn default() -> Self {
+        Self {
+            bitrate_kbps: 1000,
+            preset: VideoPreset::Medium,
+            constant_quality: None,
+        }
+    }
+}
+
+// Separator between the values of exported CSV files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum CsvDelimiter {
+    Comma,
+    // For spreadsheets in locales which use the comma as the decimal separator
+    Semicolon,
+    Tab,
+}
+
+impl CsvDelimiter {
+    pub fn character(&self) -> char {
+        match self {
+            CsvDelimiter::Comma => ',',
+            CsvDelimiter::Semicolon => ';',
+            CsvDelimiter::Tab => '\t',
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CsvDelimiter::Comma => "CSV (comma)",
+            CsvDelimiter::Semicolon => "CSV (semicolon)",
+            CsvDelimiter::Tab => "CSV (tab)",
+        }
+    }
+
+    pub fn egui_combo_box(ui: &mut Ui, id_source: impl Hash, value: &mut Self, width: f32) {
+        ComboBox::from_id_source(id_source)
+            .selected_text(value.name())
+            .width(width)
+            .show_ui(ui, |ui| {
+                for delimiter in Self::iter() {
+                    ui.selectable_value(value, delimiter, delimiter.name());
+                }
+            });
+    }
+}
+
 pub fn all_media_file_extensions() -> Vec<String> {
     let mut extensions = vec![];
     for format in ImageFormat::iter() {
@@ -85,3 +249,20 @@ pub fn all_media_file_extensions() -> Vec<String> {
     }
     extensions
 }
+
+// Whether the file is a video which can not be shown by the image loaders (GIFs can)
+pub fn is_video_file(path: &Path) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    VideoFormat::iter()
+        .filter(|format| *format != VideoFormat::GIF)
+        .any(|format| ext.to_string_lossy() == format.extension())
+}
+
+// Whether the file can be shown in the gallery
+pub fn is_media_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| all_media_file_extensions().contains(&ext.to_string_lossy().to_string()))
+        .unwrap_or(false)
+}
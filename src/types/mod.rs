@@ -1,2 +1,3 @@
 pub mod image_rotation;
 pub mod media_formats;
+pub mod watermark;
@@ -0,0 +1,84 @@
+use std::{hash::Hash, time::Instant};
+
+use chrono::{DateTime, Local};
+use eframe::egui::{ComboBox, Ui};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+// Tokens substituted in the watermark text, with their description shown in the capture pane
+pub const WATERMARK_TOKENS: [(&str, &str); 3] = [
+    ("{date}", "Capture date (YYYY-MM-DD)"),
+    ("{time}", "Capture time (HH:MM:SS)"),
+    ("{camera}", "Short name of the camera"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum WatermarkCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl WatermarkCorner {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WatermarkCorner::TopLeft => "Top left",
+            WatermarkCorner::TopRight => "Top right",
+            WatermarkCorner::BottomLeft => "Bottom left",
+            WatermarkCorner::BottomRight => "Bottom right",
+        }
+    }
+
+    pub fn egui_combo_box(ui: &mut Ui, id_source: impl Hash, value: &mut Self, width: f32) {
+        ComboBox::from_id_source(id_source)
+            .selected_text(value.name())
+            .width(width)
+            .show_ui(ui, |ui| {
+                for corner in Self::iter() {
+                    ui.selectable_value(value, corner, corner.name());
+                }
+            });
+    }
+}
+
+// Text burned into the frames of snapshots and videos, e.g. the capture time for logging
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkSettings {
+    // See WATERMARK_TOKENS
+    pub template: String,
+    pub corner: WatermarkCorner,
+    // Size of a pixel of the built-in font, 1 to 8
+    pub font_size: u32,
+    // 0 is invisible, 1 is opaque
+    pub opacity: f32,
+}
+
+impl Default for WatermarkSettings {
+    fn default() -> Self {
+        Self {
+            template: "{date} {time}".to_string(),
+            corner: WatermarkCorner::BottomRight,
+            font_size: 2,
+            opacity: 0.8,
+        }
+    }
+}
+
+impl WatermarkSettings {
+    //
+    // Returns the text of the watermark of a frame captured at `capture_time`.
+    // The capture time is converted to the wall clock, assuming the clock has not been changed since.
+    //
+    pub fn text(&self, camera: &str, capture_time: Instant) -> String {
+        let capture_local: DateTime<Local> = Local::now()
+            - chrono::Duration::from_std(Instant::now().saturating_duration_since(capture_time))
+                .unwrap_or_default();
+        self.template
+            .replace("{date}", &capture_local.format("%Y-%m-%d").to_string())
+            .replace("{time}", &capture_local.format("%H:%M:%S").to_string())
+            .replace("{camera}", camera)
+    }
+}
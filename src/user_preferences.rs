@@ -1,59 +1,201 @@
 use std::{
     fs::{self, File},
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
 
 use anyhow::Result;
 
-use crate::temperature::TemperatureUnit;
+use uuid::Uuid;
+
+use crate::{
+    gizmos::MeasurementPreset,
+    recorders::filename_template::{
+        DEFAULT_SNAPSHOT_FILENAME_TEMPLATE, DEFAULT_VIDEO_FILENAME_TEMPLATE,
+    },
+    temperature::TemperatureUnit,
+    thermal_gradient::{ThermalGradient, THERMAL_GRADIENTS},
+    types::{
+        media_formats::{
+            CsvDelimiter, ImageEncodeSettings, ImageFormat, VideoEncodeSettings, VideoFormat,
+        },
+        watermark::WatermarkSettings,
+    },
+    video_thumbnail_loader,
+};
 
 /// Denotes the maximum known version of the preferences file for this version of the application.
 ///
 /// Version 1: Initial version.
 /// Version 2: Added `captures_directory`.
-const MAX_KNOWN_PREFERENCES_VERSION: u32 = 2;
+/// Version 3: Added `custom_gradients`.
+const MAX_KNOWN_PREFERENCES_VERSION: u32 = 3;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct UserPreferences {
     pub preferences_version: u32,
     pub temperature_unit: TemperatureUnit,
+    // Decimal places of the displayed temperatures
+    pub decimal_places: u8,
     pub auto_open_camera: bool,
+    // Camera selected on startup if connected, see EnumeratedCamera::preference_key
+    pub preferred_camera: Option<String>,
     pub show_unsupported_cameras: bool,
     pub captures_directory: String,
+    // Number of files shown in the gallery, the oldest ones are dropped first
+    pub gallery_max_items: usize,
+    // Names of the captured files, see filename_template::FILENAME_TEMPLATE_TOKENS
+    pub snapshot_filename_template: String,
+    pub video_filename_template: String,
+    // Last formats selected in the capture pane
+    pub snapshot_format: ImageFormat,
+    pub video_format: VideoFormat,
+    pub video_encode_settings: VideoEncodeSettings,
+    pub csv_delimiter: CsvDelimiter,
+    // Add a color scale to snapshots
+    pub snapshot_color_scale: bool,
+    // Draw the measurements and their temperatures on snapshots and videos
+    pub overlay_measurements: bool,
+    // Text burned into snapshots and videos, kept while disabled
+    pub watermark_enabled: bool,
+    pub watermark: WatermarkSettings,
+    pub snapshot_encode_settings: ImageEncodeSettings,
+    pub show_stream_info: bool,
+    // Display-only interpolation of the thermal image
+    pub smooth_thermal_display: bool,
+    pub exit_maximized_on_escape: bool,
+    // Applied on restart
+    pub remember_window_geometry: bool,
+    pub rotate_measurements_with_image: bool,
+    // "Lock all" of the thermal display toolbar, prevents moving any measurement on the image
+    pub lock_all_gizmos: bool,
+    // Window over which measurement readouts are averaged, 0 means instantaneous readouts
+    pub readout_averaging_window_ms: u64,
+    // How long the measurement history (chart) is kept in memory
+    pub history_retention_minutes: u32,
+    // Keep a spot measurement at the center of the image
+    pub show_center_spot: bool,
+    // Show the temperature unit in the measurements header instead of every row
+    pub measurement_units_in_header: bool,
+    pub gif_max_duration_secs: u32,
+    // Keep only every n-th frame of GIF recordings, 1 keeps all of them
+    pub gif_frame_skip: u32,
+    // Number of video thumbnails generated in parallel, applied on restart
+    pub video_thumbnail_workers: usize,
+    pub show_camera_onboarding: bool,
+    pub temperature_expressions: bool,
+    pub auto_gradient: bool,
+    // Scene span (in Kelvin) above which the wide span gradient is used
+    pub auto_gradient_span_threshold: f32,
+    pub auto_gradient_narrow: Uuid,
+    pub auto_gradient_wide: Uuid,
+    // Gradients created in the gradient editor, shown after the built-in ones
+    pub custom_gradients: Vec<ThermalGradient>,
+    // Saved sets of measurements, see MeasurementPreset
+    pub measurement_presets: Vec<MeasurementPreset>,
+    // OSC output (only used when built with the `osc` feature)
+    pub osc_enabled: bool,
+    pub osc_target: String,
+    pub osc_address_prefix: String,
 }
 
 impl Default for UserPreferences {
     fn default() -> Self {
         Self {
-            preferences_version: 1,
+            preferences_version: MAX_KNOWN_PREFERENCES_VERSION,
             temperature_unit: TemperatureUnit::Celsius,
+            decimal_places: 1,
             auto_open_camera: true,
+            preferred_camera: None,
             show_unsupported_cameras: false,
             captures_directory: dirs::picture_dir()
                 .unwrap_or(dirs::home_dir().unwrap_or(PathBuf::from("./")))
                 .join("Thermal Cat")
                 .to_string_lossy()
                 .to_string(),
+            gallery_max_items: 20,
+            snapshot_filename_template: DEFAULT_SNAPSHOT_FILENAME_TEMPLATE.to_string(),
+            video_filename_template: DEFAULT_VIDEO_FILENAME_TEMPLATE.to_string(),
+            snapshot_format: ImageFormat::Png,
+            video_format: VideoFormat::MP4_H264,
+            video_encode_settings: VideoEncodeSettings::default(),
+            csv_delimiter: CsvDelimiter::Comma,
+            snapshot_color_scale: false,
+            overlay_measurements: false,
+            watermark_enabled: false,
+            watermark: WatermarkSettings::default(),
+            snapshot_encode_settings: ImageEncodeSettings::default(),
+            show_stream_info: true,
+            smooth_thermal_display: false,
+            exit_maximized_on_escape: true,
+            remember_window_geometry: true,
+            rotate_measurements_with_image: true,
+            lock_all_gizmos: false,
+            readout_averaging_window_ms: 0,
+            history_retention_minutes: 30,
+            show_center_spot: false,
+            measurement_units_in_header: false,
+            gif_max_duration_secs: 10,
+            gif_frame_skip: 1,
+            video_thumbnail_workers: video_thumbnail_loader::DEFAULT_MAX_WORKERS,
+            show_camera_onboarding: true,
+            temperature_expressions: true,
+            auto_gradient: false,
+            auto_gradient_span_threshold: 10.0,
+            auto_gradient_narrow: THERMAL_GRADIENTS[1].uuid,
+            auto_gradient_wide: THERMAL_GRADIENTS[0].uuid,
+            custom_gradients: vec![],
+            measurement_presets: vec![],
+            osc_enabled: false,
+            osc_target: "127.0.0.1:9000".to_string(),
+            osc_address_prefix: "/thermal-cat".to_string(),
         }
     }
 }
 
 impl UserPreferences {
-    pub fn preferences_path() -> PathBuf {
-        let mut path = dirs::config_dir().unwrap();
-        path.push("thermal-viewer");
-        path.push("preferences.json");
-        path
+    //
+    // Possible locations of the preferences file, in order of preference:
+    // the user's config directory, then next to the executable (portable mode).
+    //
+    fn candidate_paths() -> Vec<PathBuf> {
+        let mut paths = vec![];
+        if let Some(config_dir) = dirs::config_dir() {
+            paths.push(config_dir.join("thermal-viewer").join("preferences.json"));
+        }
+        if let Some(exe_dir) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|p| p.to_path_buf()))
+        {
+            paths.push(exe_dir.join("thermal-viewer-preferences.json"));
+        }
+        paths
+    }
+
+    //
+    // Returns the path of the existing preferences file, or the preferred location for a new one.
+    // None if no location is known at all.
+    //
+    pub fn preferences_path() -> Option<PathBuf> {
+        let candidates = Self::candidate_paths();
+        candidates
+            .iter()
+            .find(|path| path.exists())
+            .or(candidates.first())
+            .cloned()
     }
+
     pub fn load() -> Result<Self> {
-        let path = Self::preferences_path();
-        if !path.exists() {
+        let Some(path) = Self::preferences_path().filter(|path| path.exists()) else {
             return Ok(Self::default());
-        }
+        };
+        Self::load_from(&path)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         let prefs: UserPreferences = serde_json::from_reader(reader)?;
@@ -78,25 +220,134 @@ impl UserPreferences {
             prefs
         };
 
+        let prefs = if prefs.preferences_version < 3 {
+            did_migration = true;
+            log::info!("Migrating preferences to version 3");
+            UserPreferences {
+                preferences_version: 3,
+                ..prefs
+            }
+        } else {
+            prefs
+        };
+
         // More migrations here...
 
         if did_migration {
-            prefs.save()?;
+            // The migrated preferences are still usable, even if they could not be written back
+            let _ = prefs
+                .save_to(path)
+                .inspect_err(|err| log::error!("Failed to save migrated preferences: {}", err));
         }
 
         Ok(prefs)
     }
 
+    //
+    // Saves the preferences to the first writable location.
+    //
     pub fn save(&self) -> Result<()> {
-        let path = Self::preferences_path();
-        let dir_path = path.parent().unwrap();
-        if !dir_path.exists() {
-            fs::create_dir_all(dir_path)?;
+        // Try the location of an existing file first, so that portable setups keep working
+        let mut candidates = Self::candidate_paths();
+        if let Some(existing_idx) = candidates.iter().position(|path| path.exists()) {
+            let existing = candidates.remove(existing_idx);
+            candidates.insert(0, existing);
+        }
+
+        let mut last_err = anyhow::anyhow!("No location available for the preferences file");
+        for path in candidates {
+            match self.save_to(&path) {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    log::warn!(
+                        "Failed to save preferences to {}: {}",
+                        path.to_string_lossy(),
+                        err
+                    );
+                    last_err = err;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir_path) = path.parent() {
+            if !dir_path.exists() {
+                fs::create_dir_all(dir_path)?;
+            }
         }
 
         let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_preferences_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("thermal-cat-test-{}", Uuid::new_v4()))
+            .join("preferences.json")
+    }
+
+    fn custom_gradient() -> ThermalGradient {
+        ThermalGradient::new(
+            Uuid::new_v4(),
+            "My gradient".to_string(),
+            THERMAL_GRADIENTS[0].points.clone(),
+        )
+    }
+
+    #[test]
+    fn default_preferences_use_the_current_version() {
+        assert_eq!(
+            UserPreferences::default().preferences_version,
+            MAX_KNOWN_PREFERENCES_VERSION
+        );
+    }
+
+    #[test]
+    fn custom_gradients_survive_a_save_and_reload() {
+        let path = temp_preferences_path();
+        let gradient = custom_gradient();
+        let prefs = UserPreferences {
+            custom_gradients: vec![gradient.clone()],
+            ..Default::default()
+        };
+        prefs.save_to(&path).unwrap();
+
+        // Loading twice also covers the file written back by a migration
+        for _ in 0..2 {
+            let loaded = UserPreferences::load_from(&path).unwrap();
+            assert_eq!(loaded.preferences_version, MAX_KNOWN_PREFERENCES_VERSION);
+            assert_eq!(loaded.custom_gradients.len(), 1);
+            assert_eq!(loaded.custom_gradients[0].uuid, gradient.uuid);
+            assert_eq!(loaded.custom_gradients[0].name, gradient.name);
+        }
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn migration_keeps_custom_gradients() {
+        let path = temp_preferences_path();
+        let prefs = UserPreferences {
+            preferences_version: 1,
+            custom_gradients: vec![custom_gradient()],
+            ..Default::default()
+        };
+        prefs.save_to(&path).unwrap();
+
+        let loaded = UserPreferences::load_from(&path).unwrap();
+        assert_eq!(loaded.preferences_version, MAX_KNOWN_PREFERENCES_VERSION);
+        assert_eq!(loaded.custom_gradients.len(), 1);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,7 +1,22 @@
-use crate::types::image_rotation::ImageRotation;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    gizmos::{Gizmo, GizmoKind, GizmoResult},
+    temperature::{TempRange, TemperatureUnit},
+    thermal_data::{ThermalDataPos, ThermalDataRect},
+    types::{image_rotation::ImageRotation, watermark::WatermarkCorner},
+};
 use eframe::epaint::{Color32, ColorImage};
 use image::{GenericImage, Pixel, Rgb, RgbImage, Rgba};
-use imageproc::rect::Rect;
+use imageproc::{
+    drawing::{draw_cross_mut, draw_filled_rect_mut, draw_hollow_rect_mut, draw_line_segment_mut},
+    rect::Rect,
+};
+use uuid::Uuid;
 
 pub fn rotate_image(img: ColorImage, rotation: ImageRotation) -> ColorImage {
     if rotation == ImageRotation::None {
@@ -39,6 +54,41 @@ pub fn rotate_image(img: ColorImage, rotation: ImageRotation) -> ColorImage {
     new_img
 }
 
+//
+// Opens the file with the default application of the system.
+//
+pub fn open_in_system(path: &Path) -> Result<(), anyhow::Error> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path).spawn()?;
+    Ok(())
+}
+
+//
+// Shows the file in the file manager of the system, selected where supported.
+//
+pub fn reveal_in_folder(path: &Path) -> Result<(), anyhow::Error> {
+    if cfg!(target_os = "windows") {
+        Command::new("explorer")
+            .arg(format!("/select,{}", path.display()))
+            .spawn()?;
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg("-R").arg(path).spawn()?;
+    } else {
+        // xdg-open can not select a file, open its folder instead
+        let folder = path.parent().unwrap_or(Path::new("."));
+        Command::new("xdg-open").arg(folder).spawn()?;
+    }
+    Ok(())
+}
+
 pub fn pathify_string(s: String) -> String {
     s.to_lowercase()
         .chars()
@@ -49,6 +99,19 @@ pub fn pathify_string(s: String) -> String {
         .collect()
 }
 
+// Returns `dir/base.ext`, or `dir/base_N.ext` with the lowest N for which no file exists yet.
+// Captures use second-resolution timestamps in their names, so this prevents overwriting
+// a capture made within the same second.
+pub fn unique_path(dir: &Path, base: &str, ext: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", base, ext));
+    let mut suffix = 1;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.{}", base, suffix, ext));
+        suffix += 1;
+    }
+    path
+}
+
 pub fn rgba8_to_rgb8(
     input: image::ImageBuffer<Rgba<u8>, Vec<u8>>,
 ) -> image::ImageBuffer<Rgb<u8>, Vec<u8>> {
@@ -80,6 +143,65 @@ pub fn image_to_egui_color_image(img: image::DynamicImage) -> ColorImage {
     ColorImage::from_rgba_unmultiplied(size, pixels.as_slice())
 }
 
+//
+// Returns the strength of the edges of the image, 0 to 1 for each pixel in row-major order.
+// Sobel operator on the luminance, normalized by the strongest edge.
+//
+pub fn sobel_edge_map(img: &ColorImage) -> Vec<f32> {
+    let [width, height] = img.size;
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+    let luminance: Vec<f32> = img
+        .pixels
+        .iter()
+        .map(|p| 0.299 * p.r() as f32 + 0.587 * p.g() as f32 + 0.114 * p.b() as f32)
+        .collect();
+    // The border pixels are repeated outside of the image
+    let at = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        luminance[y * width + x]
+    };
+
+    let mut edges: Vec<f32> = (0..width * height)
+        .map(|i| {
+            let (x, y) = ((i % width) as isize, (i / width) as isize);
+            let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            (gx * gx + gy * gy).sqrt()
+        })
+        .collect();
+
+    let max_edge = edges.iter().cloned().fold(0.0_f32, f32::max);
+    if max_edge > 0.0 {
+        edges.iter_mut().for_each(|edge| *edge /= max_edge);
+    }
+    edges
+}
+
+//
+// Lightens the image towards white along the edges of an edge map of the same size,
+// e.g. from sobel_edge_map. Ignored if the sizes do not match.
+//
+pub fn blend_edge_map(img: &mut ColorImage, edges: &[f32], opacity: f32) {
+    if edges.len() != img.pixels.len() {
+        return;
+    }
+    let opacity = opacity.clamp(0.0, 1.0);
+    for (pixel, edge) in img.pixels.iter_mut().zip(edges) {
+        let factor = edge * opacity;
+        let lighten = |channel: u8| (channel as f32 + (255.0 - channel as f32) * factor) as u8;
+        *pixel = Color32::from_rgb(lighten(pixel.r()), lighten(pixel.g()), lighten(pixel.b()));
+    }
+}
+
 pub fn draw_blended_rect_mut<I>(image: &mut I, rect: Rect, color: I::Pixel)
 where
     I: GenericImage,
@@ -189,3 +311,389 @@ pub fn overlay_film_frame(img: image::DynamicImage) -> RgbImage {
     }
     image::DynamicImage::ImageRgba8(img).to_rgb8()
 }
+
+// 3x5 pixel glyphs for the characters of temperature labels and watermarks,
+// each row is 3 bits from the left
+fn small_glyph(c: char) -> Option<[u8; 5]> {
+    Some(match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '°' => [0b010, 0b101, 0b010, 0b000, 0b000],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'c' => [0b000, 0b111, 0b100, 0b100, 0b111],
+        'o' => [0b000, 0b111, 0b101, 0b101, 0b111],
+        'u' => [0b000, 0b101, 0b101, 0b101, 0b111],
+        'n' => [0b000, 0b110, 0b101, 0b101, 0b101],
+        't' => [0b010, 0b111, 0b010, 0b010, 0b011],
+        's' => [0b000, 0b011, 0b100, 0b001, 0b110],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b101, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        ' ' => [0; 5],
+        _ => return None,
+    })
+}
+
+// Width in pixels of text drawn by draw_small_text_mut
+fn small_text_width(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32 * 4).saturating_sub(1) * scale
+}
+
+// Draws text with the built-in 3x5 pixel font, unknown characters are left blank
+fn draw_small_text_mut(img: &mut RgbImage, x: u32, y: u32, scale: u32, text: &str, color: Rgb<u8>) {
+    draw_small_text_blended_mut(img, x, y, scale, text, color, 1.0);
+}
+
+// Like draw_small_text_mut, mixed with the image by `opacity`.
+// Lowercase letters without a glyph of their own are drawn in uppercase.
+fn draw_small_text_blended_mut(
+    img: &mut RgbImage,
+    x: u32,
+    y: u32,
+    scale: u32,
+    text: &str,
+    color: Rgb<u8>,
+    opacity: f32,
+) {
+    for (i, c) in text.chars().enumerate() {
+        let Some(glyph) = small_glyph(c).or_else(|| small_glyph(c.to_ascii_uppercase())) else {
+            continue;
+        };
+        let glyph_x = x + i as u32 * 4 * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                blend_filled_rect_mut(
+                    img,
+                    Rect::at(
+                        (glyph_x + col * scale) as i32,
+                        (y + row as u32 * scale) as i32,
+                    )
+                    .of_size(scale, scale),
+                    color,
+                    opacity,
+                );
+            }
+        }
+    }
+}
+
+// Fills the rectangle with the color mixed with the image by `opacity`, from 0 to 1
+fn blend_filled_rect_mut(img: &mut RgbImage, rect: Rect, color: Rgb<u8>, opacity: f32) {
+    let opacity = opacity.clamp(0.0, 1.0);
+    let image_bounds = Rect::at(0, 0).of_size(img.width(), img.height());
+    let Some(intersection) = image_bounds.intersect(rect) else {
+        return;
+    };
+    for y in intersection.top()..=intersection.bottom() {
+        for x in intersection.left()..=intersection.right() {
+            let pixel = img.get_pixel_mut(x as u32, y as u32);
+            *pixel = pixel.map2(&color, |current, target| {
+                (current as f32 * (1.0 - opacity) + target as f32 * opacity).round() as u8
+            });
+        }
+    }
+}
+
+//
+// Returns the image extended with a color scale on its right edge:
+// a bar with the colors of `scale_colors` (from the minimum to the maximum of `range`)
+// labeled with the minimum at the bottom and the maximum at the top.
+//
+pub fn overlay_color_scale(
+    img: &RgbImage,
+    scale_colors: &[Color32],
+    range: TempRange,
+    unit: TemperatureUnit,
+    decimals: usize,
+    unit_suffix: &str,
+) -> RgbImage {
+    const MARGIN: u32 = 4;
+    const BAR_WIDTH: u32 = 10;
+
+    let (width, height) = img.dimensions();
+    // Keep the labels readable on larger images
+    let scale = (height / 120).clamp(1, 4);
+    let max_label = format!("{} {}", range.max.format(unit, decimals), unit_suffix);
+    let min_label = format!("{} {}", range.min.format(unit, decimals), unit_suffix);
+    let label_width = small_text_width(&max_label, scale).max(small_text_width(&min_label, scale));
+    let strip_width = MARGIN * 3 + BAR_WIDTH + label_width;
+
+    let mut output = RgbImage::new(width + strip_width, height);
+    output.copy_from(img, 0, 0).unwrap();
+
+    let bar_x = width + MARGIN;
+    let bar_top = MARGIN;
+    let bar_height = height.saturating_sub(MARGIN * 2);
+    if !scale_colors.is_empty() && bar_height > 0 {
+        for dy in 0..bar_height {
+            // The maximum is at the top
+            let factor = 1.0 - dy as f32 / (bar_height - 1).max(1) as f32;
+            let index = (factor * (scale_colors.len() - 1) as f32).round() as usize;
+            let color = scale_colors[index];
+            for dx in 0..BAR_WIDTH {
+                output.put_pixel(
+                    bar_x + dx,
+                    bar_top + dy,
+                    Rgb([color.r(), color.g(), color.b()]),
+                );
+            }
+        }
+    }
+
+    let text_color = Rgb([255, 255, 255]);
+    let text_x = bar_x + BAR_WIDTH + MARGIN;
+    draw_small_text_mut(&mut output, text_x, MARGIN, scale, &max_label, text_color);
+    draw_small_text_mut(
+        &mut output,
+        text_x,
+        height.saturating_sub(MARGIN + 5 * scale),
+        scale,
+        &min_label,
+        text_color,
+    );
+    output
+}
+
+//
+// Draws the measurements on an image of thermal data `data_width` pixels wide:
+// crosses on the points, the outlines of the regions and the lines.
+//
+pub fn draw_gizmo_markers(
+    img: &mut RgbImage,
+    gizmos: &[Gizmo],
+    gizmo_results: &HashMap<Uuid, GizmoResult>,
+    data_width: usize,
+) {
+    // The image is larger than the data if it has been upscaled for display
+    let scale = img.width() as f32 / data_width.max(1) as f32;
+    let to_image =
+        |pos: ThermalDataPos| ((pos.x as f32 + 0.5) * scale, (pos.y as f32 + 0.5) * scale);
+
+    for gizmo in gizmos {
+        let color = Rgb([gizmo.color.r(), gizmo.color.g(), gizmo.color.b()]);
+        match gizmo.kind {
+            GizmoKind::IntegratedAboveAmbient { region } => {
+                draw_region(img, region, color, scale);
+            }
+            GizmoKind::Rect {
+                top_left,
+                bottom_right,
+                ..
+            } => {
+                draw_region(
+                    img,
+                    ThermalDataRect::from_corners(top_left, bottom_right),
+                    color,
+                    scale,
+                );
+            }
+            // Only shown in the list of measurements
+            GizmoKind::Delta { .. } => {}
+            GizmoKind::Line { start, end } => {
+                draw_line_segment_mut(img, to_image(start), to_image(end), color);
+            }
+            _ => {
+                if let Some(gizmo_result) = gizmo_results.get(&gizmo.uuid) {
+                    let (x, y) = to_image(gizmo_result.pos);
+                    draw_cross_mut(img, color, x as i32, y as i32);
+                }
+            }
+        }
+    }
+}
+
+//
+// Draws the measurements like draw_gizmo_markers, with their temperature next to them.
+// Deltas and integrated regions are not labeled,
+// their readings are not the temperature at a position.
+//
+pub fn draw_gizmo_overlay(
+    img: &mut RgbImage,
+    gizmos: &[Gizmo],
+    gizmo_results: &HashMap<Uuid, GizmoResult>,
+    data_width: usize,
+    unit: TemperatureUnit,
+    decimals: usize,
+    unit_suffix: &str,
+) {
+    const PADDING: u32 = 1;
+
+    draw_gizmo_markers(img, gizmos, gizmo_results, data_width);
+
+    let (width, height) = img.dimensions();
+    let scale = width as f32 / data_width.max(1) as f32;
+    // Same size as the labels of the color scale
+    let text_scale = (height / 120).clamp(1, 4);
+    for gizmo in gizmos {
+        if matches!(
+            gizmo.kind,
+            GizmoKind::Delta { .. } | GizmoKind::IntegratedAboveAmbient { .. }
+        ) {
+            continue;
+        }
+        let Some(gizmo_result) = gizmo_results.get(&gizmo.uuid) else {
+            continue;
+        };
+        let label = format!(
+            "{} {}",
+            gizmo_result.temperature.format(unit, decimals),
+            unit_suffix
+        );
+
+        // Below and to the right of the marker, moved inside the image near its edges
+        let label_width = small_text_width(&label, text_scale) + PADDING * 2 * text_scale;
+        let label_height = (5 + PADDING * 2) * text_scale;
+        let x = (((gizmo_result.pos.x as f32 + 0.5) * scale) as u32 + 3 * text_scale)
+            .min(width.saturating_sub(label_width));
+        let y = (((gizmo_result.pos.y as f32 + 0.5) * scale) as u32 + 3 * text_scale)
+            .min(height.saturating_sub(label_height));
+
+        draw_filled_rect_mut(
+            img,
+            Rect::at(x as i32, y as i32).of_size(label_width.max(1), label_height),
+            Rgb([0, 0, 0]),
+        );
+        draw_small_text_mut(
+            img,
+            x + PADDING * text_scale,
+            y + PADDING * text_scale,
+            text_scale,
+            &label,
+            Rgb([gizmo.color.r(), gizmo.color.g(), gizmo.color.b()]),
+        );
+    }
+}
+
+// Outlines a region of the image, both corners of the region are inclusive.
+// `scale` is the size of a pixel of the data in the image.
+fn draw_region(img: &mut RgbImage, region: ThermalDataRect, color: Rgb<u8>, scale: f32) {
+    let left = (region.min.x as f32 * scale) as i32;
+    let top = (region.min.y as f32 * scale) as i32;
+    let right = ((region.max.x + 1) as f32 * scale) as i32;
+    let bottom = ((region.max.y + 1) as f32 * scale) as i32;
+    draw_hollow_rect_mut(
+        img,
+        Rect::at(left, top).of_size((right - left).max(1) as u32, (bottom - top).max(1) as u32),
+        color,
+    );
+}
+
+//
+// Draws a line of text with the built-in pixel font on a dark box in a corner of the image,
+// both mixed with the image by `opacity`. `scale` is the size of a pixel of the font.
+//
+pub fn draw_corner_label(
+    img: &mut RgbImage,
+    text: &str,
+    corner: WatermarkCorner,
+    scale: u32,
+    opacity: f32,
+) {
+    const MARGIN: u32 = 4;
+    const PADDING: u32 = 1;
+
+    let scale = scale.max(1);
+    let (width, height) = img.dimensions();
+    let box_width = small_text_width(text, scale) + PADDING * 2 * scale;
+    let box_height = (5 + PADDING * 2) * scale;
+    let x = match corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::BottomLeft => MARGIN,
+        WatermarkCorner::TopRight | WatermarkCorner::BottomRight => {
+            width.saturating_sub(box_width + MARGIN)
+        }
+    };
+    let y = match corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::TopRight => MARGIN,
+        WatermarkCorner::BottomLeft | WatermarkCorner::BottomRight => {
+            height.saturating_sub(box_height + MARGIN)
+        }
+    };
+
+    blend_filled_rect_mut(
+        img,
+        Rect::at(x as i32, y as i32).of_size(box_width.max(1), box_height),
+        Rgb([0, 0, 0]),
+        opacity,
+    );
+    draw_small_text_blended_mut(
+        img,
+        x + PADDING * scale,
+        y + PADDING * scale,
+        scale,
+        text,
+        Rgb([255, 255, 255]),
+        opacity,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn unique_path_numbers_existing_files() {
+        let dir = std::env::temp_dir().join(format!("thermal-cat-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let first = unique_path(&dir, "capture", "png");
+        assert_eq!(first, dir.join("capture.png"));
+        fs::write(&first, b"").unwrap();
+
+        let second = unique_path(&dir, "capture", "png");
+        assert_eq!(second, dir.join("capture_1.png"));
+        fs::write(&second, b"").unwrap();
+
+        assert_eq!(
+            unique_path(&dir, "capture", "png"),
+            dir.join("capture_2.png")
+        );
+        // Only files with the same extension are taken into account
+        assert_eq!(unique_path(&dir, "capture", "mp4"), dir.join("capture.mp4"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
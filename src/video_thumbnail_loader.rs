@@ -1,6 +1,11 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use std::{mem::size_of, path::Path, sync::Arc, thread};
+use std::{
+    mem::size_of,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread,
+};
 
 use eframe::egui::{
     ahash::HashMap,
@@ -21,16 +26,70 @@ use crate::util::{image_to_egui_color_image, overlay_film_frame};
 
 type Entry = Result<ImagePoll, String>;
 
+/// Number of thumbnails generated in parallel when not configured otherwise
+pub const DEFAULT_MAX_WORKERS: usize = 2;
+
+struct ThumbnailJob {
+    uri: String,
+    path: PathBuf,
+    ctx: Context,
+}
+
 /// egui image loader for video thumbnails
 ///
-/// Uses ffmpeg to extract them
-#[derive(Default)]
+/// Uses ffmpeg to extract them. Thumbnails are generated by a fixed number of worker threads,
+/// the remaining requests wait in a queue.
 pub struct VideoThumbnailLoader {
     cache: Arc<Mutex<HashMap<String, Entry>>>,
+    job_sender: mpsc::Sender<ThumbnailJob>,
 }
 
 impl VideoThumbnailLoader {
     pub const ID: &'static str = eframe::egui::generate_loader_id!(VideoThumbnailLoader);
+
+    pub fn new(max_workers: usize) -> Self {
+        let cache: Arc<Mutex<HashMap<String, Entry>>> = Default::default();
+        let (job_sender, job_receiver) = mpsc::channel::<ThumbnailJob>();
+        let job_receiver = Arc::new(std::sync::Mutex::new(job_receiver));
+
+        for worker_idx in 0..max_workers.max(1) {
+            let cache = cache.clone();
+            let job_receiver = job_receiver.clone();
+            thread::Builder::new()
+                .name(format!(
+                    "thermal_cat::VideoThumbnailLoader::worker({worker_idx})"
+                ))
+                .spawn(move || loop {
+                    // The lock is only held while waiting for a job, not during the extraction
+                    let job = match job_receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        // The loader was dropped
+                        Err(_) => break,
+                    };
+                    log::trace!("generating thumbnail {:?}", job.uri);
+                    log::trace!("path: {:?}", job.path);
+
+                    let result = match extract_thumbnail(&job.path) {
+                        Ok(image) => Ok(ImagePoll::Ready { image }),
+                        Err(err) => Err(err.to_string()),
+                    };
+
+                    cache.lock().insert(job.uri.clone(), result);
+
+                    job.ctx.request_repaint();
+                    log::trace!("finished generating thumbnail {:?}", job.uri);
+                })
+                .expect("failed to spawn thread");
+        }
+
+        VideoThumbnailLoader { cache, job_sender }
+    }
+}
+
+impl Default for VideoThumbnailLoader {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_WORKERS)
+    }
 }
 
 const PROTOCOL: &str = "file://";
@@ -62,100 +121,18 @@ impl ImageLoader for VideoThumbnailLoader {
                 Err(err) => Err(LoadError::Loading(err)),
             }
         } else {
-            let path = path.to_owned();
             cache.insert(uri.to_owned(), Ok(ImagePoll::Pending { size: None }));
             drop(cache);
-            thread::Builder::new()
-                .name(format!("thermal_cat::VideoThumbnailLoader::load({uri:?})"))
-                .spawn({
-                    let ctx = ctx.clone();
-                    let cache = self.cache.clone();
-                    let uri = uri.to_owned();
-                    move || {
-                        // extract the thing here
-                        log::trace!("generating thumbnail {uri:?}");
-                        log::trace!("path: {path:?}");
-                        fn do_extraction(path: &Path) -> Result<Arc<ColorImage>, ffmpeg::Error> {
-                            let mut ictx = input(path)?;
-                            let input = ictx
-                                .streams()
-                                .best(Type::Video)
-                                .ok_or(ffmpeg::Error::StreamNotFound)?;
-                            let video_stream_index = input.index();
-                            let context_decoder = ffmpeg::codec::context::Context::from_parameters(
-                                input.parameters(),
-                            )?;
-                            let mut decoder = context_decoder.decoder().video()?;
-
-                            let mut scaler = ffmpeg::software::scaling::Context::get(
-                                decoder.format(),
-                                decoder.width(),
-                                decoder.height(),
-                                Pixel::RGB24,
-                                decoder.width(),
-                                decoder.height(),
-                                Flags::BILINEAR,
-                            )?;
-                            const MAX_FRAMES: usize = 10; // let's take the tenth frame at maximum.
-                            let mut frame_idx = 0;
-                            let mut rgb_frame = None;
-                            for (stream, packet) in ictx.packets() {
-                                if stream.index() == video_stream_index {
-                                    log::trace!("decoding frame {frame_idx}");
-                                    decoder.send_packet(&packet)?;
-                                    let mut decoded = Video::empty();
-                                    while decoder.receive_frame(&mut decoded).is_ok()
-                                        && frame_idx < MAX_FRAMES
-                                    {
-                                        let mut scaled_frame = Video::empty();
-                                        scaler.run(&decoded, &mut scaled_frame)?;
-                                        rgb_frame = Some(scaled_frame);
-
-                                        frame_idx += 1;
-                                    }
-                                }
-                                if frame_idx >= MAX_FRAMES {
-                                    break;
-                                }
-                            }
-                            // Ok(Arc::new(ColorImage::new([0, 0], Color32::BLACK)))
-                            rgb_frame
-                                .map(|f| {
-                                    // Arc::new(ColorImage::from_rgb(
-                                    //     [f.width() as usize, f.height() as usize],
-                                    //     f.data(0),
-                                    // ))
-                                    image::DynamicImage::ImageRgb8(
-                                        image::RgbImage::from_raw(
-                                            f.width(),
-                                            f.height(),
-                                            f.data(0).to_vec(),
-                                        )
-                                        .unwrap(),
-                                    )
-                                })
-                                .map(overlay_film_frame)
-                                .map(|img| {
-                                    Arc::new(image_to_egui_color_image(
-                                        image::DynamicImage::ImageRgb8(img),
-                                    ))
-                                })
-                                .ok_or(ffmpeg::Error::StreamNotFound)
-                        }
-
-                        let result = match do_extraction(&path) {
-                            Ok(image) => Ok(ImagePoll::Ready { image }),
-                            Err(err) => Err(err.to_string()),
-                        };
-
-                        cache.lock().insert(uri.clone(), result);
-                        drop(cache);
-
-                        ctx.request_repaint();
-                        log::trace!("finished generating thumbnail {uri:?}");
-                    }
-                })
-                .expect("failed to spawn thread");
+            let job = ThumbnailJob {
+                uri: uri.to_owned(),
+                path: path.to_owned(),
+                ctx: ctx.clone(),
+            };
+            if self.job_sender.send(job).is_err() {
+                let err = "thumbnail workers are not running".to_string();
+                self.cache.lock().insert(uri.to_owned(), Err(err.clone()));
+                return Err(LoadError::Loading(err));
+            }
             Ok(ImagePoll::Pending { size: None })
         }
     }
@@ -183,6 +160,66 @@ impl ImageLoader for VideoThumbnailLoader {
     }
 }
 
+// Decodes the first few frames of the video and returns the last one with a film frame overlay
+fn extract_thumbnail(path: &Path) -> Result<Arc<ColorImage>, ffmpeg::Error> {
+    let mut ictx = input(path)?;
+    let input = ictx
+        .streams()
+        .best(Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let video_stream_index = input.index();
+    let context_decoder = ffmpeg::codec::context::Context::from_parameters(input.parameters())?;
+    let mut decoder = context_decoder.decoder().video()?;
+
+    let mut scaler = ffmpeg::software::scaling::Context::get(
+        decoder.format(),
+        decoder.width(),
+        decoder.height(),
+        Pixel::RGB24,
+        decoder.width(),
+        decoder.height(),
+        Flags::BILINEAR,
+    )?;
+    const MAX_FRAMES: usize = 10; // let's take the tenth frame at maximum.
+    let mut frame_idx = 0;
+    let mut rgb_frame = None;
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == video_stream_index {
+            log::trace!("decoding frame {frame_idx}");
+            decoder.send_packet(&packet)?;
+            let mut decoded = Video::empty();
+            while decoder.receive_frame(&mut decoded).is_ok() && frame_idx < MAX_FRAMES {
+                let mut scaled_frame = Video::empty();
+                scaler.run(&decoded, &mut scaled_frame)?;
+                rgb_frame = Some(scaled_frame);
+
+                frame_idx += 1;
+            }
+        }
+        if frame_idx >= MAX_FRAMES {
+            break;
+        }
+    }
+    // Ok(Arc::new(ColorImage::new([0, 0], Color32::BLACK)))
+    rgb_frame
+        .map(|f| {
+            // Arc::new(ColorImage::from_rgb(
+            //     [f.width() as usize, f.height() as usize],
+            //     f.data(0),
+            // ))
+            image::DynamicImage::ImageRgb8(
+                image::RgbImage::from_raw(f.width(), f.height(), f.data(0).to_vec()).unwrap(),
+            )
+        })
+        .map(overlay_film_frame)
+        .map(|img| {
+            Arc::new(image_to_egui_color_image(image::DynamicImage::ImageRgb8(
+                img,
+            )))
+        })
+        .ok_or(ffmpeg::Error::StreamNotFound)
+}
+
 /// Remove the leading slash from the path if the target OS is Windows.
 ///
 /// This is because Windows paths are not supposed to start with a slash.